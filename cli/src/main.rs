@@ -0,0 +1,541 @@
+//! Builds and signs marketplace deploys without a node. The JSON it writes can be sent with
+//! `casper-client send-deploy` or any other tool that accepts a deploy. The `key` and `decode`
+//! subcommands locate and read the marketplace's dictionary entries.
+
+use std::{
+    fs,
+    io::{self, Read},
+    path::{Path, PathBuf},
+    process,
+};
+
+use casper_types::{
+    AsymmetricType, ContractHash, ContractPackageHash, Key, PublicKey, SecretKey, TimeDiff,
+    Timestamp, U256, U512,
+};
+use clap::{Args, Parser, Subcommand};
+use marketplace_client::{
+    sessions,
+    storage::{self, RecordKind, AUCTIONS_DICTIONARY, LISTINGS_DICTIONARY, OFFERS_DICTIONARY},
+    Deploy, DeployItem, DeployParams, Marketplace,
+};
+use serde_json::{json, Value};
+
+#[derive(Parser)]
+#[command(
+    name = "marketplace-cli",
+    about = "Signs marketplace deploys and decodes its storage"
+)]
+struct Cli {
+    #[command(flatten)]
+    deploy: DeployArgs,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Args)]
+struct DeployArgs {
+    /// PEM file with the secret key of the deploying account.
+    #[arg(long)]
+    secret_key: Option<PathBuf>,
+    #[arg(long)]
+    chain_name: Option<String>,
+    /// Standard payment in motes.
+    #[arg(long, value_parser = parse_u512)]
+    payment_amount: Option<U512>,
+    #[arg(long, default_value = "30m", value_parser = parse_ttl)]
+    ttl: TimeDiff,
+    #[arg(long, default_value_t = 1)]
+    gas_price: u64,
+    /// RFC 3339 timestamp, the current time when omitted.
+    #[arg(long, value_parser = parse_timestamp)]
+    timestamp: Option<Timestamp>,
+    /// File to write the result to, standard output when omitted.
+    #[arg(long)]
+    output: Option<PathBuf>,
+}
+
+/// Contract calls go either to one contract version or to the latest version of the package.
+#[derive(Args)]
+#[group(required = true, multiple = false)]
+struct MarketplaceArgs {
+    #[arg(long, value_parser = parse_contract_hash)]
+    marketplace: Option<ContractHash>,
+    #[arg(long, value_parser = parse_package_hash)]
+    marketplace_package: Option<ContractPackageHash>,
+}
+
+impl MarketplaceArgs {
+    fn marketplace(&self) -> Marketplace {
+        match (self.marketplace, self.marketplace_package) {
+            (Some(contract_hash), _) => Marketplace::at_contract(contract_hash),
+            (None, Some(package_hash)) => Marketplace::at_package(package_hash),
+            (None, None) => unreachable!("clap requires one of the marketplace arguments"),
+        }
+    }
+}
+
+/// Session wasm pays from the account's main purse and calls the marketplace contract directly.
+#[derive(Args)]
+struct SessionArgs {
+    /// Compiled session wasm, e.g. `payment-call.wasm`.
+    #[arg(long)]
+    session_path: PathBuf,
+    #[arg(long, value_parser = parse_contract_hash)]
+    marketplace: ContractHash,
+}
+
+#[derive(Args)]
+struct TokenArgs {
+    #[arg(long, value_parser = parse_contract_hash)]
+    collection: ContractHash,
+    #[arg(long, value_parser = parse_u256)]
+    token_id: U256,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Install the marketplace contract.
+    Install {
+        #[arg(long)]
+        wasm: PathBuf,
+    },
+    /// Add a new contract version, migrating the given legacy records.
+    Upgrade {
+        #[arg(long)]
+        wasm: PathBuf,
+        /// Legacy auction as `<collection>:<token id>`; may be repeated.
+        #[arg(long = "auction", value_parser = parse_auction_record)]
+        auctions: Vec<(ContractHash, U256)>,
+        /// Legacy offer as `<collection>:<token id>:<offerer>`; may be repeated.
+        #[arg(long = "offer", value_parser = parse_offer_record)]
+        offers: Vec<(ContractHash, U256, Key)>,
+    },
+    /// Create a listing.
+    List {
+        #[command(flatten)]
+        marketplace: MarketplaceArgs,
+        #[command(flatten)]
+        token: TokenArgs,
+        #[arg(long, value_parser = parse_u512)]
+        price: U512,
+        /// Zero lists without expiration.
+        #[arg(long, default_value_t = 0)]
+        duration_minutes: u64,
+        /// Escrow the token in the marketplace instead of relying on an approval.
+        #[arg(long)]
+        custodial: bool,
+    },
+    /// Cancel a listing.
+    Cancel {
+        #[command(flatten)]
+        marketplace: MarketplaceArgs,
+        #[command(flatten)]
+        token: TokenArgs,
+    },
+    /// Buy a listed token through `payment-call.wasm`.
+    Buy {
+        #[command(flatten)]
+        session: SessionArgs,
+        #[command(flatten)]
+        token: TokenArgs,
+        #[arg(long, value_parser = parse_u512)]
+        amount: U512,
+        #[arg(long, value_parser = parse_key)]
+        referrer: Option<Key>,
+    },
+    /// Make an offer through `make-offer-call.wasm`.
+    Offer {
+        #[command(flatten)]
+        session: SessionArgs,
+        #[command(flatten)]
+        token: TokenArgs,
+        #[arg(long, value_parser = parse_u512)]
+        amount: U512,
+    },
+    /// Accept an offer on a token the account owns.
+    Accept {
+        #[command(flatten)]
+        marketplace: MarketplaceArgs,
+        #[command(flatten)]
+        token: TokenArgs,
+        #[arg(long, value_parser = parse_key)]
+        offerer: Key,
+        #[arg(long, value_parser = parse_key)]
+        referrer: Option<Key>,
+    },
+    /// Start an auction.
+    Auction {
+        #[command(flatten)]
+        marketplace: MarketplaceArgs,
+        #[command(flatten)]
+        token: TokenArgs,
+        #[arg(long, value_parser = parse_u512)]
+        starting_price: U512,
+        #[arg(long)]
+        duration_minutes: u64,
+        #[arg(long)]
+        custodial: bool,
+    },
+    /// Bid on an auction through `bid-call.wasm`.
+    Bid {
+        #[command(flatten)]
+        session: SessionArgs,
+        #[command(flatten)]
+        token: TokenArgs,
+        #[arg(long, value_parser = parse_u512)]
+        amount: U512,
+        #[arg(long, value_parser = parse_key)]
+        referrer: Option<Key>,
+    },
+    /// End an auction, paying the seller and handing the token to the winner.
+    Settle {
+        #[command(flatten)]
+        marketplace: MarketplaceArgs,
+        #[command(flatten)]
+        token: TokenArgs,
+    },
+    /// Set the marketplace fee and its recipient.
+    SetFee {
+        #[command(flatten)]
+        marketplace: MarketplaceArgs,
+        #[arg(long)]
+        fee_basis_points: u32,
+        #[arg(long, value_parser = parse_key)]
+        fee_recipient: Key,
+    },
+    /// Change a registered collection's royalty.
+    SetRoyalty {
+        #[command(flatten)]
+        marketplace: MarketplaceArgs,
+        #[arg(long, value_parser = parse_contract_hash)]
+        collection: ContractHash,
+        /// Percentage of each resale paid to the collection's royalty recipient.
+        #[arg(long)]
+        royalties_percentage: u8,
+    },
+    /// Print the dictionary item keys of a token's records, or of an offer with `--offerer`.
+    Key {
+        #[command(flatten)]
+        token: TokenArgs,
+        #[arg(long, value_parser = parse_key)]
+        offerer: Option<Key>,
+    },
+    /// Decode a listing, offer or auction read from global state.
+    Decode {
+        /// One of listing, offer, auction or legacy-auction.
+        #[arg(long)]
+        record: RecordKind,
+        /// Hex encoded bytes, read from standard input when omitted.
+        #[arg(long)]
+        bytes: Option<String>,
+        /// The bytes are a serialized CLValue rather than a StoredValue.
+        #[arg(long)]
+        cl_value: bool,
+    },
+}
+
+fn parse_hash_addr(value: &str) -> Result<[u8; 32], String> {
+    let hex_hash = [
+        "contract-package-wasm",
+        "contract-package-",
+        "contract-",
+        "hash-",
+    ]
+    .iter()
+    .find_map(|prefix| value.strip_prefix(prefix))
+    .unwrap_or(value);
+
+    let bytes =
+        hex::decode(hex_hash).map_err(|error| format!("invalid hash {}: {}", value, error))?;
+    bytes
+        .try_into()
+        .map_err(|_| format!("invalid hash {}: expected 32 bytes", value))
+}
+
+fn parse_contract_hash(value: &str) -> Result<ContractHash, String> {
+    parse_hash_addr(value).map(ContractHash::new)
+}
+
+fn parse_package_hash(value: &str) -> Result<ContractPackageHash, String> {
+    parse_hash_addr(value).map(ContractPackageHash::new)
+}
+
+/// Accepts a formatted key (`account-hash-…`, `hash-…`) or a hex public key.
+fn parse_key(value: &str) -> Result<Key, String> {
+    if let Ok(key) = Key::from_formatted_str(value) {
+        return Ok(key);
+    }
+
+    PublicKey::from_hex(value)
+        .map(|public_key| Key::Account(public_key.to_account_hash()))
+        .map_err(|_| format!("invalid key {}", value))
+}
+
+fn parse_u256(value: &str) -> Result<U256, String> {
+    U256::from_dec_str(value).map_err(|_| format!("invalid number {}", value))
+}
+
+fn parse_u512(value: &str) -> Result<U512, String> {
+    U512::from_dec_str(value).map_err(|_| format!("invalid number {}", value))
+}
+
+fn parse_ttl(value: &str) -> Result<TimeDiff, String> {
+    value.parse().map_err(|_| format!("invalid ttl {}", value))
+}
+
+fn parse_timestamp(value: &str) -> Result<Timestamp, String> {
+    value
+        .parse()
+        .map_err(|_| format!("invalid timestamp {}", value))
+}
+
+fn parse_auction_record(value: &str) -> Result<(ContractHash, U256), String> {
+    match value.split_once(':') {
+        Some((collection, token_id)) => {
+            Ok((parse_contract_hash(collection)?, parse_u256(token_id)?))
+        }
+        None => Err(format!("expected <collection>:<token id>, got {}", value)),
+    }
+}
+
+fn parse_offer_record(value: &str) -> Result<(ContractHash, U256, Key), String> {
+    let mut parts = value.splitn(3, ':');
+    match (parts.next(), parts.next(), parts.next()) {
+        (Some(collection), Some(token_id), Some(offerer)) => Ok((
+            parse_contract_hash(collection)?,
+            parse_u256(token_id)?,
+            parse_key(offerer)?,
+        )),
+        _ => Err(format!(
+            "expected <collection>:<token id>:<offerer>, got {}",
+            value
+        )),
+    }
+}
+
+fn read_wasm(path: &Path) -> Result<Vec<u8>, String> {
+    fs::read(path).map_err(|error| format!("cannot read {}: {}", path.display(), error))
+}
+
+fn session_item(command: Command) -> Result<DeployItem, String> {
+    let item = match command {
+        Command::Install { wasm } => sessions::install(read_wasm(&wasm)?),
+        Command::Upgrade {
+            wasm,
+            auctions,
+            offers,
+        } => sessions::upgrade(read_wasm(&wasm)?, &auctions, &offers),
+        Command::List {
+            marketplace,
+            token,
+            price,
+            duration_minutes,
+            custodial,
+        } => marketplace.marketplace().create_listing(
+            token.collection,
+            token.token_id,
+            price,
+            duration_minutes,
+            custodial.then_some(true),
+        ),
+        Command::Cancel { marketplace, token } => marketplace
+            .marketplace()
+            .cancel_listing(token.collection, token.token_id),
+        Command::Buy {
+            session,
+            token,
+            amount,
+            referrer,
+        } => sessions::buy_listing(
+            read_wasm(&session.session_path)?,
+            session.marketplace,
+            token.collection,
+            token.token_id,
+            amount,
+            referrer,
+        ),
+        Command::Offer {
+            session,
+            token,
+            amount,
+        } => sessions::make_offer(
+            read_wasm(&session.session_path)?,
+            session.marketplace,
+            token.collection,
+            token.token_id,
+            amount,
+        ),
+        Command::Accept {
+            marketplace,
+            token,
+            offerer,
+            referrer,
+        } => marketplace.marketplace().accept_offer(
+            token.collection,
+            token.token_id,
+            offerer,
+            referrer,
+        ),
+        Command::Auction {
+            marketplace,
+            token,
+            starting_price,
+            duration_minutes,
+            custodial,
+        } => marketplace.marketplace().start_auction(
+            token.collection,
+            token.token_id,
+            starting_price,
+            duration_minutes,
+            custodial.then_some(true),
+        ),
+        Command::Bid {
+            session,
+            token,
+            amount,
+            referrer,
+        } => sessions::place_bid(
+            read_wasm(&session.session_path)?,
+            session.marketplace,
+            token.collection,
+            token.token_id,
+            amount,
+            referrer,
+        ),
+        Command::Settle { marketplace, token } => marketplace
+            .marketplace()
+            .end_auction(token.collection, token.token_id),
+        Command::SetFee {
+            marketplace,
+            fee_basis_points,
+            fee_recipient,
+        } => marketplace
+            .marketplace()
+            .set_marketplace_fee(fee_basis_points, fee_recipient),
+        Command::SetRoyalty {
+            marketplace,
+            collection,
+            royalties_percentage,
+        } => marketplace
+            .marketplace()
+            .set_royalties(collection, royalties_percentage),
+        Command::Key { .. } | Command::Decode { .. } => {
+            unreachable!("storage commands do not build a deploy")
+        }
+    };
+
+    Ok(item)
+}
+
+fn item_keys(token: TokenArgs, offerer: Option<Key>) -> Value {
+    match offerer {
+        Some(offerer) => {
+            let mut keys = json!({
+                OFFERS_DICTIONARY: storage::offer_key(token.collection, token.token_id, &offerer),
+            });
+            if let Some(account_hash) = offerer.into_account() {
+                keys["legacy_offer"] = json!(storage::legacy_offer_key(
+                    token.collection,
+                    token.token_id,
+                    account_hash
+                ));
+            }
+            keys
+        }
+        None => {
+            let key = storage::listing_key(token.collection, token.token_id);
+            json!({
+                LISTINGS_DICTIONARY: key,
+                AUCTIONS_DICTIONARY: key,
+            })
+        }
+    }
+}
+
+fn decode(record: RecordKind, bytes: Option<String>, cl_value: bool) -> Result<Value, String> {
+    let hex_bytes = match bytes {
+        Some(bytes) => bytes,
+        None => {
+            let mut input = String::new();
+            io::stdin()
+                .read_to_string(&mut input)
+                .map_err(|error| format!("cannot read standard input: {}", error))?;
+            input
+        }
+    };
+    let bytes = hex::decode(hex_bytes.trim()).map_err(|error| format!("invalid hex: {}", error))?;
+
+    let decoded = if cl_value {
+        casper_types::bytesrepr::deserialize(bytes)
+            .map_err(storage::DecodeError::from)
+            .and_then(|value| storage::decode_record(record, value))
+    } else {
+        storage::decode_stored_record(record, &bytes)
+    };
+
+    decoded
+        .map(|record| json!(record))
+        .map_err(|error| format!("cannot decode {}: {}", record.dictionary(), error))
+}
+
+fn sign(deploy_args: DeployArgs, command: Command) -> Result<Value, String> {
+    let DeployArgs {
+        secret_key,
+        chain_name,
+        payment_amount,
+        ttl,
+        gas_price,
+        timestamp,
+        ..
+    } = deploy_args;
+
+    let secret_key = secret_key.ok_or("--secret-key is required to sign a deploy")?;
+    let secret_key = SecretKey::from_file(&secret_key)
+        .map_err(|error| format!("cannot read secret key {}: {}", secret_key.display(), error))?;
+    let params = DeployParams {
+        chain_name: chain_name.ok_or("--chain-name is required to sign a deploy")?,
+        timestamp: timestamp.unwrap_or_else(Timestamp::now),
+        ttl,
+        gas_price,
+    };
+    let payment_amount = payment_amount.ok_or("--payment-amount is required to sign a deploy")?;
+    let payment = DeployItem::standard_payment(payment_amount);
+    let session = session_item(command)?;
+
+    let deploy = Deploy::new(params, payment, session, &secret_key)
+        .map_err(|error| format!("cannot serialize deploy: {}", error))?;
+    deploy
+        .to_json()
+        .map_err(|error| format!("cannot serialize deploy: {}", error))
+}
+
+fn run(cli: Cli) -> Result<(), String> {
+    let output = cli.deploy.output.clone();
+    let result = match cli.command {
+        Command::Key { token, offerer } => item_keys(token, offerer),
+        Command::Decode {
+            record,
+            bytes,
+            cl_value,
+        } => decode(record, bytes, cl_value)?,
+        command => sign(cli.deploy, command)?,
+    };
+    let result = serde_json::to_string_pretty(&result).unwrap();
+
+    match output {
+        Some(path) => fs::write(&path, result)
+            .map_err(|error| format!("cannot write {}: {}", path.display(), error)),
+        None => {
+            println!("{}", result);
+            Ok(())
+        }
+    }
+}
+
+fn main() {
+    if let Err(error) = run(Cli::parse()) {
+        eprintln!("error: {}", error);
+        process::exit(1);
+    }
+}
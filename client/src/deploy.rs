@@ -0,0 +1,138 @@
+use blake2::{
+    digest::{Update, VariableOutput},
+    VarBlake2b,
+};
+use casper_types::{
+    bytesrepr::{self, ToBytes},
+    crypto, PublicKey, SecretKey, Signature, TimeDiff, Timestamp,
+};
+use serde_json::{json, Value};
+
+use crate::DeployItem;
+
+const DIGEST_LENGTH: usize = 32;
+
+fn blake2b(data: &[u8]) -> [u8; DIGEST_LENGTH] {
+    let mut digest = [0u8; DIGEST_LENGTH];
+    let mut hasher = VarBlake2b::new(DIGEST_LENGTH).unwrap();
+    hasher.update(data);
+    hasher.finalize_variable(|hash| digest.copy_from_slice(hash));
+    digest
+}
+
+/// Header fields the deploying account chooses.
+#[derive(Clone, Debug)]
+pub struct DeployParams {
+    pub chain_name: String,
+    pub timestamp: Timestamp,
+    pub ttl: TimeDiff,
+    pub gas_price: u64,
+}
+
+#[derive(Clone, Debug)]
+pub struct DeployHeader {
+    pub account: PublicKey,
+    pub timestamp: Timestamp,
+    pub ttl: TimeDiff,
+    pub gas_price: u64,
+    pub body_hash: [u8; DIGEST_LENGTH],
+    pub chain_name: String,
+}
+
+impl DeployHeader {
+    /// Same layout as the node's header. The marketplace never declares dependencies, so the list
+    /// is always empty.
+    pub fn to_bytes(&self) -> Result<Vec<u8>, bytesrepr::Error> {
+        let mut buffer = Vec::new();
+        buffer.extend(self.account.to_bytes()?);
+        buffer.extend(self.timestamp.to_bytes()?);
+        buffer.extend(self.ttl.to_bytes()?);
+        buffer.extend(self.gas_price.to_bytes()?);
+        buffer.extend(self.body_hash.to_bytes()?);
+        buffer.extend(Vec::<[u8; DIGEST_LENGTH]>::new().to_bytes()?);
+        buffer.extend(self.chain_name.to_bytes()?);
+        Ok(buffer)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Approval {
+    pub signer: PublicKey,
+    pub signature: Signature,
+}
+
+/// Deploy signed by a single key, built without talking to a node.
+#[derive(Clone, Debug)]
+pub struct Deploy {
+    pub hash: [u8; DIGEST_LENGTH],
+    pub header: DeployHeader,
+    pub payment: DeployItem,
+    pub session: DeployItem,
+    pub approvals: Vec<Approval>,
+}
+
+impl Deploy {
+    pub fn new(
+        params: DeployParams,
+        payment: DeployItem,
+        session: DeployItem,
+        secret_key: &SecretKey,
+    ) -> Result<Self, bytesrepr::Error> {
+        let account = PublicKey::from(secret_key);
+
+        let mut body = payment.to_bytes()?;
+        body.extend(session.to_bytes()?);
+
+        let header = DeployHeader {
+            account: account.clone(),
+            timestamp: params.timestamp,
+            ttl: params.ttl,
+            gas_price: params.gas_price,
+            body_hash: blake2b(&body),
+            chain_name: params.chain_name,
+        };
+        let hash = blake2b(&header.to_bytes()?);
+        let signature = crypto::sign(hash, secret_key, &account);
+
+        Ok(Deploy {
+            hash,
+            header,
+            payment,
+            session,
+            approvals: vec![Approval {
+                signer: account,
+                signature,
+            }],
+        })
+    }
+
+    /// The deploy in the JSON form `account_put_deploy` and `casper-client` accept.
+    pub fn to_json(&self) -> Result<Value, bytesrepr::Error> {
+        let approvals = self
+            .approvals
+            .iter()
+            .map(|approval| {
+                Ok(json!({
+                    "signer": hex::encode(approval.signer.to_bytes()?),
+                    "signature": hex::encode(approval.signature.to_bytes()?),
+                }))
+            })
+            .collect::<Result<Vec<_>, bytesrepr::Error>>()?;
+
+        Ok(json!({
+            "hash": hex::encode(self.hash),
+            "header": {
+                "account": hex::encode(self.header.account.to_bytes()?),
+                "timestamp": self.header.timestamp.to_string(),
+                "ttl": self.header.ttl.to_string(),
+                "gas_price": self.header.gas_price,
+                "body_hash": hex::encode(self.header.body_hash),
+                "dependencies": [],
+                "chain_name": self.header.chain_name,
+            },
+            "payment": self.payment.to_json(),
+            "session": self.session.to_json(),
+            "approvals": approvals,
+        }))
+    }
+}
@@ -0,0 +1,211 @@
+use casper_types::{
+    bytesrepr::{self, Bytes, FromBytes, ToBytes, U8_SERIALIZED_LENGTH},
+    runtime_args, ContractHash, ContractPackageHash, ContractVersion, RuntimeArgs, U512,
+};
+use serde_json::{json, Value};
+
+// Same tags as the node's `ExecutableDeployItem`. The variants the marketplace never needs (stored
+// contracts by name and native transfers) are left out.
+const MODULE_BYTES_TAG: u8 = 0;
+const STORED_CONTRACT_BY_HASH_TAG: u8 = 1;
+const STORED_VERSIONED_CONTRACT_BY_HASH_TAG: u8 = 3;
+
+/// Session or payment code of a deploy.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DeployItem {
+    /// Wasm executed in the context of the deploying account.
+    ModuleBytes {
+        module_bytes: Bytes,
+        args: RuntimeArgs,
+    },
+    /// Entry point of one specific contract version.
+    StoredContractByHash {
+        hash: ContractHash,
+        entry_point: String,
+        args: RuntimeArgs,
+    },
+    /// Entry point of a contract package, at `version` or at the latest version when `None`.
+    StoredVersionedContractByHash {
+        hash: ContractPackageHash,
+        version: Option<ContractVersion>,
+        entry_point: String,
+        args: RuntimeArgs,
+    },
+}
+
+impl DeployItem {
+    pub fn module_bytes(module_bytes: Vec<u8>, args: RuntimeArgs) -> Self {
+        DeployItem::ModuleBytes {
+            module_bytes: module_bytes.into(),
+            args,
+        }
+    }
+
+    /// Payment through the system's standard payment, which empty module bytes stand for.
+    pub fn standard_payment(amount: U512) -> Self {
+        DeployItem::module_bytes(Vec::new(), runtime_args! { "amount" => amount })
+    }
+
+    pub fn args(&self) -> &RuntimeArgs {
+        match self {
+            DeployItem::ModuleBytes { args, .. }
+            | DeployItem::StoredContractByHash { args, .. }
+            | DeployItem::StoredVersionedContractByHash { args, .. } => args,
+        }
+    }
+
+    /// Entry point the item calls, `None` for wasm which always runs `call`.
+    pub fn entry_point(&self) -> Option<&str> {
+        match self {
+            DeployItem::ModuleBytes { .. } => None,
+            DeployItem::StoredContractByHash { entry_point, .. }
+            | DeployItem::StoredVersionedContractByHash { entry_point, .. } => Some(entry_point),
+        }
+    }
+
+    /// The item as it appears in a deploy's JSON, with hashes and module bytes hex encoded.
+    pub fn to_json(&self) -> Value {
+        let args = serde_json::to_value(self.args()).expect("runtime args should serialize");
+
+        match self {
+            DeployItem::ModuleBytes { module_bytes, .. } => json!({
+                "ModuleBytes": {
+                    "module_bytes": hex::encode(module_bytes.inner_bytes()),
+                    "args": args,
+                }
+            }),
+            DeployItem::StoredContractByHash {
+                hash, entry_point, ..
+            } => json!({
+                "StoredContractByHash": {
+                    "hash": hex::encode(hash.value()),
+                    "entry_point": entry_point,
+                    "args": args,
+                }
+            }),
+            DeployItem::StoredVersionedContractByHash {
+                hash,
+                version,
+                entry_point,
+                ..
+            } => json!({
+                "StoredVersionedContractByHash": {
+                    "hash": hex::encode(hash.value()),
+                    "version": version,
+                    "entry_point": entry_point,
+                    "args": args,
+                }
+            }),
+        }
+    }
+}
+
+impl ToBytes for DeployItem {
+    fn to_bytes(&self) -> Result<Vec<u8>, bytesrepr::Error> {
+        let mut buffer = bytesrepr::allocate_buffer(self)?;
+
+        match self {
+            DeployItem::ModuleBytes { module_bytes, args } => {
+                buffer.push(MODULE_BYTES_TAG);
+                buffer.extend(module_bytes.to_bytes()?);
+                buffer.extend(args.to_bytes()?);
+            }
+            DeployItem::StoredContractByHash {
+                hash,
+                entry_point,
+                args,
+            } => {
+                buffer.push(STORED_CONTRACT_BY_HASH_TAG);
+                buffer.extend(hash.to_bytes()?);
+                buffer.extend(entry_point.to_bytes()?);
+                buffer.extend(args.to_bytes()?);
+            }
+            DeployItem::StoredVersionedContractByHash {
+                hash,
+                version,
+                entry_point,
+                args,
+            } => {
+                buffer.push(STORED_VERSIONED_CONTRACT_BY_HASH_TAG);
+                buffer.extend(hash.to_bytes()?);
+                buffer.extend(version.to_bytes()?);
+                buffer.extend(entry_point.to_bytes()?);
+                buffer.extend(args.to_bytes()?);
+            }
+        }
+
+        Ok(buffer)
+    }
+
+    fn serialized_length(&self) -> usize {
+        U8_SERIALIZED_LENGTH
+            + match self {
+                DeployItem::ModuleBytes { module_bytes, args } => {
+                    module_bytes.serialized_length() + args.serialized_length()
+                }
+                DeployItem::StoredContractByHash {
+                    hash,
+                    entry_point,
+                    args,
+                } => {
+                    hash.serialized_length()
+                        + entry_point.serialized_length()
+                        + args.serialized_length()
+                }
+                DeployItem::StoredVersionedContractByHash {
+                    hash,
+                    version,
+                    entry_point,
+                    args,
+                } => {
+                    hash.serialized_length()
+                        + version.serialized_length()
+                        + entry_point.serialized_length()
+                        + args.serialized_length()
+                }
+            }
+    }
+}
+
+impl FromBytes for DeployItem {
+    fn from_bytes(bytes: &[u8]) -> Result<(Self, &[u8]), bytesrepr::Error> {
+        let (tag, remainder) = u8::from_bytes(bytes)?;
+
+        match tag {
+            MODULE_BYTES_TAG => {
+                let (module_bytes, remainder) = Bytes::from_bytes(remainder)?;
+                let (args, remainder) = RuntimeArgs::from_bytes(remainder)?;
+                Ok((DeployItem::ModuleBytes { module_bytes, args }, remainder))
+            }
+            STORED_CONTRACT_BY_HASH_TAG => {
+                let (hash, remainder) = ContractHash::from_bytes(remainder)?;
+                let (entry_point, remainder) = String::from_bytes(remainder)?;
+                let (args, remainder) = RuntimeArgs::from_bytes(remainder)?;
+                Ok((
+                    DeployItem::StoredContractByHash {
+                        hash,
+                        entry_point,
+                        args,
+                    },
+                    remainder,
+                ))
+            }
+            STORED_VERSIONED_CONTRACT_BY_HASH_TAG => {
+                let (hash, remainder) = ContractPackageHash::from_bytes(remainder)?;
+                let (version, remainder) = Option::<ContractVersion>::from_bytes(remainder)?;
+                let (entry_point, remainder) = String::from_bytes(remainder)?;
+                let (args, remainder) = RuntimeArgs::from_bytes(remainder)?;
+                Ok((
+                    DeployItem::StoredVersionedContractByHash {
+                        hash,
+                        version,
+                        entry_point,
+                        args,
+                    },
+                    remainder,
+                ))
+            }
+            _ => Err(bytesrepr::Error::Formatting),
+        }
+    }
+}
@@ -0,0 +1,18 @@
+//! Host-side helpers for talking to the marketplace contract.
+//!
+//! Every entry point and session wasm has a typed builder that produces a [`DeployItem`]. Deploy
+//! items serialize like the node's `ExecutableDeployItem`, so the same value can be turned into an
+//! engine test request or signed into a [`Deploy`] offline. The [`types`] module mirrors what the
+//! contract keeps in its dictionaries, and [`storage`] derives their item keys and decodes raw
+//! global state.
+
+pub mod deploy;
+pub mod deploy_item;
+pub mod marketplace;
+pub mod sessions;
+pub mod storage;
+pub mod types;
+
+pub use deploy::{Deploy, DeployParams};
+pub use deploy_item::DeployItem;
+pub use marketplace::Marketplace;
@@ -0,0 +1,853 @@
+use std::collections::BTreeMap;
+
+use casper_types::{
+    bytesrepr::ToBytes, runtime_args, CLTyped, ContractHash, ContractPackageHash, Key, RuntimeArgs,
+    URef, U256, U512,
+};
+
+use crate::DeployItem;
+
+// One builder per entry point of the marketplace contract, taking the arguments the entry point
+// declares. Collections are sent as `Key::Hash`, and arguments an entry point may do without are
+// `Option`s that are left out of the deploy when `None`.
+
+/// Where deploys built by a [`Marketplace`] are sent.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Target {
+    /// One version of the contract.
+    Contract(ContractHash),
+    /// The latest version of the contract package, which keeps working across upgrades.
+    Package(ContractPackageHash),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Marketplace {
+    target: Target,
+}
+
+fn collection_key(token_contract: ContractHash) -> Key {
+    Key::Hash(token_contract.value())
+}
+
+fn token_list(tokens: &[(ContractHash, U256)]) -> Vec<(Key, U256)> {
+    tokens
+        .iter()
+        .map(|(token_contract, token_id)| (collection_key(*token_contract), *token_id))
+        .collect()
+}
+
+fn offer_list(offers: &[(ContractHash, U256, Key)]) -> Vec<(Key, U256, Key)> {
+    offers
+        .iter()
+        .map(|(token_contract, token_id, offerer)| {
+            (collection_key(*token_contract), *token_id, *offerer)
+        })
+        .collect()
+}
+
+pub(crate) fn insert_optional<T: CLTyped + ToBytes>(
+    args: &mut RuntimeArgs,
+    name: &str,
+    value: Option<T>,
+) {
+    if let Some(value) = value {
+        args.insert(name, value)
+            .expect("marketplace arguments should serialize");
+    }
+}
+
+impl Marketplace {
+    pub fn at_contract(contract_hash: ContractHash) -> Self {
+        Marketplace {
+            target: Target::Contract(contract_hash),
+        }
+    }
+
+    pub fn at_package(contract_package_hash: ContractPackageHash) -> Self {
+        Marketplace {
+            target: Target::Package(contract_package_hash),
+        }
+    }
+
+    pub fn target(&self) -> Target {
+        self.target
+    }
+
+    /// Calls any entry point, for the ones added after this client was built.
+    pub fn call(&self, entry_point: &str, args: RuntimeArgs) -> DeployItem {
+        match self.target {
+            Target::Contract(hash) => DeployItem::StoredContractByHash {
+                hash,
+                entry_point: entry_point.to_string(),
+                args,
+            },
+            Target::Package(hash) => DeployItem::StoredVersionedContractByHash {
+                hash,
+                version: None,
+                entry_point: entry_point.to_string(),
+                args,
+            },
+        }
+    }
+
+    // Listings
+    pub fn create_listing(
+        &self,
+        token_contract: ContractHash,
+        token_id: U256,
+        price: U512,
+        duration_minutes: u64,
+        custodial: Option<bool>,
+    ) -> DeployItem {
+        let mut args = runtime_args! {
+            "contract_hash" => collection_key(token_contract),
+            "token_id" => token_id,
+            "price" => price,
+            "duration_minutes" => duration_minutes,
+        };
+        insert_optional(&mut args, "custodial", custodial);
+
+        self.call("create_listing", args)
+    }
+
+    pub fn update_listing(
+        &self,
+        token_contract: ContractHash,
+        token_id: U256,
+        price: U512,
+        duration_minutes: u64,
+    ) -> DeployItem {
+        self.call(
+            "update_listing",
+            runtime_args! {
+                "contract_hash" => collection_key(token_contract),
+                "token_id" => token_id,
+                "price" => price,
+                "duration_minutes" => duration_minutes,
+            },
+        )
+    }
+
+    pub fn cancel_listing(&self, token_contract: ContractHash, token_id: U256) -> DeployItem {
+        self.call(
+            "cancel_listing",
+            runtime_args! {
+                "contract_hash" => collection_key(token_contract),
+                "token_id" => token_id,
+            },
+        )
+    }
+
+    pub fn remove_expired_listing(
+        &self,
+        token_contract: ContractHash,
+        token_id: U256,
+    ) -> DeployItem {
+        self.call(
+            "remove_expired_listing",
+            runtime_args! {
+                "contract_hash" => collection_key(token_contract),
+                "token_id" => token_id,
+            },
+        )
+    }
+
+    pub fn get_collection_listings(
+        &self,
+        token_contract: ContractHash,
+        offset: u64,
+        limit: u64,
+    ) -> DeployItem {
+        self.call(
+            "get_collection_listings",
+            runtime_args! {
+                "contract_hash" => collection_key(token_contract),
+                "offset" => offset,
+                "limit" => limit,
+            },
+        )
+    }
+
+    pub fn get_seller_listings(&self, seller: Key, offset: u64, limit: u64) -> DeployItem {
+        self.call(
+            "get_seller_listings",
+            runtime_args! {
+                "seller" => seller,
+                "offset" => offset,
+                "limit" => limit,
+            },
+        )
+    }
+
+    pub fn buy_listing(
+        &self,
+        token_contract: ContractHash,
+        token_id: U256,
+        buy_purse: URef,
+        referrer: Option<Key>,
+    ) -> DeployItem {
+        let mut args = runtime_args! {
+            "contract_hash" => collection_key(token_contract),
+            "token_id" => token_id,
+            "buy_purse" => buy_purse,
+        };
+        insert_optional(&mut args, "referrer", referrer);
+
+        self.call("buy_listing", args)
+    }
+
+    pub fn buy_listings_batch(
+        &self,
+        tokens: &[(ContractHash, U256)],
+        max_spend: U512,
+        skip_unavailable: bool,
+        buy_purse: URef,
+        referrer: Option<Key>,
+    ) -> DeployItem {
+        let mut args = runtime_args! {
+            "tokens" => token_list(tokens),
+            "max_spend" => max_spend,
+            "skip_unavailable" => skip_unavailable,
+            "buy_purse" => buy_purse,
+        };
+        insert_optional(&mut args, "referrer", referrer);
+
+        self.call("buy_listings_batch", args)
+    }
+
+    pub fn get_listing(&self, token_contract: ContractHash, token_id: U256) -> DeployItem {
+        self.call(
+            "get_listing",
+            runtime_args! {
+                "contract_hash" => collection_key(token_contract),
+                "token_id" => token_id,
+            },
+        )
+    }
+
+    pub fn create_lazy_listing(
+        &self,
+        token_contract: ContractHash,
+        token_id: U256,
+        price: U512,
+        duration_minutes: u64,
+        token_meta: BTreeMap<String, String>,
+    ) -> DeployItem {
+        self.call(
+            "create_lazy_listing",
+            runtime_args! {
+                "contract_hash" => collection_key(token_contract),
+                "token_id" => token_id,
+                "price" => price,
+                "duration_minutes" => duration_minutes,
+                "token_meta" => token_meta,
+            },
+        )
+    }
+
+    pub fn get_lazy_listing(&self, token_contract: ContractHash, token_id: U256) -> DeployItem {
+        self.call(
+            "get_lazy_listing",
+            runtime_args! {
+                "contract_hash" => collection_key(token_contract),
+                "token_id" => token_id,
+            },
+        )
+    }
+
+    // Offers
+    pub fn make_offer(
+        &self,
+        token_contract: ContractHash,
+        token_id: U256,
+        buy_purse: URef,
+    ) -> DeployItem {
+        self.call(
+            "make_offer",
+            runtime_args! {
+                "contract_hash" => collection_key(token_contract),
+                "token_id" => token_id,
+                "buy_purse" => buy_purse,
+            },
+        )
+    }
+
+    pub fn accept_offer(
+        &self,
+        token_contract: ContractHash,
+        token_id: U256,
+        offerer: Key,
+        referrer: Option<Key>,
+    ) -> DeployItem {
+        let mut args = runtime_args! {
+            "contract_hash" => collection_key(token_contract),
+            "token_id" => token_id,
+            "offerer" => offerer,
+        };
+        insert_optional(&mut args, "referrer", referrer);
+
+        self.call("accept_offer", args)
+    }
+
+    pub fn cancel_offer(&self, token_contract: ContractHash, token_id: U256) -> DeployItem {
+        self.call(
+            "cancel_offer",
+            runtime_args! {
+                "contract_hash" => collection_key(token_contract),
+                "token_id" => token_id,
+            },
+        )
+    }
+
+    pub fn get_offer(
+        &self,
+        token_contract: ContractHash,
+        token_id: U256,
+        offerer: Key,
+    ) -> DeployItem {
+        self.call(
+            "get_offer",
+            runtime_args! {
+                "contract_hash" => collection_key(token_contract),
+                "token_id" => token_id,
+                "offerer" => offerer,
+            },
+        )
+    }
+
+    // Auctions
+    pub fn start_auction(
+        &self,
+        token_contract: ContractHash,
+        token_id: U256,
+        price: U512,
+        duration_minutes: u64,
+        custodial: Option<bool>,
+    ) -> DeployItem {
+        let mut args = runtime_args! {
+            "contract_hash" => collection_key(token_contract),
+            "token_id" => token_id,
+            "price" => price,
+            "duration_minutes" => duration_minutes,
+        };
+        insert_optional(&mut args, "custodial", custodial);
+
+        self.call("start_auction", args)
+    }
+
+    pub fn place_bid(
+        &self,
+        token_contract: ContractHash,
+        token_id: U256,
+        buy_purse: URef,
+        referrer: Option<Key>,
+    ) -> DeployItem {
+        let mut args = runtime_args! {
+            "contract_hash" => collection_key(token_contract),
+            "token_id" => token_id,
+            "buy_purse" => buy_purse,
+        };
+        insert_optional(&mut args, "referrer", referrer);
+
+        self.call("place_bid", args)
+    }
+
+    pub fn end_auction(&self, token_contract: ContractHash, token_id: U256) -> DeployItem {
+        self.call(
+            "end_auction",
+            runtime_args! {
+                "contract_hash" => collection_key(token_contract),
+                "token_id" => token_id,
+            },
+        )
+    }
+
+    pub fn get_auction(&self, token_contract: ContractHash, token_id: U256) -> DeployItem {
+        self.call(
+            "get_auction",
+            runtime_args! {
+                "contract_hash" => collection_key(token_contract),
+                "token_id" => token_id,
+            },
+        )
+    }
+
+    // Token state and proceeds
+    pub fn get_token_state(&self, token_contract: ContractHash, token_id: U256) -> DeployItem {
+        self.call(
+            "get_token_state",
+            runtime_args! {
+                "contract_hash" => collection_key(token_contract),
+                "token_id" => token_id,
+            },
+        )
+    }
+
+    pub fn withdraw(&self, target_purse: Option<URef>) -> DeployItem {
+        let mut args = RuntimeArgs::new();
+        insert_optional(&mut args, "target_purse", target_purse);
+
+        self.call("withdraw", args)
+    }
+
+    pub fn get_balance(&self, owner: Key) -> DeployItem {
+        self.call(
+            "get_balance",
+            runtime_args! {
+                "owner" => owner,
+            },
+        )
+    }
+
+    pub fn get_referral_earnings(&self, referrer: Key) -> DeployItem {
+        self.call(
+            "get_referral_earnings",
+            runtime_args! {
+                "referrer" => referrer,
+            },
+        )
+    }
+
+    // Swaps
+    pub fn propose_swap(
+        &self,
+        counterparty: Key,
+        offered_tokens: &[(ContractHash, U256)],
+        requested_tokens: &[(ContractHash, U256)],
+        duration_minutes: u64,
+        buy_purse: Option<URef>,
+    ) -> DeployItem {
+        let mut args = runtime_args! {
+            "counterparty" => counterparty,
+            "offered_tokens" => token_list(offered_tokens),
+            "requested_tokens" => token_list(requested_tokens),
+            "duration_minutes" => duration_minutes,
+        };
+        insert_optional(&mut args, "buy_purse", buy_purse);
+
+        self.call("propose_swap", args)
+    }
+
+    pub fn accept_swap(&self, swap_id: u64) -> DeployItem {
+        self.call(
+            "accept_swap",
+            runtime_args! {
+                "swap_id" => swap_id,
+            },
+        )
+    }
+
+    pub fn cancel_swap(&self, swap_id: u64) -> DeployItem {
+        self.call(
+            "cancel_swap",
+            runtime_args! {
+                "swap_id" => swap_id,
+            },
+        )
+    }
+
+    pub fn get_swap(&self, swap_id: u64) -> DeployItem {
+        self.call(
+            "get_swap",
+            runtime_args! {
+                "swap_id" => swap_id,
+            },
+        )
+    }
+
+    // Rentals
+    pub fn list_for_rent(
+        &self,
+        token_contract: ContractHash,
+        token_id: U256,
+        price_per_day: U512,
+        max_days: u64,
+    ) -> DeployItem {
+        self.call(
+            "list_for_rent",
+            runtime_args! {
+                "contract_hash" => collection_key(token_contract),
+                "token_id" => token_id,
+                "price_per_day" => price_per_day,
+                "max_days" => max_days,
+            },
+        )
+    }
+
+    pub fn rent(
+        &self,
+        token_contract: ContractHash,
+        token_id: U256,
+        days: u64,
+        buy_purse: URef,
+    ) -> DeployItem {
+        self.call(
+            "rent",
+            runtime_args! {
+                "contract_hash" => collection_key(token_contract),
+                "token_id" => token_id,
+                "days" => days,
+                "buy_purse" => buy_purse,
+            },
+        )
+    }
+
+    pub fn end_rental(&self, token_contract: ContractHash, token_id: U256) -> DeployItem {
+        self.call(
+            "end_rental",
+            runtime_args! {
+                "contract_hash" => collection_key(token_contract),
+                "token_id" => token_id,
+            },
+        )
+    }
+
+    pub fn get_rental(&self, token_contract: ContractHash, token_id: U256) -> DeployItem {
+        self.call(
+            "get_rental",
+            runtime_args! {
+                "contract_hash" => collection_key(token_contract),
+                "token_id" => token_id,
+            },
+        )
+    }
+
+    pub fn get_user(&self, token_contract: ContractHash, token_id: U256) -> DeployItem {
+        self.call(
+            "get_user",
+            runtime_args! {
+                "contract_hash" => collection_key(token_contract),
+                "token_id" => token_id,
+            },
+        )
+    }
+
+    // Drops
+    pub fn create_drop(
+        &self,
+        token_contract: ContractHash,
+        token_meta: BTreeMap<String, String>,
+        start_token_id: U256,
+        inventory: Vec<U256>,
+        max_supply: u64,
+    ) -> DeployItem {
+        self.call(
+            "create_drop",
+            runtime_args! {
+                "contract_hash" => collection_key(token_contract),
+                "token_meta" => token_meta,
+                "start_token_id" => start_token_id,
+                "inventory" => inventory,
+                "max_supply" => max_supply,
+            },
+        )
+    }
+
+    pub fn add_drop_phase(
+        &self,
+        token_contract: ContractHash,
+        allowlist_only: bool,
+        price: U512,
+        start_time: u64,
+        end_time: u64,
+        wallet_limit: u64,
+    ) -> DeployItem {
+        self.call(
+            "add_drop_phase",
+            runtime_args! {
+                "contract_hash" => collection_key(token_contract),
+                "allowlist_only" => allowlist_only,
+                "price" => price,
+                "start_time" => start_time,
+                "end_time" => end_time,
+                "wallet_limit" => wallet_limit,
+            },
+        )
+    }
+
+    pub fn set_drop_allowlist(
+        &self,
+        token_contract: ContractHash,
+        accounts: Vec<Key>,
+        allowed: bool,
+    ) -> DeployItem {
+        self.call(
+            "set_drop_allowlist",
+            runtime_args! {
+                "contract_hash" => collection_key(token_contract),
+                "accounts" => accounts,
+                "allowed" => allowed,
+            },
+        )
+    }
+
+    pub fn buy_drop(
+        &self,
+        token_contract: ContractHash,
+        quantity: u64,
+        buy_purse: URef,
+    ) -> DeployItem {
+        self.call(
+            "buy_drop",
+            runtime_args! {
+                "contract_hash" => collection_key(token_contract),
+                "quantity" => quantity,
+                "buy_purse" => buy_purse,
+            },
+        )
+    }
+
+    pub fn get_drop(&self, token_contract: ContractHash) -> DeployItem {
+        self.call(
+            "get_drop",
+            runtime_args! {
+                "contract_hash" => collection_key(token_contract),
+            },
+        )
+    }
+
+    // Sales and statistics
+    pub fn get_sale(&self, sale_id: u64) -> DeployItem {
+        self.call(
+            "get_sale",
+            runtime_args! {
+                "sale_id" => sale_id,
+            },
+        )
+    }
+
+    pub fn get_sale_count(&self) -> DeployItem {
+        self.call("get_sale_count", RuntimeArgs::new())
+    }
+
+    pub fn get_last_sale_price(&self, token_contract: ContractHash, token_id: U256) -> DeployItem {
+        self.call(
+            "get_last_sale_price",
+            runtime_args! {
+                "contract_hash" => collection_key(token_contract),
+                "token_id" => token_id,
+            },
+        )
+    }
+
+    pub fn get_collection_volume(&self, token_contract: ContractHash) -> DeployItem {
+        self.call(
+            "get_collection_volume",
+            runtime_args! {
+                "contract_hash" => collection_key(token_contract),
+            },
+        )
+    }
+
+    pub fn get_collection_stats(&self, token_contract: ContractHash) -> DeployItem {
+        self.call(
+            "get_collection_stats",
+            runtime_args! {
+                "contract_hash" => collection_key(token_contract),
+            },
+        )
+    }
+
+    pub fn get_daily_volume(&self, token_contract: ContractHash, day: u64) -> DeployItem {
+        self.call(
+            "get_daily_volume",
+            runtime_args! {
+                "contract_hash" => collection_key(token_contract),
+                "day" => day,
+            },
+        )
+    }
+
+    pub fn get_fee_quote(
+        &self,
+        token_contract: ContractHash,
+        price: U512,
+        seller: Key,
+        buyer: Key,
+    ) -> DeployItem {
+        self.call(
+            "get_fee_quote",
+            runtime_args! {
+                "contract_hash" => collection_key(token_contract),
+                "price" => price,
+                "seller" => seller,
+                "buyer" => buyer,
+            },
+        )
+    }
+
+    // Administration
+    pub fn set_royalties(
+        &self,
+        token_contract: ContractHash,
+        royalties_percentage: u8,
+    ) -> DeployItem {
+        self.call(
+            "set_royalties",
+            runtime_args! {
+                "contract_hash" => collection_key(token_contract),
+                "royalties_percentage" => royalties_percentage,
+            },
+        )
+    }
+
+    pub fn set_curator(&self, curator: Key) -> DeployItem {
+        self.call(
+            "set_curator",
+            runtime_args! {
+                "curator" => curator,
+            },
+        )
+    }
+
+    pub fn set_allowlist_mode(&self, enabled: bool) -> DeployItem {
+        self.call(
+            "set_allowlist_mode",
+            runtime_args! {
+                "enabled" => enabled,
+            },
+        )
+    }
+
+    pub fn register_collection(
+        &self,
+        token_contract: ContractHash,
+        name: &str,
+        verified: bool,
+        royalty_recipient: Option<Key>,
+        royalties_percentage: u8,
+    ) -> DeployItem {
+        self.call(
+            "register_collection",
+            runtime_args! {
+                "contract_hash" => collection_key(token_contract),
+                "name" => name.to_string(),
+                "verified" => verified,
+                "royalty_recipient" => royalty_recipient,
+                "royalties_percentage" => royalties_percentage,
+            },
+        )
+    }
+
+    pub fn remove_collection(&self, token_contract: ContractHash) -> DeployItem {
+        self.call(
+            "remove_collection",
+            runtime_args! {
+                "contract_hash" => collection_key(token_contract),
+            },
+        )
+    }
+
+    pub fn get_collection(&self, token_contract: ContractHash) -> DeployItem {
+        self.call(
+            "get_collection",
+            runtime_args! {
+                "contract_hash" => collection_key(token_contract),
+            },
+        )
+    }
+
+    pub fn set_blocked(&self, account: Key, blocked: bool) -> DeployItem {
+        self.call(
+            "set_blocked",
+            runtime_args! {
+                "account" => account,
+                "blocked" => blocked,
+            },
+        )
+    }
+
+    pub fn get_blocked(&self, account: Key) -> DeployItem {
+        self.call(
+            "get_blocked",
+            runtime_args! {
+                "account" => account,
+            },
+        )
+    }
+
+    pub fn set_collection_creator(
+        &self,
+        token_contract: ContractHash,
+        creator: Option<Key>,
+    ) -> DeployItem {
+        self.call(
+            "set_collection_creator",
+            runtime_args! {
+                "contract_hash" => collection_key(token_contract),
+                "creator" => creator,
+            },
+        )
+    }
+
+    pub fn set_collection_package(
+        &self,
+        package_hash: ContractPackageHash,
+        token_contract: ContractHash,
+    ) -> DeployItem {
+        self.call(
+            "set_collection_package",
+            runtime_args! {
+                "package_hash" => package_hash,
+                "contract_hash" => collection_key(token_contract),
+            },
+        )
+    }
+
+    pub fn set_marketplace_fee(&self, fee_basis_points: u32, fee_recipient: Key) -> DeployItem {
+        self.call(
+            "set_marketplace_fee",
+            runtime_args! {
+                "fee_basis_points" => fee_basis_points,
+                "fee_recipient" => fee_recipient,
+            },
+        )
+    }
+
+    pub fn set_referral_share(&self, referral_share_basis_points: u32) -> DeployItem {
+        self.call(
+            "set_referral_share",
+            runtime_args! {
+                "referral_share_basis_points" => referral_share_basis_points,
+            },
+        )
+    }
+
+    pub fn set_collection_fee(
+        &self,
+        token_contract: ContractHash,
+        fee_basis_points: Option<u32>,
+    ) -> DeployItem {
+        self.call(
+            "set_collection_fee",
+            runtime_args! {
+                "contract_hash" => collection_key(token_contract),
+                "fee_basis_points" => fee_basis_points,
+            },
+        )
+    }
+
+    pub fn set_fee_exempt(&self, account: Key, exempt: bool) -> DeployItem {
+        self.call(
+            "set_fee_exempt",
+            runtime_args! {
+                "account" => account,
+                "exempt" => exempt,
+            },
+        )
+    }
+
+    pub fn migrate_storage(
+        &self,
+        auctions: &[(ContractHash, U256)],
+        offers: &[(ContractHash, U256, Key)],
+    ) -> DeployItem {
+        self.call(
+            "migrate_storage",
+            runtime_args! {
+                "auctions" => token_list(auctions),
+                "offers" => offer_list(offers),
+            },
+        )
+    }
+}
@@ -0,0 +1,182 @@
+use casper_types::{runtime_args, ContractHash, Key, RuntimeArgs, U256, U512};
+
+use crate::{
+    marketplace::{insert_optional, Marketplace},
+    DeployItem,
+};
+
+// Builders for the wasm that runs in the deploying account: the installer, and the session
+// contracts that fund a purse from the account's main purse before calling an entry point which
+// takes payment. Each takes the compiled module, which it does not check.
+
+fn token_string_list(tokens: &[(ContractHash, U256)]) -> Vec<(String, U256)> {
+    tokens
+        .iter()
+        .map(|(token_contract, token_id)| (token_contract.to_formatted_string(), *token_id))
+        .collect()
+}
+
+/// Installs `contract.wasm` as a new contract package.
+pub fn install(module_bytes: Vec<u8>) -> DeployItem {
+    DeployItem::module_bytes(module_bytes, RuntimeArgs::new())
+}
+
+/// Runs `contract.wasm` from the account that installed the package, adding a new version. The
+/// listed auctions and offers are migrated from the legacy storage layout as part of the upgrade.
+pub fn upgrade(
+    module_bytes: Vec<u8>,
+    auctions: &[(ContractHash, U256)],
+    offers: &[(ContractHash, U256, Key)],
+) -> DeployItem {
+    let migrate_args = Marketplace::at_contract(ContractHash::default())
+        .migrate_storage(auctions, offers)
+        .args()
+        .clone();
+
+    DeployItem::module_bytes(module_bytes, migrate_args)
+}
+
+/// `payment-call.wasm`
+pub fn buy_listing(
+    module_bytes: Vec<u8>,
+    marketplace_hash: ContractHash,
+    token_contract: ContractHash,
+    token_id: U256,
+    amount: U512,
+    referrer: Option<Key>,
+) -> DeployItem {
+    let mut args = runtime_args! {
+        "token_id" => token_id,
+        "buy_contract_hash" => token_contract.to_formatted_string(),
+        "marketplace_hash" => marketplace_hash.to_formatted_string(),
+        "amount" => amount,
+    };
+    insert_optional(&mut args, "referrer", referrer);
+
+    DeployItem::module_bytes(module_bytes, args)
+}
+
+/// `batch-payment-call.wasm`. `amount` is deposited for the purchases, `max_spend` caps what the
+/// marketplace takes from it.
+pub fn buy_listings_batch(
+    module_bytes: Vec<u8>,
+    marketplace_hash: ContractHash,
+    tokens: &[(ContractHash, U256)],
+    max_spend: U512,
+    skip_unavailable: bool,
+    amount: U512,
+    referrer: Option<Key>,
+) -> DeployItem {
+    let mut args = runtime_args! {
+        "tokens" => token_string_list(tokens),
+        "marketplace_hash" => marketplace_hash.to_formatted_string(),
+        "max_spend" => max_spend,
+        "skip_unavailable" => skip_unavailable,
+        "amount" => amount,
+    };
+    insert_optional(&mut args, "referrer", referrer);
+
+    DeployItem::module_bytes(module_bytes, args)
+}
+
+/// `make-offer-call.wasm`
+pub fn make_offer(
+    module_bytes: Vec<u8>,
+    marketplace_hash: ContractHash,
+    token_contract: ContractHash,
+    token_id: U256,
+    amount: U512,
+) -> DeployItem {
+    DeployItem::module_bytes(
+        module_bytes,
+        runtime_args! {
+            "token_id" => token_id,
+            "offer_contract_hash" => token_contract.to_formatted_string(),
+            "marketplace_hash" => marketplace_hash.to_formatted_string(),
+            "amount" => amount,
+        },
+    )
+}
+
+/// `bid-call.wasm`
+pub fn place_bid(
+    module_bytes: Vec<u8>,
+    marketplace_hash: ContractHash,
+    token_contract: ContractHash,
+    token_id: U256,
+    amount: U512,
+    referrer: Option<Key>,
+) -> DeployItem {
+    let mut args = runtime_args! {
+        "token_id" => token_id,
+        "bid_contract_hash" => token_contract.to_formatted_string(),
+        "marketplace_hash" => marketplace_hash.to_formatted_string(),
+        "amount" => amount,
+    };
+    insert_optional(&mut args, "referrer", referrer);
+
+    DeployItem::module_bytes(module_bytes, args)
+}
+
+/// `swap-call.wasm`. `amount` is the CSPR sweetener, which may be zero.
+pub fn propose_swap(
+    module_bytes: Vec<u8>,
+    marketplace_hash: ContractHash,
+    counterparty: Key,
+    offered_tokens: &[(ContractHash, U256)],
+    requested_tokens: &[(ContractHash, U256)],
+    duration_minutes: u64,
+    amount: U512,
+) -> DeployItem {
+    DeployItem::module_bytes(
+        module_bytes,
+        runtime_args! {
+            "marketplace_hash" => marketplace_hash.to_formatted_string(),
+            "counterparty" => counterparty,
+            "offered_tokens" => token_string_list(offered_tokens),
+            "requested_tokens" => token_string_list(requested_tokens),
+            "duration_minutes" => duration_minutes,
+            "amount" => amount,
+        },
+    )
+}
+
+/// `rent-call.wasm`
+pub fn rent(
+    module_bytes: Vec<u8>,
+    marketplace_hash: ContractHash,
+    token_contract: ContractHash,
+    token_id: U256,
+    days: u64,
+    amount: U512,
+) -> DeployItem {
+    DeployItem::module_bytes(
+        module_bytes,
+        runtime_args! {
+            "token_id" => token_id,
+            "rent_contract_hash" => token_contract.to_formatted_string(),
+            "marketplace_hash" => marketplace_hash.to_formatted_string(),
+            "days" => days,
+            "amount" => amount,
+        },
+    )
+}
+
+/// `drop-call.wasm`
+pub fn buy_drop(
+    module_bytes: Vec<u8>,
+    marketplace_hash: ContractHash,
+    token_contract: ContractHash,
+    quantity: u64,
+    amount: U512,
+) -> DeployItem {
+    DeployItem::module_bytes(
+        module_bytes,
+        runtime_args! {
+            "quantity" => quantity,
+            "drop_contract_hash" => token_contract.to_formatted_string(),
+            "marketplace_hash" => marketplace_hash.to_formatted_string(),
+            "amount" => amount,
+        },
+    )
+}
@@ -0,0 +1,180 @@
+use std::{fmt, str::FromStr};
+
+use blake2::{
+    digest::{Update, VariableOutput},
+    VarBlake2b,
+};
+use casper_types::{
+    account::AccountHash,
+    bytesrepr::{self, Bytes, FromBytes, ToBytes},
+    CLType, CLValue, CLValueError, ContractHash, Key, StoredValue, U256,
+};
+use serde::Serialize;
+
+use crate::types::{self, AuctionData, ListingData, OfferData};
+
+// Dictionary item keys and value decoding, so a record can be looked up with
+// `state_get_dictionary_item` or read from raw global state without the contract's help.
+
+pub const LISTINGS_DICTIONARY: &str = "listings";
+pub const OFFERS_DICTIONARY: &str = "offers";
+pub const AUCTIONS_DICTIONARY: &str = "auctions_v2";
+pub const LEGACY_AUCTIONS_DICTIONARY: &str = "auctions";
+pub const BALANCES_DICTIONARY: &str = "balances";
+
+fn blake2b_hex<T: AsRef<[u8]>>(data: T) -> String {
+    let mut digest = [0u8; 32];
+    let mut hasher = VarBlake2b::new(32).unwrap();
+    hasher.update(data);
+    hasher.finalize_variable(|hash| digest.copy_from_slice(hash));
+    hex::encode(digest)
+}
+
+/// Item key of a listing, an auction and every other per-token record (`get_listing_key`).
+pub fn listing_key(token_contract: ContractHash, token_id: U256) -> String {
+    blake2b_hex(format!("{}_{}", token_contract, token_id))
+}
+
+/// Item key of an offer (`get_offer_key`).
+pub fn offer_key(token_contract: ContractHash, token_id: U256, offerer: &Key) -> String {
+    blake2b_hex(format!(
+        "{}_{}_{}",
+        token_contract,
+        offerer.to_formatted_string(),
+        token_id
+    ))
+}
+
+/// Item key of an offer written before offerers were stored as `Key`s
+/// (`migration::get_legacy_offer_key`).
+pub fn legacy_offer_key(
+    token_contract: ContractHash,
+    token_id: U256,
+    offerer: AccountHash,
+) -> String {
+    blake2b_hex(format!("{}_{}_{}", token_contract, offerer, token_id))
+}
+
+/// Item key of per-party records such as balances and referral earnings (`key_to_str`).
+pub fn party_key(party: &Key) -> String {
+    blake2b_hex(party.to_bytes().expect("should serialize key"))
+}
+
+#[derive(Debug)]
+pub enum DecodeError {
+    Bytes(bytesrepr::Error),
+    NotCLValue(String),
+    CLValue(CLValueError),
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DecodeError::Bytes(error) => write!(formatter, "malformed bytes: {}", error),
+            DecodeError::NotCLValue(type_name) => {
+                write!(formatter, "stored value is a {}, not a CLValue", type_name)
+            }
+            DecodeError::CLValue(error) => write!(formatter, "unexpected value: {}", error),
+        }
+    }
+}
+
+impl From<bytesrepr::Error> for DecodeError {
+    fn from(error: bytesrepr::Error) -> Self {
+        DecodeError::Bytes(error)
+    }
+}
+
+impl From<CLValueError> for DecodeError {
+    fn from(error: CLValueError) -> Self {
+        DecodeError::CLValue(error)
+    }
+}
+
+/// Splits a dictionary entry wrapper into the value it holds, `None` if `bytes` are not one.
+fn unwrap_dictionary_value(bytes: &[u8]) -> Option<CLValue> {
+    let (inner, remainder) = CLValue::from_bytes(bytes).ok()?;
+    let (_seed, remainder) = Bytes::from_bytes(remainder).ok()?;
+    let (_item_key, remainder) = Bytes::from_bytes(remainder).ok()?;
+
+    remainder.is_empty().then_some(inner)
+}
+
+/// Reads the `CLValue` out of serialized `StoredValue` bytes. Global state keeps a dictionary
+/// entry wrapped together with its seed and item key as an untyped value; the wrapper is removed
+/// here, so both raw entries and the values nodes return for dictionary queries decode the same.
+/// The records are untyped as well, so an untyped value is only unwrapped if it parses as one.
+pub fn stored_value_to_cl_value(bytes: &[u8]) -> Result<CLValue, DecodeError> {
+    let stored_value = bytesrepr::deserialize::<StoredValue>(bytes.to_vec())?;
+    let type_name = stored_value.type_name();
+    let cl_value =
+        CLValue::try_from(stored_value).map_err(|_| DecodeError::NotCLValue(type_name))?;
+
+    match cl_value.cl_type() {
+        CLType::Any => Ok(unwrap_dictionary_value(cl_value.inner_bytes()).unwrap_or(cl_value)),
+        _ => Ok(cl_value),
+    }
+}
+
+/// Which dictionary a value was read from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RecordKind {
+    Listing,
+    Offer,
+    Auction,
+    LegacyAuction,
+}
+
+impl RecordKind {
+    pub fn dictionary(&self) -> &'static str {
+        match self {
+            RecordKind::Listing => LISTINGS_DICTIONARY,
+            RecordKind::Offer => OFFERS_DICTIONARY,
+            RecordKind::Auction => AUCTIONS_DICTIONARY,
+            RecordKind::LegacyAuction => LEGACY_AUCTIONS_DICTIONARY,
+        }
+    }
+}
+
+impl FromStr for RecordKind {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "listing" => Ok(RecordKind::Listing),
+            "offer" => Ok(RecordKind::Offer),
+            "auction" => Ok(RecordKind::Auction),
+            "legacy-auction" => Ok(RecordKind::LegacyAuction),
+            _ => Err(format!(
+                "unknown record {}, expected listing, offer, auction or legacy-auction",
+                value
+            )),
+        }
+    }
+}
+
+/// A decoded dictionary entry. Legacy auctions decode into the current layout.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Record {
+    Listing(ListingData),
+    Offer(OfferData),
+    Auction(AuctionData),
+}
+
+/// Decodes a dictionary value. Returns `None` for an entry the contract has cleared.
+pub fn decode_record(kind: RecordKind, value: CLValue) -> Result<Option<Record>, DecodeError> {
+    let record = match kind {
+        RecordKind::Listing => types::decode_listing(value)?.map(Record::Listing),
+        RecordKind::Offer => types::decode_offer(value)?.map(Record::Offer),
+        RecordKind::Auction => types::decode_auction(value)?.map(Record::Auction),
+        RecordKind::LegacyAuction => types::decode_legacy_auction(value)?.map(Record::Auction),
+    };
+
+    Ok(record)
+}
+
+/// Decodes serialized `StoredValue` bytes, as read from global state, into a record.
+pub fn decode_stored_record(kind: RecordKind, bytes: &[u8]) -> Result<Option<Record>, DecodeError> {
+    decode_record(kind, stored_value_to_cl_value(bytes)?)
+}
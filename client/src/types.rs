@@ -0,0 +1,90 @@
+use casper_types::{
+    account::AccountHash, bytesrepr::FromBytes, CLType, CLTyped, CLValue, CLValueError, Key, U512,
+};
+use casper_types_derive::{CLTyped, FromBytes, ToBytes};
+use serde::{Serialize, Serializer};
+
+// Mirrors of the structs the marketplace keeps in its dictionaries. Field order is part of the
+// byte layout and must follow the contract.
+
+/// Writes a party as its formatted string, the way `AccountHash` serializes itself, instead of
+/// the tagged object `Key` produces.
+fn serialize_key<S: Serializer>(key: &Key, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&key.to_formatted_string())
+}
+
+/// Entry of the `listings` dictionary.
+#[derive(CLTyped, ToBytes, FromBytes, Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct ListingData {
+    #[serde(serialize_with = "serialize_key")]
+    pub seller: Key,
+    pub price: U512,
+    pub expiration_time: Option<u64>,
+}
+
+/// Entry of the `offers` dictionary. The offerer is only part of the item key.
+#[derive(CLTyped, ToBytes, FromBytes, Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct OfferData {
+    pub price: U512,
+    pub expiration_time: u64,
+}
+
+/// Entry of the `auctions_v2` dictionary.
+#[derive(CLTyped, ToBytes, FromBytes, Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct AuctionData {
+    #[serde(serialize_with = "serialize_key")]
+    pub seller: Key,
+    pub starting_price: U512,
+    pub current_bid: U512,
+    #[serde(serialize_with = "serialize_key")]
+    pub current_winner: Key,
+    pub end_time: u64,
+}
+
+/// Entry of the legacy `auctions` dictionary, written before parties became `Key`s.
+#[derive(CLTyped, ToBytes, FromBytes, Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct LegacyAuctionData {
+    pub seller: AccountHash,
+    pub starting_price: U512,
+    pub current_bid: U512,
+    pub current_winner: AccountHash,
+    pub end_time: u64,
+}
+
+impl From<LegacyAuctionData> for AuctionData {
+    fn from(auction: LegacyAuctionData) -> Self {
+        AuctionData {
+            seller: Key::Account(auction.seller),
+            starting_price: auction.starting_price,
+            current_bid: auction.current_bid,
+            current_winner: Key::Account(auction.current_winner),
+            end_time: auction.end_time,
+        }
+    }
+}
+
+/// Decodes a dictionary entry. The contract clears entries by writing `None::<T>`, so a value of
+/// type `Option<T>` decodes to what it holds, and anything else is read as a `T`.
+pub fn decode_entry<T: CLTyped + FromBytes>(value: CLValue) -> Result<Option<T>, CLValueError> {
+    match value.cl_type() {
+        CLType::Option(_) => value.into_t::<Option<T>>(),
+        _ => value.into_t::<T>().map(Some),
+    }
+}
+
+pub fn decode_listing(value: CLValue) -> Result<Option<ListingData>, CLValueError> {
+    decode_entry(value)
+}
+
+pub fn decode_offer(value: CLValue) -> Result<Option<OfferData>, CLValueError> {
+    decode_entry(value)
+}
+
+pub fn decode_auction(value: CLValue) -> Result<Option<AuctionData>, CLValueError> {
+    decode_entry(value)
+}
+
+/// Decodes an entry of the legacy `auctions` dictionary into the current layout.
+pub fn decode_legacy_auction(value: CLValue) -> Result<Option<AuctionData>, CLValueError> {
+    decode_entry::<LegacyAuctionData>(value).map(|auction| auction.map(AuctionData::from))
+}
@@ -0,0 +1,163 @@
+use alloc::{string::String, vec::Vec};
+use casper_contract::{contract_api::runtime, unwrap_or_revert::UnwrapOrRevert};
+use casper_types::{
+    account::AccountHash, bytesrepr::FromBytes, ApiError, ContractHash, ContractPackageHash, Key,
+    U256,
+};
+
+use crate::{
+    entry_points::FormattedParam,
+    error::Error,
+    utils::{get_named_arg_bytes, read_collection_package},
+};
+
+// Collections and accounts are accepted as typed CLValues, and in the formatted string form older
+// clients send. Both are read from the raw argument bytes. A serialized `String` starts with its
+// length, which for any formatted hash is far above the `Key` tags, so the leading byte tells them
+// apart.
+
+const KEY_ACCOUNT_TAG: u8 = 0;
+const KEY_HASH_TAG: u8 = 1;
+const HASH_LENGTH: usize = 32;
+
+fn read_arg_bytes(name: &str) -> Vec<u8> {
+    get_named_arg_bytes(name).unwrap_or_revert_with(ApiError::MissingArgument)
+}
+
+/// Contract hash of a collection given by its package hash, looked up in the registry the curator
+/// keeps with `set_collection_package`. Contracts cannot read a package's versions themselves.
+fn resolve_package_hash(package_hash: ContractPackageHash) -> Result<ContractHash, Error> {
+    read_collection_package(package_hash).ok_or(Error::UnknownCollectionPackage)
+}
+
+/// Parses one collection off the front of `bytes`, given as a `Key::Hash` or a formatted
+/// `contract-...` or `contract-package-...` string. Listings and every other record are keyed by
+/// the collection's contract hash and the collection is called through it, so a package hash is
+/// resolved to its current contract hash. A `Key::Hash` has no separate package form and is taken
+/// to be the contract hash.
+fn parse_contract_hash(bytes: &[u8]) -> Result<(ContractHash, &[u8]), Error> {
+    if bytes.first() == Some(&KEY_HASH_TAG) {
+        return match Key::from_bytes(bytes) {
+            Ok((Key::Hash(hash), remainder)) => Ok((ContractHash::new(hash), remainder)),
+            _ => Err(Error::InvalidTokenContract),
+        };
+    }
+
+    let (string, remainder) =
+        String::from_bytes(bytes).map_err(|_error| Error::InvalidTokenContract)?;
+
+    let token_contract_hash = match ContractPackageHash::from_formatted_str(&string) {
+        Ok(package_hash) => resolve_package_hash(package_hash)?,
+        Err(_error) => ContractHash::from_formatted_str(&string)
+            .map_err(|_error| Error::InvalidTokenContract)?,
+    };
+
+    Ok((token_contract_hash, remainder))
+}
+
+impl FormattedParam {
+    /// Reads a collection passed as a `ContractPackageHash`, a `Key::Hash` or a formatted string,
+    /// see `parse_contract_hash`. A bare 32-byte value is taken to be a `ContractPackageHash` and
+    /// resolved like one; a contract hash has to be passed as a `Key::Hash`.
+    pub fn read_token_contract(&self) -> ContractHash {
+        let bytes = read_arg_bytes(self.0.name);
+
+        let parsed = if bytes.len() == HASH_LENGTH {
+            ContractPackageHash::from_bytes(&bytes)
+                .map_err(|_error| Error::InvalidTokenContract)
+                .and_then(|(package_hash, remainder)| {
+                    Ok((resolve_package_hash(package_hash)?, remainder))
+                })
+        } else {
+            parse_contract_hash(&bytes)
+        };
+
+        match parsed {
+            Ok((token_contract_hash, remainder)) if remainder.is_empty() => token_contract_hash,
+            Ok(_) => runtime::revert(Error::InvalidTokenContract),
+            Err(error) => runtime::revert(error),
+        }
+    }
+
+    /// Reads a list of tokens whose collections are given as `Key::Hash` or formatted strings,
+    /// which may be mixed within one list.
+    pub fn read_token_list(&self) -> Vec<(ContractHash, U256)> {
+        let bytes = read_arg_bytes(self.0.name);
+
+        let (count, mut remainder) =
+            u32::from_bytes(&bytes).unwrap_or_revert_with(Error::InvalidTokenContract);
+        let mut tokens = Vec::new();
+
+        for _ in 0..count {
+            let (token_contract_hash, rest) = parse_contract_hash(remainder).unwrap_or_revert();
+            let (token_id, rest) =
+                U256::from_bytes(rest).unwrap_or_revert_with(Error::InvalidTokenContract);
+
+            tokens.push((token_contract_hash, token_id));
+            remainder = rest;
+        }
+
+        if !remainder.is_empty() {
+            runtime::revert(Error::InvalidTokenContract);
+        }
+
+        tokens
+    }
+
+    /// Reads a list of offers as `(collection, token id, offerer)`, with collections given the way
+    /// `read_token_list` accepts them and offerers as a `Key::Account` or a `Key::Hash`.
+    pub fn read_offer_list(&self) -> Vec<(ContractHash, U256, Key)> {
+        let bytes = read_arg_bytes(self.0.name);
+
+        let (count, mut remainder) =
+            u32::from_bytes(&bytes).unwrap_or_revert_with(Error::InvalidTokenContract);
+        let mut offers = Vec::new();
+
+        for _ in 0..count {
+            let (token_contract_hash, rest) = parse_contract_hash(remainder).unwrap_or_revert();
+            let (token_id, rest) =
+                U256::from_bytes(rest).unwrap_or_revert_with(Error::InvalidTokenContract);
+            let (offerer, rest) = match Key::from_bytes(rest) {
+                Ok((key @ (Key::Account(_) | Key::Hash(_)), rest)) => (key, rest),
+                _ => runtime::revert(Error::InvalidAccount),
+            };
+
+            offers.push((token_contract_hash, token_id, offerer));
+            remainder = rest;
+        }
+
+        if !remainder.is_empty() {
+            runtime::revert(Error::InvalidTokenContract);
+        }
+
+        offers
+    }
+
+    /// Reads an account or a contract passed as an `AccountHash`, a `Key::Account`, a `Key::Hash`
+    /// or a formatted string.
+    pub fn read_key(&self) -> Key {
+        let bytes = read_arg_bytes(self.0.name);
+
+        let parsed = if bytes.len() == HASH_LENGTH {
+            AccountHash::from_bytes(&bytes)
+                .ok()
+                .map(|(account_hash, remainder)| (Key::Account(account_hash), remainder))
+        } else if bytes.first() == Some(&KEY_ACCOUNT_TAG) || bytes.first() == Some(&KEY_HASH_TAG) {
+            Key::from_bytes(&bytes).ok()
+        } else {
+            String::from_bytes(&bytes)
+                .ok()
+                .and_then(|(string, remainder)| {
+                    Key::from_formatted_str(&string)
+                        .ok()
+                        .filter(|key| matches!(key, Key::Account(_) | Key::Hash(_)))
+                        .map(|key| (key, remainder))
+                })
+        };
+
+        match parsed {
+            Some((key, remainder)) if remainder.is_empty() => key,
+            _ => runtime::revert(Error::InvalidAccount),
+        }
+    }
+}
@@ -0,0 +1,98 @@
+use alloc::{format, string::String};
+use casper_contract::{
+    contract_api::{runtime, storage},
+    unwrap_or_revert::UnwrapOrRevert,
+};
+use casper_types::{ContractHash, Key, URef};
+
+use crate::{
+    utils::{get_collection_key, get_dictionary_uref, key_to_str},
+    DropData, DropPhase,
+};
+
+pub fn get_drop_dictionary() -> URef {
+    get_dictionary_uref("drops")
+}
+
+fn get_drop_allowlist_dictionary() -> URef {
+    get_dictionary_uref("drop_allowlist")
+}
+
+fn get_drop_mints_dictionary() -> URef {
+    get_dictionary_uref("drop_mints")
+}
+
+/// Dictionary item keys are limited to 64 bytes, so composite keys are hashed.
+fn get_wallet_key(token_contract_hash: ContractHash, suffix: &str, wallet: &Key) -> String {
+    let key_string = format!(
+        "{}_{}_{}",
+        get_collection_key(token_contract_hash),
+        suffix,
+        key_to_str(wallet)
+    );
+    hex::encode(runtime::blake2b(key_string))
+}
+
+pub fn read_drop_data(token_contract_hash: ContractHash) -> Option<DropData> {
+    match storage::dictionary_get(
+        get_drop_dictionary(),
+        &get_collection_key(token_contract_hash),
+    ) {
+        Ok(item) => item,
+        Err(_error) => None,
+    }
+}
+
+pub fn is_allowlisted(token_contract_hash: ContractHash, wallet: &Key) -> bool {
+    storage::dictionary_get(
+        get_drop_allowlist_dictionary(),
+        &get_wallet_key(token_contract_hash, "allowlist", wallet),
+    )
+    .unwrap_or_revert()
+    .unwrap_or_default()
+}
+
+pub fn set_allowlisted(token_contract_hash: ContractHash, wallet: &Key, allowed: bool) {
+    storage::dictionary_put(
+        get_drop_allowlist_dictionary(),
+        &get_wallet_key(token_contract_hash, "allowlist", wallet),
+        allowed,
+    )
+}
+
+/// Number of tokens `wallet` bought in the given phase of the drop.
+pub fn get_phase_mints(token_contract_hash: ContractHash, phase: usize, wallet: &Key) -> u64 {
+    storage::dictionary_get(
+        get_drop_mints_dictionary(),
+        &get_wallet_key(token_contract_hash, &format!("phase{}", phase), wallet),
+    )
+    .unwrap_or_revert()
+    .unwrap_or_default()
+}
+
+pub fn set_phase_mints(token_contract_hash: ContractHash, phase: usize, wallet: &Key, count: u64) {
+    storage::dictionary_put(
+        get_drop_mints_dictionary(),
+        &get_wallet_key(token_contract_hash, &format!("phase{}", phase), wallet),
+        count,
+    )
+}
+
+/// First phase open at `current_time` that `wallet` may buy in. Allowlist phases are skipped for
+/// wallets that are not on the allowlist.
+pub fn find_active_phase(
+    drop: &DropData,
+    token_contract_hash: ContractHash,
+    wallet: &Key,
+    current_time: u64,
+) -> Option<(usize, DropPhase)> {
+    drop.phases
+        .iter()
+        .enumerate()
+        .find(|(_index, phase)| {
+            phase.start_time <= current_time
+                && current_time <= phase.end_time
+                && (!phase.allowlist_only || is_allowlisted(token_contract_hash, wallet))
+        })
+        .map(|(index, phase)| (index, phase.clone()))
+}
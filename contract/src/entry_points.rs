@@ -1,128 +1,535 @@
-use alloc::{string::String, vec};
+use alloc::{collections::BTreeMap, string::String, vec::Vec};
+use casper_contract::contract_api::runtime;
 use casper_types::{
-    CLType, CLTyped, EntryPoint, EntryPointAccess, EntryPointType, EntryPoints, Parameter, URef, U256, U512
+    bytesrepr::FromBytes, ApiError, CLType, CLTyped, ContractPackageHash, EntryPoint,
+    EntryPointAccess, EntryPointType, EntryPoints, Key, Parameter, URef, U256, U512,
 };
 
-pub fn get_entry_points() -> EntryPoints {
-    let mut entry_points = EntryPoints::new();
+use crate::{
+    utils::get_optional_named_arg, AuctionData, CollectionData, CollectionStats, DropData,
+    FeeQuote, LazyListing, ListingData, ListingEntry, OfferData, RentalData, SaleRecord,
+    SwapProposal, TokenState,
+};
+
+// Every entry point is declared once in `ENTRY_POINT_SPECS`. The contract's `EntryPoints` are built
+// from the table and the entry points read their arguments through the same `Param`s, so a name or
+// type can only be changed in one place.
+
+/// Named argument of an entry point together with the type it is declared and read as. An
+/// optional one may be left out by callers and is read with `read_optional`, a required one with
+/// `read`. Reading it the other way reverts with `ApiError::InvalidArgument`.
+pub struct Param {
+    pub name: &'static str,
+    pub cl_type: fn() -> CLType,
+    pub optional: bool,
+}
+
+/// Argument that is also accepted in the formatted string form older clients send. It is declared
+/// as `CLType::Any` and can only be read with the parsers in `args.rs`.
+pub struct FormattedParam(pub Param);
 
-    entry_points.add_entry_point(EntryPoint::new(
-        "create_listing",
-        vec![
-            Parameter::new("token_id", U256::cl_type()),
-            Parameter::new("price", U512::cl_type()),
-            Parameter::new("contract_hash", String::cl_type()),
+impl Param {
+    const fn new(name: &'static str, cl_type: fn() -> CLType) -> Self {
+        Param {
+            name,
+            cl_type,
+            optional: false,
+        }
+    }
+
+    const fn optional(name: &'static str, cl_type: fn() -> CLType) -> Self {
+        Param {
+            name,
+            cl_type,
+            optional: true,
+        }
+    }
+
+    pub fn read<T: CLTyped + FromBytes>(&self) -> T {
+        if self.optional {
+            runtime::revert(ApiError::InvalidArgument);
+        }
+        runtime::get_named_arg(self.name)
+    }
+
+    /// Reads an argument callers are allowed to omit.
+    pub fn read_optional<T: FromBytes>(&self) -> Option<T> {
+        if !self.optional {
+            runtime::revert(ApiError::InvalidArgument);
+        }
+        get_optional_named_arg(self.name)
+    }
+}
+
+impl FormattedParam {
+    const fn new(name: &'static str) -> Self {
+        FormattedParam(Param::new(name, any_cl_type))
+    }
+}
+
+fn any_cl_type() -> CLType {
+    CLType::Any
+}
+
+pub struct EntryPointSpec {
+    pub name: &'static str,
+    pub params: &'static [Param],
+    pub ret: fn() -> CLType,
+}
+
+pub const ARG_DURATION_MINUTES: Param = Param::new("duration_minutes", u64::cl_type);
+pub const ARG_PRICE: Param = Param::new("price", U512::cl_type);
+pub const ARG_TOKEN_ID: Param = Param::new("token_id", U256::cl_type);
+pub const ARG_TOKEN_CONTRACT: FormattedParam = FormattedParam::new("contract_hash");
+pub const ARG_BUY_PURSE: Param = Param::new("buy_purse", URef::cl_type);
+pub const ARG_SWEETENER_PURSE: Param = Param::optional("buy_purse", URef::cl_type);
+pub const ARG_ROYALTIES_PERCENTAGE: Param = Param::new("royalties_percentage", u8::cl_type);
+pub const ARG_TOKENS: FormattedParam = FormattedParam::new("tokens");
+pub const ARG_MAX_SPEND: Param = Param::new("max_spend", U512::cl_type);
+pub const ARG_SKIP_UNAVAILABLE: Param = Param::new("skip_unavailable", bool::cl_type);
+pub const ARG_SELLER: Param = Param::new("seller", Key::cl_type);
+pub const ARG_OFFSET: Param = Param::new("offset", u64::cl_type);
+pub const ARG_LIMIT: Param = Param::new("limit", u64::cl_type);
+pub const ARG_OFFERER: FormattedParam = FormattedParam::new("offerer");
+pub const ARG_OWNER: Param = Param::new("owner", Key::cl_type);
+pub const ARG_TARGET_PURSE: Param = Param::optional("target_purse", URef::cl_type);
+pub const ARG_CURATOR: Param = Param::new("curator", Key::cl_type);
+pub const ARG_NAME: Param = Param::new("name", String::cl_type);
+pub const ARG_VERIFIED: Param = Param::new("verified", bool::cl_type);
+pub const ARG_ROYALTY_RECIPIENT: Param = Param::new("royalty_recipient", Option::<Key>::cl_type);
+pub const ARG_ENABLED: Param = Param::new("enabled", bool::cl_type);
+pub const ARG_ACCOUNT: Param = Param::new("account", Key::cl_type);
+pub const ARG_BLOCKED: Param = Param::new("blocked", bool::cl_type);
+pub const ARG_CUSTODIAL: Param = Param::optional("custodial", bool::cl_type);
+pub const ARG_COUNTERPARTY: Param = Param::new("counterparty", Key::cl_type);
+pub const ARG_OFFERED_TOKENS: FormattedParam = FormattedParam::new("offered_tokens");
+pub const ARG_REQUESTED_TOKENS: FormattedParam = FormattedParam::new("requested_tokens");
+pub const ARG_SWAP_ID: Param = Param::new("swap_id", u64::cl_type);
+pub const ARG_PRICE_PER_DAY: Param = Param::new("price_per_day", U512::cl_type);
+pub const ARG_MAX_DAYS: Param = Param::new("max_days", u64::cl_type);
+pub const ARG_DAYS: Param = Param::new("days", u64::cl_type);
+pub const ARG_CREATOR: Param = Param::new("creator", Option::<Key>::cl_type);
+pub const ARG_PACKAGE_HASH: Param = Param::new("package_hash", ContractPackageHash::cl_type);
+pub const ARG_TOKEN_META: Param = Param::new("token_meta", BTreeMap::<String, String>::cl_type);
+pub const ARG_FEE_BASIS_POINTS: Param = Param::new("fee_basis_points", u32::cl_type);
+pub const ARG_COLLECTION_FEE_BASIS_POINTS: Param =
+    Param::new("fee_basis_points", Option::<u32>::cl_type);
+pub const ARG_FEE_RECIPIENT: Param = Param::new("fee_recipient", Key::cl_type);
+pub const ARG_MAX_SUPPLY: Param = Param::new("max_supply", u64::cl_type);
+pub const ARG_START_TOKEN_ID: Param = Param::new("start_token_id", U256::cl_type);
+pub const ARG_INVENTORY: Param = Param::new("inventory", Vec::<U256>::cl_type);
+pub const ARG_ALLOWLIST_ONLY: Param = Param::new("allowlist_only", bool::cl_type);
+pub const ARG_START_TIME: Param = Param::new("start_time", u64::cl_type);
+pub const ARG_END_TIME: Param = Param::new("end_time", u64::cl_type);
+pub const ARG_WALLET_LIMIT: Param = Param::new("wallet_limit", u64::cl_type);
+pub const ARG_ACCOUNTS: Param = Param::new("accounts", Vec::<Key>::cl_type);
+pub const ARG_ALLOWED: Param = Param::new("allowed", bool::cl_type);
+pub const ARG_QUANTITY: Param = Param::new("quantity", u64::cl_type);
+pub const ARG_REFERRER: Param = Param::optional("referrer", Key::cl_type);
+pub const ARG_REFERRAL_ACCOUNT: Param = Param::new("referrer", Key::cl_type);
+pub const ARG_REFERRAL_SHARE_BASIS_POINTS: Param =
+    Param::new("referral_share_basis_points", u32::cl_type);
+pub const ARG_EXEMPT: Param = Param::new("exempt", bool::cl_type);
+pub const ARG_BUYER: Param = Param::new("buyer", Key::cl_type);
+pub const ARG_SALE_ID: Param = Param::new("sale_id", u64::cl_type);
+pub const ARG_DAY: Param = Param::new("day", u64::cl_type);
+pub const ARG_AUCTIONS: FormattedParam = FormattedParam::new("auctions");
+pub const ARG_OFFERS: FormattedParam = FormattedParam::new("offers");
+
+/// Parameters written as `FormattedParam.0` are declared as `CLType::Any`: `contract_hash`,
+/// `tokens`, `offerer`, `offered_tokens`, `requested_tokens`, `auctions` and `offers`. Each is
+/// parsed from its raw bytes by `args.rs`, so callers may send either the typed value or the
+/// formatted string form.
+pub const ENTRY_POINT_SPECS: &[EntryPointSpec] = &[
+    EntryPointSpec {
+        name: "create_listing",
+        params: &[
+            ARG_TOKEN_ID,
+            ARG_PRICE,
+            ARG_DURATION_MINUTES,
+            ARG_TOKEN_CONTRACT.0,
+            ARG_CUSTODIAL,
         ],
-        CLType::Unit,
-        EntryPointAccess::Public,
-        EntryPointType::Contract,
-    ));
-
-    entry_points.add_entry_point(EntryPoint::new(
-        "cancel_listing",
-        vec![
-            Parameter::new("token_id", U256::cl_type()),
-            Parameter::new("contract_hash", String::cl_type()),
+        ret: <()>::cl_type,
+    },
+    EntryPointSpec {
+        name: "update_listing",
+        params: &[
+            ARG_TOKEN_CONTRACT.0,
+            ARG_TOKEN_ID,
+            ARG_PRICE,
+            ARG_DURATION_MINUTES,
         ],
-        CLType::Unit,
-        EntryPointAccess::Public,
-        EntryPointType::Contract,
-    ));
-
-    entry_points.add_entry_point(EntryPoint::new(
-        "buy_listing",
-        vec![
-            Parameter::new("contract_hash", String::cl_type()),
-            Parameter::new("token_id", U256::cl_type()),
-            Parameter::new("buy_purse", URef::cl_type())
+        ret: <()>::cl_type,
+    },
+    EntryPointSpec {
+        name: "cancel_listing",
+        params: &[ARG_TOKEN_ID, ARG_TOKEN_CONTRACT.0],
+        ret: <()>::cl_type,
+    },
+    EntryPointSpec {
+        name: "remove_expired_listing",
+        params: &[ARG_TOKEN_CONTRACT.0, ARG_TOKEN_ID],
+        ret: <()>::cl_type,
+    },
+    EntryPointSpec {
+        name: "get_collection_listings",
+        params: &[ARG_TOKEN_CONTRACT.0, ARG_OFFSET, ARG_LIMIT],
+        ret: Vec::<ListingEntry>::cl_type,
+    },
+    EntryPointSpec {
+        name: "get_seller_listings",
+        params: &[ARG_SELLER, ARG_OFFSET, ARG_LIMIT],
+        ret: Vec::<ListingEntry>::cl_type,
+    },
+    EntryPointSpec {
+        name: "buy_listing",
+        params: &[
+            ARG_TOKEN_CONTRACT.0,
+            ARG_TOKEN_ID,
+            ARG_BUY_PURSE,
+            ARG_REFERRER,
         ],
-        <()>::cl_type(),
-        EntryPointAccess::Public,
-        EntryPointType::Contract,
-    ));
-
-    entry_points.add_entry_point(EntryPoint::new(
-        "make_offer",
-        vec![
-            Parameter::new("contract_hash", String::cl_type()),
-            Parameter::new("token_id", U256::cl_type()),
-            Parameter::new("buy_purse", URef::cl_type())
+        ret: <()>::cl_type,
+    },
+    EntryPointSpec {
+        name: "buy_listings_batch",
+        params: &[
+            ARG_TOKENS.0,
+            ARG_MAX_SPEND,
+            ARG_SKIP_UNAVAILABLE,
+            ARG_BUY_PURSE,
+            ARG_REFERRER,
         ],
-        <()>::cl_type(),
-        EntryPointAccess::Public,
-        EntryPointType::Contract,
-    ));
-
-    entry_points.add_entry_point(EntryPoint::new(
-        "accept_offer",
-        vec![
-            Parameter::new("contract_hash", String::cl_type()),
-            Parameter::new("token_id", U256::cl_type()),
-            Parameter::new("offerer", String::cl_type())
+        ret: <()>::cl_type,
+    },
+    EntryPointSpec {
+        name: "make_offer",
+        params: &[ARG_TOKEN_CONTRACT.0, ARG_TOKEN_ID, ARG_BUY_PURSE],
+        ret: <()>::cl_type,
+    },
+    EntryPointSpec {
+        name: "accept_offer",
+        params: &[
+            ARG_TOKEN_CONTRACT.0,
+            ARG_TOKEN_ID,
+            ARG_OFFERER.0,
+            ARG_REFERRER,
         ],
-        <()>::cl_type(),
-        EntryPointAccess::Public,
-        EntryPointType::Contract,
-    ));
-
-    entry_points.add_entry_point(EntryPoint::new(
-        "cancel_offer",
-        vec![
-            Parameter::new("contract_hash", String::cl_type()),
-            Parameter::new("token_id", U256::cl_type()),
+        ret: <()>::cl_type,
+    },
+    EntryPointSpec {
+        name: "cancel_offer",
+        params: &[ARG_TOKEN_CONTRACT.0, ARG_TOKEN_ID],
+        ret: <()>::cl_type,
+    },
+    EntryPointSpec {
+        name: "start_auction",
+        params: &[
+            ARG_TOKEN_CONTRACT.0,
+            ARG_TOKEN_ID,
+            ARG_PRICE,
+            ARG_DURATION_MINUTES,
+            ARG_CUSTODIAL,
         ],
-        <()>::cl_type(),
-        EntryPointAccess::Public,
-        EntryPointType::Contract,
-    ));
-
-    entry_points.add_entry_point(EntryPoint::new(
-        "start_auction",
-        vec![
-            Parameter::new("contract_hash", String::cl_type()),
-            Parameter::new("token_id", U256::cl_type()),
-            Parameter::new("price", U512::cl_type()),
+        ret: <()>::cl_type,
+    },
+    EntryPointSpec {
+        name: "place_bid",
+        params: &[
+            ARG_TOKEN_CONTRACT.0,
+            ARG_TOKEN_ID,
+            ARG_BUY_PURSE,
+            ARG_REFERRER,
         ],
-        <()>::cl_type(),
-        EntryPointAccess::Public,
-        EntryPointType::Contract,
-    ));
-
-    entry_points.add_entry_point(EntryPoint::new(
-        "set_royalties",
-        vec![
-            Parameter::new("contract_hash", String::cl_type()),
-            Parameter::new("royalties_percentage", U256::cl_type()),
+        ret: <()>::cl_type,
+    },
+    EntryPointSpec {
+        name: "end_auction",
+        params: &[ARG_TOKEN_CONTRACT.0, ARG_TOKEN_ID],
+        ret: <()>::cl_type,
+    },
+    EntryPointSpec {
+        name: "get_listing",
+        params: &[ARG_TOKEN_CONTRACT.0, ARG_TOKEN_ID],
+        ret: Option::<ListingData>::cl_type,
+    },
+    EntryPointSpec {
+        name: "get_offer",
+        params: &[ARG_TOKEN_CONTRACT.0, ARG_TOKEN_ID, ARG_OFFERER.0],
+        ret: Option::<OfferData>::cl_type,
+    },
+    EntryPointSpec {
+        name: "get_auction",
+        params: &[ARG_TOKEN_CONTRACT.0, ARG_TOKEN_ID],
+        ret: Option::<AuctionData>::cl_type,
+    },
+    EntryPointSpec {
+        name: "get_token_state",
+        params: &[ARG_TOKEN_CONTRACT.0, ARG_TOKEN_ID],
+        ret: TokenState::cl_type,
+    },
+    EntryPointSpec {
+        name: "withdraw",
+        params: &[ARG_TARGET_PURSE],
+        ret: <()>::cl_type,
+    },
+    EntryPointSpec {
+        name: "get_balance",
+        params: &[ARG_OWNER],
+        ret: U512::cl_type,
+    },
+    EntryPointSpec {
+        name: "set_curator",
+        params: &[ARG_CURATOR],
+        ret: <()>::cl_type,
+    },
+    EntryPointSpec {
+        name: "set_allowlist_mode",
+        params: &[ARG_ENABLED],
+        ret: <()>::cl_type,
+    },
+    EntryPointSpec {
+        name: "register_collection",
+        params: &[
+            ARG_TOKEN_CONTRACT.0,
+            ARG_NAME,
+            ARG_VERIFIED,
+            ARG_ROYALTY_RECIPIENT,
+            ARG_ROYALTIES_PERCENTAGE,
         ],
-        <()>::cl_type(),
-        EntryPointAccess::Public,
-        EntryPointType::Contract,
-    ));
-
-    entry_points.add_entry_point(EntryPoint::new(
-        "place_bid",
-        vec![
-            Parameter::new("contract_hash", String::cl_type()),
-            Parameter::new("token_id", U256::cl_type()),
-            Parameter::new("buy_purse", URef::cl_type())
+        ret: <()>::cl_type,
+    },
+    EntryPointSpec {
+        name: "set_royalties",
+        params: &[ARG_TOKEN_CONTRACT.0, ARG_ROYALTIES_PERCENTAGE],
+        ret: <()>::cl_type,
+    },
+    EntryPointSpec {
+        name: "remove_collection",
+        params: &[ARG_TOKEN_CONTRACT.0],
+        ret: <()>::cl_type,
+    },
+    EntryPointSpec {
+        name: "get_collection",
+        params: &[ARG_TOKEN_CONTRACT.0],
+        ret: Option::<CollectionData>::cl_type,
+    },
+    EntryPointSpec {
+        name: "set_blocked",
+        params: &[ARG_ACCOUNT, ARG_BLOCKED],
+        ret: <()>::cl_type,
+    },
+    EntryPointSpec {
+        name: "get_blocked",
+        params: &[ARG_ACCOUNT],
+        ret: bool::cl_type,
+    },
+    EntryPointSpec {
+        name: "propose_swap",
+        params: &[
+            ARG_COUNTERPARTY,
+            ARG_OFFERED_TOKENS.0,
+            ARG_REQUESTED_TOKENS.0,
+            ARG_DURATION_MINUTES,
+            ARG_SWEETENER_PURSE,
         ],
-        <()>::cl_type(),
-        EntryPointAccess::Public,
-        EntryPointType::Contract,
-    ));
-
-    entry_points.add_entry_point(EntryPoint::new(
-        "end_auction",
-        vec![
-            Parameter::new("contract_hash", String::cl_type()),
-            Parameter::new("token_id", U256::cl_type()),
+        ret: u64::cl_type,
+    },
+    EntryPointSpec {
+        name: "accept_swap",
+        params: &[ARG_SWAP_ID],
+        ret: <()>::cl_type,
+    },
+    EntryPointSpec {
+        name: "cancel_swap",
+        params: &[ARG_SWAP_ID],
+        ret: <()>::cl_type,
+    },
+    EntryPointSpec {
+        name: "get_swap",
+        params: &[ARG_SWAP_ID],
+        ret: Option::<SwapProposal>::cl_type,
+    },
+    EntryPointSpec {
+        name: "list_for_rent",
+        params: &[
+            ARG_TOKEN_CONTRACT.0,
+            ARG_TOKEN_ID,
+            ARG_PRICE_PER_DAY,
+            ARG_MAX_DAYS,
         ],
-        <()>::cl_type(),
-        EntryPointAccess::Public,
-        EntryPointType::Contract,
-    ));
+        ret: <()>::cl_type,
+    },
+    EntryPointSpec {
+        name: "rent",
+        params: &[ARG_TOKEN_CONTRACT.0, ARG_TOKEN_ID, ARG_DAYS, ARG_BUY_PURSE],
+        ret: <()>::cl_type,
+    },
+    EntryPointSpec {
+        name: "end_rental",
+        params: &[ARG_TOKEN_CONTRACT.0, ARG_TOKEN_ID],
+        ret: <()>::cl_type,
+    },
+    EntryPointSpec {
+        name: "get_rental",
+        params: &[ARG_TOKEN_CONTRACT.0, ARG_TOKEN_ID],
+        ret: Option::<RentalData>::cl_type,
+    },
+    EntryPointSpec {
+        name: "get_user",
+        params: &[ARG_TOKEN_CONTRACT.0, ARG_TOKEN_ID],
+        ret: Option::<Key>::cl_type,
+    },
+    EntryPointSpec {
+        name: "set_collection_creator",
+        params: &[ARG_TOKEN_CONTRACT.0, ARG_CREATOR],
+        ret: <()>::cl_type,
+    },
+    EntryPointSpec {
+        name: "set_collection_package",
+        params: &[ARG_PACKAGE_HASH, ARG_TOKEN_CONTRACT.0],
+        ret: <()>::cl_type,
+    },
+    EntryPointSpec {
+        name: "create_lazy_listing",
+        params: &[
+            ARG_TOKEN_CONTRACT.0,
+            ARG_TOKEN_ID,
+            ARG_PRICE,
+            ARG_DURATION_MINUTES,
+            ARG_TOKEN_META,
+        ],
+        ret: <()>::cl_type,
+    },
+    EntryPointSpec {
+        name: "get_lazy_listing",
+        params: &[ARG_TOKEN_CONTRACT.0, ARG_TOKEN_ID],
+        ret: Option::<LazyListing>::cl_type,
+    },
+    EntryPointSpec {
+        name: "set_marketplace_fee",
+        params: &[ARG_FEE_BASIS_POINTS, ARG_FEE_RECIPIENT],
+        ret: <()>::cl_type,
+    },
+    EntryPointSpec {
+        name: "create_drop",
+        params: &[
+            ARG_TOKEN_CONTRACT.0,
+            ARG_TOKEN_META,
+            ARG_START_TOKEN_ID,
+            ARG_INVENTORY,
+            ARG_MAX_SUPPLY,
+        ],
+        ret: <()>::cl_type,
+    },
+    EntryPointSpec {
+        name: "add_drop_phase",
+        params: &[
+            ARG_TOKEN_CONTRACT.0,
+            ARG_ALLOWLIST_ONLY,
+            ARG_PRICE,
+            ARG_START_TIME,
+            ARG_END_TIME,
+            ARG_WALLET_LIMIT,
+        ],
+        ret: <()>::cl_type,
+    },
+    EntryPointSpec {
+        name: "set_drop_allowlist",
+        params: &[ARG_TOKEN_CONTRACT.0, ARG_ACCOUNTS, ARG_ALLOWED],
+        ret: <()>::cl_type,
+    },
+    EntryPointSpec {
+        name: "buy_drop",
+        params: &[ARG_TOKEN_CONTRACT.0, ARG_QUANTITY, ARG_BUY_PURSE],
+        ret: <()>::cl_type,
+    },
+    EntryPointSpec {
+        name: "get_drop",
+        params: &[ARG_TOKEN_CONTRACT.0],
+        ret: Option::<DropData>::cl_type,
+    },
+    EntryPointSpec {
+        name: "set_referral_share",
+        params: &[ARG_REFERRAL_SHARE_BASIS_POINTS],
+        ret: <()>::cl_type,
+    },
+    EntryPointSpec {
+        name: "get_referral_earnings",
+        params: &[ARG_REFERRAL_ACCOUNT],
+        ret: U512::cl_type,
+    },
+    EntryPointSpec {
+        name: "set_collection_fee",
+        params: &[ARG_TOKEN_CONTRACT.0, ARG_COLLECTION_FEE_BASIS_POINTS],
+        ret: <()>::cl_type,
+    },
+    EntryPointSpec {
+        name: "set_fee_exempt",
+        params: &[ARG_ACCOUNT, ARG_EXEMPT],
+        ret: <()>::cl_type,
+    },
+    EntryPointSpec {
+        name: "get_fee_quote",
+        params: &[ARG_TOKEN_CONTRACT.0, ARG_PRICE, ARG_SELLER, ARG_BUYER],
+        ret: FeeQuote::cl_type,
+    },
+    EntryPointSpec {
+        name: "get_sale",
+        params: &[ARG_SALE_ID],
+        ret: Option::<SaleRecord>::cl_type,
+    },
+    EntryPointSpec {
+        name: "get_sale_count",
+        params: &[],
+        ret: u64::cl_type,
+    },
+    EntryPointSpec {
+        name: "get_last_sale_price",
+        params: &[ARG_TOKEN_CONTRACT.0, ARG_TOKEN_ID],
+        ret: Option::<U512>::cl_type,
+    },
+    EntryPointSpec {
+        name: "get_collection_volume",
+        params: &[ARG_TOKEN_CONTRACT.0],
+        ret: U512::cl_type,
+    },
+    EntryPointSpec {
+        name: "get_collection_stats",
+        params: &[ARG_TOKEN_CONTRACT.0],
+        ret: CollectionStats::cl_type,
+    },
+    EntryPointSpec {
+        name: "get_daily_volume",
+        params: &[ARG_TOKEN_CONTRACT.0, ARG_DAY],
+        ret: U512::cl_type,
+    },
+    EntryPointSpec {
+        name: "migrate_storage",
+        params: &[ARG_AUCTIONS.0, ARG_OFFERS.0],
+        ret: <()>::cl_type,
+    },
+];
+
+pub fn get_entry_points() -> EntryPoints {
+    let mut entry_points = EntryPoints::new();
 
+    for spec in ENTRY_POINT_SPECS {
+        let params = spec
+            .params
+            .iter()
+            .map(|param| Parameter::new(param.name, (param.cl_type)()))
+            .collect();
 
+        entry_points.add_entry_point(EntryPoint::new(
+            spec.name,
+            params,
+            (spec.ret)(),
+            EntryPointAccess::Public,
+            EntryPointType::Contract,
+        ));
+    }
 
     entry_points
 }
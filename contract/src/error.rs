@@ -17,7 +17,35 @@ pub enum Error {
     OfferCancelledOrFinished = 12,
     AuctionDoesntExist = 13,
     AuctionCancelledOrFinished = 14,
-    AuctionNotFinished = 15
+    AuctionNotFinished = 15,
+    MaxSpendExceeded = 16,
+    ListingNotExpired = 17,
+    NothingToWithdraw = 18,
+    Reentrancy = 19,
+    CollectionNotAllowed = 20,
+    InvalidRoyalties = 21,
+    AccountBlocked = 22,
+    TokenAlreadyListed = 23,
+    InvalidSwap = 24,
+    SwapDoesntExist = 25,
+    SwapExpired = 26,
+    RentalDoesntExist = 27,
+    RentalActive = 28,
+    InvalidRentalDuration = 29,
+    TokenAlreadyMinted = 30,
+    DropDoesntExist = 31,
+    DropNotActive = 32,
+    DropSoldOut = 33,
+    WalletLimitReached = 34,
+    InvalidQuantity = 35,
+    InvalidFee = 36,
+    InvalidTokenContract = 37,
+    InvalidAccount = 38,
+    // 39 was `AccountCallerRequired`, retired once contract callers could trade. Not reused so
+    // that clients reading an old code do not misreport it.
+    TargetPurseRequired = 40,
+    InvalidDropPhase = 41,
+    UnknownCollectionPackage = 42,
 }
 
 impl From<Error> for ApiError {
@@ -0,0 +1,94 @@
+use casper_contract::{contract_api::storage, unwrap_or_revert::UnwrapOrRevert};
+use casper_types::{ContractHash, Key, URef, U512};
+
+use crate::{
+    utils::{
+        get_collection_key, get_dictionary_uref, key_to_str, read_collection_data, read_config,
+        FEE_RECIPIENT_KEY, MARKETPLACE_FEE_KEY,
+    },
+    FeeQuote, MAX_FEE_BASIS_POINTS,
+};
+
+pub fn get_collection_fee_dictionary() -> URef {
+    get_dictionary_uref("collection_fees")
+}
+
+pub fn get_fee_exempt_dictionary() -> URef {
+    get_dictionary_uref("fee_exempt")
+}
+
+/// Fee the admin set for a single collection, overriding the global one.
+pub fn read_collection_fee(token_contract_hash: ContractHash) -> Option<u32> {
+    match storage::dictionary_get(
+        get_collection_fee_dictionary(),
+        &get_collection_key(token_contract_hash),
+    ) {
+        Ok(item) => item,
+        Err(_error) => None,
+    }
+}
+
+pub fn is_fee_exempt(key: &Key) -> bool {
+    storage::dictionary_get(get_fee_exempt_dictionary(), &key_to_str(key))
+        .unwrap_or_revert()
+        .unwrap_or_default()
+}
+
+/// Fee charged on a sale between `seller` and `buyer`: nothing when either of them is exempt or
+/// no fee recipient is configured, otherwise the collection override or the global fee.
+pub fn resolve_fee_basis_points(
+    token_contract_hash: ContractHash,
+    seller: &Key,
+    buyer: &Key,
+) -> u32 {
+    if read_config::<Key>(FEE_RECIPIENT_KEY).is_none()
+        || is_fee_exempt(seller)
+        || is_fee_exempt(buyer)
+    {
+        return 0;
+    }
+
+    match read_collection_fee(token_contract_hash) {
+        Some(fee_basis_points) => fee_basis_points,
+        None => read_config(MARKETPLACE_FEE_KEY).unwrap_or_default(),
+    }
+}
+
+/// Marketplace fee on a primary sale, where the creator sells newly minted tokens. Only the global
+/// fee applies: collection overrides, exemptions and royalties are meant for resales.
+pub fn primary_sale_fee(price: U512) -> U512 {
+    if read_config::<Key>(FEE_RECIPIENT_KEY).is_none() {
+        return U512::zero();
+    }
+
+    let fee_basis_points: u32 = read_config(MARKETPLACE_FEE_KEY).unwrap_or_default();
+    price * U512::from(fee_basis_points) / U512::from(MAX_FEE_BASIS_POINTS)
+}
+
+/// Splits `price` the way a sale would. Royalties are taken from what is left after the
+/// marketplace fee, so the two can never add up to more than the price.
+pub fn quote_sale(
+    token_contract_hash: ContractHash,
+    seller: &Key,
+    buyer: &Key,
+    price: U512,
+) -> FeeQuote {
+    let fee_basis_points = resolve_fee_basis_points(token_contract_hash, seller, buyer);
+    let marketplace_fee = price * U512::from(fee_basis_points) / U512::from(MAX_FEE_BASIS_POINTS);
+
+    let (royalty_recipient, royalty) = match read_collection_data(token_contract_hash) {
+        Some(collection) if collection.royalty_recipient.is_some() => (
+            collection.royalty_recipient,
+            (price - marketplace_fee) * U512::from(collection.royalties_percentage) / 100,
+        ),
+        _ => (None, U512::zero()),
+    };
+
+    FeeQuote {
+        fee_basis_points,
+        marketplace_fee,
+        royalty_recipient,
+        royalty,
+        seller_proceeds: price - marketplace_fee - royalty,
+    }
+}
@@ -0,0 +1,202 @@
+use alloc::{
+    format,
+    string::{String, ToString},
+    vec::Vec,
+};
+use casper_contract::{
+    contract_api::{runtime, storage},
+    unwrap_or_revert::UnwrapOrRevert,
+};
+use casper_types::{ContractHash, Key, URef, U256};
+
+use crate::{
+    utils::{
+        get_collection_key, get_dictionary_uref, get_listing_key, key_to_str, read_listing_data,
+    },
+    ListingData, ListingEntry,
+};
+
+pub const COLLECTION_INDEX: &str = "collection_listings";
+pub const SELLER_INDEX: &str = "seller_listings";
+
+pub const MAX_PAGE_SIZE: u64 = 50;
+
+// Every index is split over three dictionaries: `<index>_len` holds the number of entries per
+// owner, `<index>_items` the token stored at each position and `<index>_pos` the position of each
+// listing, so removals can swap the last entry into the freed slot.
+
+fn get_len_dictionary(index: &str) -> URef {
+    get_dictionary_uref(&format!("{}_len", index))
+}
+
+fn get_items_dictionary(index: &str) -> URef {
+    get_dictionary_uref(&format!("{}_items", index))
+}
+
+fn get_positions_dictionary(index: &str) -> URef {
+    get_dictionary_uref(&format!("{}_pos", index))
+}
+
+/// Dictionary item keys are limited to 64 bytes, so composite keys are hashed.
+fn get_entry_key(owner: &str, suffix: &str) -> String {
+    hex::encode(runtime::blake2b(format!("{}_{}", owner, suffix)))
+}
+
+fn get_position(index: &str, owner: &str, listing_key: &str) -> Option<u64> {
+    storage::dictionary_get::<Option<u64>>(
+        get_positions_dictionary(index),
+        &get_entry_key(owner, listing_key),
+    )
+    .unwrap_or_revert()
+    .flatten()
+}
+
+pub fn get_collection_owner(token_contract_hash: ContractHash) -> String {
+    get_collection_key(token_contract_hash)
+}
+
+pub fn get_seller_owner(seller: &Key) -> String {
+    key_to_str(seller)
+}
+
+pub fn index_len(index: &str, owner: &str) -> u64 {
+    storage::dictionary_get(get_len_dictionary(index), owner)
+        .unwrap_or_revert()
+        .unwrap_or_default()
+}
+
+pub fn index_add(index: &str, owner: &str, token_contract_hash: ContractHash, token_id: U256) {
+    let listing_key = get_listing_key(token_contract_hash, token_id);
+
+    if get_position(index, owner, &listing_key).is_some() {
+        return;
+    }
+
+    let len = index_len(index, owner);
+
+    storage::dictionary_put(
+        get_items_dictionary(index),
+        &get_entry_key(owner, &len.to_string()),
+        (token_contract_hash, token_id),
+    );
+    storage::dictionary_put(
+        get_positions_dictionary(index),
+        &get_entry_key(owner, &listing_key),
+        Some(len),
+    );
+    storage::dictionary_put(get_len_dictionary(index), owner, len + 1);
+}
+
+pub fn index_remove(index: &str, owner: &str, token_contract_hash: ContractHash, token_id: U256) {
+    let listing_key = get_listing_key(token_contract_hash, token_id);
+
+    let position = match get_position(index, owner, &listing_key) {
+        Some(position) => position,
+        None => return,
+    };
+
+    let last = index_len(index, owner) - 1;
+
+    if position != last {
+        let items = get_items_dictionary(index);
+        let last_item: (ContractHash, U256) =
+            storage::dictionary_get(items, &get_entry_key(owner, &last.to_string()))
+                .unwrap_or_revert()
+                .unwrap_or_revert();
+
+        storage::dictionary_put(
+            items,
+            &get_entry_key(owner, &position.to_string()),
+            last_item,
+        );
+        storage::dictionary_put(
+            get_positions_dictionary(index),
+            &get_entry_key(owner, &get_listing_key(last_item.0, last_item.1)),
+            Some(position),
+        );
+    }
+
+    storage::dictionary_put(
+        get_positions_dictionary(index),
+        &get_entry_key(owner, &listing_key),
+        None::<u64>,
+    );
+    storage::dictionary_put(get_len_dictionary(index), owner, last);
+}
+
+/// Listings stay indexed until `remove_expired_listing` is called, so pages check this first.
+pub fn is_expired(listing: &ListingData, current_time: u64) -> bool {
+    listing
+        .expiration_time
+        .map_or(false, |expiration_time| current_time > expiration_time)
+}
+
+/// Listings at positions `offset..offset + limit` of the index, including expired ones that were
+/// not removed yet.
+pub fn index_entries(index: &str, owner: &str, offset: u64, limit: u64) -> Vec<ListingEntry> {
+    let len = index_len(index, owner);
+    let end = len.min(offset.saturating_add(limit.min(MAX_PAGE_SIZE)));
+    let items = get_items_dictionary(index);
+
+    let mut page = Vec::new();
+
+    for position in offset..end {
+        let (token_contract, token_id): (ContractHash, U256) =
+            storage::dictionary_get(items, &get_entry_key(owner, &position.to_string()))
+                .unwrap_or_revert()
+                .unwrap_or_revert();
+
+        if let Some(listing) = read_listing_data(&get_listing_key(token_contract, token_id)) {
+            page.push(ListingEntry {
+                token_contract,
+                token_id,
+                listing,
+            });
+        }
+    }
+
+    page
+}
+
+/// Like `index_entries`, but expired listings are left out, so a page can hold fewer than `limit`
+/// entries before the end of the index is reached.
+pub fn index_page(index: &str, owner: &str, offset: u64, limit: u64) -> Vec<ListingEntry> {
+    let current_time: u64 = runtime::get_blocktime().into();
+
+    let mut page = index_entries(index, owner, offset, limit);
+    page.retain(|entry| !is_expired(&entry.listing, current_time));
+
+    page
+}
+
+/// Adds an active listing to both the collection and the seller index.
+pub fn index_listing(token_contract_hash: ContractHash, token_id: U256, seller: &Key) {
+    index_add(
+        COLLECTION_INDEX,
+        &get_collection_owner(token_contract_hash),
+        token_contract_hash,
+        token_id,
+    );
+    index_add(
+        SELLER_INDEX,
+        &get_seller_owner(seller),
+        token_contract_hash,
+        token_id,
+    );
+}
+
+/// Removes a listing that is no longer active from both indexes.
+pub fn deindex_listing(token_contract_hash: ContractHash, token_id: U256, seller: &Key) {
+    index_remove(
+        COLLECTION_INDEX,
+        &get_collection_owner(token_contract_hash),
+        token_contract_hash,
+        token_id,
+    );
+    index_remove(
+        SELLER_INDEX,
+        &get_seller_owner(seller),
+        token_contract_hash,
+        token_id,
+    );
+}
@@ -6,6 +6,7 @@ compile_error!("target arch should be wasm32: compile with '--target wasm32-unkn
 extern crate alloc;
 
 use alloc::{
+    collections::BTreeMap,
     string::{String, ToString},
     vec,
     vec::Vec,
@@ -19,28 +20,83 @@ use casper_contract::{
     unwrap_or_revert::UnwrapOrRevert,
 };
 use casper_types::{
-    account::AccountHash, contracts::NamedKeys, runtime_args, ApiError, ContractHash, Key,
-    RuntimeArgs, URef, U256, U512,
+    bytesrepr::ToBytes, contracts::NamedKeys, runtime_args, CLType, CLValue, ContractHash,
+    ContractPackageHash, Key, RuntimeArgs, URef, U256, U512,
+};
+use drops::{
+    find_active_phase, get_drop_dictionary, get_phase_mints, read_drop_data, set_allowlisted,
+    set_phase_mints,
+};
+use entry_points::{
+    get_entry_points, ARG_ACCOUNT, ARG_ACCOUNTS, ARG_ALLOWED, ARG_ALLOWLIST_ONLY, ARG_AUCTIONS,
+    ARG_BLOCKED, ARG_BUYER, ARG_BUY_PURSE, ARG_COLLECTION_FEE_BASIS_POINTS, ARG_COUNTERPARTY,
+    ARG_CREATOR, ARG_CURATOR, ARG_CUSTODIAL, ARG_DAY, ARG_DAYS, ARG_DURATION_MINUTES, ARG_ENABLED,
+    ARG_END_TIME, ARG_EXEMPT, ARG_FEE_BASIS_POINTS, ARG_FEE_RECIPIENT, ARG_INVENTORY, ARG_LIMIT,
+    ARG_MAX_DAYS, ARG_MAX_SPEND, ARG_MAX_SUPPLY, ARG_NAME, ARG_OFFERED_TOKENS, ARG_OFFERER,
+    ARG_OFFERS, ARG_OFFSET, ARG_OWNER, ARG_PACKAGE_HASH, ARG_PRICE, ARG_PRICE_PER_DAY,
+    ARG_QUANTITY, ARG_REFERRAL_ACCOUNT, ARG_REFERRAL_SHARE_BASIS_POINTS, ARG_REFERRER,
+    ARG_REQUESTED_TOKENS, ARG_ROYALTIES_PERCENTAGE, ARG_ROYALTY_RECIPIENT, ARG_SALE_ID, ARG_SELLER,
+    ARG_SKIP_UNAVAILABLE, ARG_START_TIME, ARG_START_TOKEN_ID, ARG_SWAP_ID, ARG_SWEETENER_PURSE,
+    ARG_TARGET_PURSE, ARG_TOKENS, ARG_TOKEN_CONTRACT, ARG_TOKEN_ID, ARG_TOKEN_META, ARG_VERIFIED,
+    ARG_WALLET_LIMIT,
+};
+use fees::{
+    get_collection_fee_dictionary, get_fee_exempt_dictionary, primary_sale_fee, quote_sale,
+};
+use listing_index::{
+    deindex_listing, get_collection_owner, get_seller_owner, index_listing, index_page,
+    COLLECTION_INDEX, SELLER_INDEX,
+};
+use migration::{migrate_auction, migrate_offer, read_legacy_offer};
+use rentals::{get_rental_dictionary, get_rental_user, read_rental_data};
+use sales::{
+    read_collection_volume, read_last_sale_price, read_sale, read_sale_count, record_sale,
+    SALE_TYPE_AUCTION, SALE_TYPE_DROP, SALE_TYPE_LISTING, SALE_TYPE_OFFER,
+};
+use stats::{
+    auction_ended, auction_started, listing_added, listing_removed, listing_repriced,
+    read_collection_stats, read_daily_volume,
+};
+use swaps::{
+    get_swap_dictionary, read_swap_proposal, require_tokens_transferable, transfer_tokens,
+    SWAP_COUNT_KEY,
 };
-use entry_points::get_entry_points;
 use utils::{
-    contract_package_hash, get_acutin_dictionary, get_auction_data, get_listing_data, get_listing_dictionary, get_listing_key, get_offer_dictionary, get_offer_key, get_purse, get_token_owner, transfer_approved
+    credit_balance, credit_referral, custody_key, get_acutin_dictionary, get_auction_data,
+    get_auction_referrer_dictionary, get_balance_dictionary, get_blocklist_dictionary,
+    get_caller_key, get_collection_dictionary, get_collection_key,
+    get_collection_package_dictionary, get_creator_dictionary, get_custody_dictionary,
+    get_lazy_listing_dictionary, get_listing_data, get_listing_dictionary, get_listing_key,
+    get_named_arg_bytes, get_offer_dictionary, get_offer_key, get_pending_balance,
+    get_price_change_dictionary, get_purse, get_token_owner, is_blocked, key_to_str, lock, next_id,
+    pay_out, read_auction_data, read_auction_referrer, read_collection_creator,
+    read_collection_data, read_config, read_custodian, read_lazy_listing, read_listing_data,
+    read_offer_data, read_referral_earnings, require_admin, require_collection_allowed,
+    require_curator, require_not_blocked, transfer_approved, unlock, write_config, ADMIN_KEY,
+    ALLOWLIST_MODE_KEY, CURATOR_KEY, FEE_RECIPIENT_KEY, MARKETPLACE_FEE_KEY, PROCEEDS_PURSE,
+    REFERRAL_SHARE_KEY,
 };
 
+mod args;
+mod drops;
 mod entry_points;
 mod error;
+mod fees;
+mod listing_index;
+mod migration;
+mod rentals;
+mod sales;
+mod stats;
+mod swaps;
 mod utils;
 
 use error::Error;
 
 const MILLISECONDS_IN_MINUTE: u64 = 60000;
+const MILLISECONDS_IN_DAY: u64 = 24 * 60 * MILLISECONDS_IN_MINUTE;
 
-const ARG_DURATION_MINUTES: &str = "duration_minutes";
-const ARG_PRICE: &str = "price";
-const ARG_TOKEN_ID: &str = "token_id";
-const ARG_TOKEN_CONTRACT: &str = "contract_hash";
-const ARG_BUY_PURSE: &str = "buy_purse";
-const ARG_ROYALTIES_PERCENTAGE: &str = "royalties_percentage";
+const MAX_ROYALTIES_PERCENTAGE: u8 = 100;
+const MAX_FEE_BASIS_POINTS: u32 = 10_000;
 
 const CONTRACT_VERSION_KEY: &str = "version777";
 const CONTRACT_KEY: &str = "mystra_marketplace777";
@@ -48,6 +104,8 @@ const CONTRACT_KEY: &str = "mystra_marketplace777";
 const CONTRACT_PACKAGE_NAME: &str = "mystra_marketplace_package_name777";
 const CONTRACT_ACCESS_UREF: &str = "mystra_marketplace_access_uref777";
 
+const PRICE_CHANGE_COUNT_KEY: &str = "price_change_count";
+
 use casper_types_derive::{CLTyped, FromBytes, ToBytes};
 
 #[derive(CLTyped, ToBytes, FromBytes)]
@@ -57,12 +115,20 @@ pub struct ListingData {
     pub expiration_time: Option<u64>,
 }
 
+/// A listing together with the token it refers to, as returned by the paginated queries.
+#[derive(CLTyped, ToBytes, FromBytes)]
+pub struct ListingEntry {
+    pub token_contract: ContractHash,
+    pub token_id: U256,
+    pub listing: ListingData,
+}
+
 #[derive(CLTyped, ToBytes, FromBytes)]
 pub struct AuctionData {
-    pub seller: AccountHash,
+    pub seller: Key,
     pub starting_price: U512,
     pub current_bid: U512,
-    pub current_winner: AccountHash,
+    pub current_winner: Key,
     pub end_time: u64,
 }
 
@@ -72,22 +138,143 @@ pub struct OfferData {
     pub expiration_time: u64,
 }
 
+/// Everything the marketplace knows about a single token.
+#[derive(CLTyped, ToBytes, FromBytes)]
+pub struct TokenState {
+    pub owner: Option<Key>,
+    pub listing: Option<ListingData>,
+    pub auction: Option<AuctionData>,
+}
+
+/// Curator-maintained registry entry for a collection.
+#[derive(CLTyped, ToBytes, FromBytes)]
+pub struct CollectionData {
+    pub name: String,
+    pub verified: bool,
+    pub royalty_recipient: Option<Key>,
+    pub royalties_percentage: u8,
+}
+
+#[derive(CLTyped, ToBytes, FromBytes)]
+pub struct PriceChangeRecord {
+    pub token_contract: ContractHash,
+    pub token_id: U256,
+    pub seller: Key,
+    pub old_price: U512,
+    pub new_price: U512,
+    pub expiration_time: Option<u64>,
+    pub timestamp: u64,
+}
+
+/// Proposal to trade `offered` tokens, plus `cspr_amount` held in the offers purse, for the
+/// `requested` tokens of `counterparty`.
+#[derive(CLTyped, ToBytes, FromBytes)]
+pub struct SwapProposal {
+    pub proposer: Key,
+    pub counterparty: Key,
+    pub offered: Vec<(ContractHash, U256)>,
+    pub requested: Vec<(ContractHash, U256)>,
+    pub cspr_amount: U512,
+    pub expiration_time: Option<u64>,
+}
+
+/// Listing of a token that does not exist yet. The marketplace mints it to the first buyer.
+#[derive(CLTyped, ToBytes, FromBytes)]
+pub struct LazyListing {
+    pub creator: Key,
+    pub token_meta: BTreeMap<String, String>,
+}
+
+/// Sale window of a drop. A `wallet_limit` of 0 means no per-wallet cap.
+#[derive(Clone, CLTyped, ToBytes, FromBytes)]
+pub struct DropPhase {
+    pub allowlist_only: bool,
+    pub price: U512,
+    pub start_time: u64,
+    pub end_time: u64,
+    pub wallet_limit: u64,
+}
+
+/// Primary sale of a collection. Tokens are minted from `start_token_id` onwards, or handed out
+/// in order from the creator's pre-minted `inventory` when one was given.
+#[derive(CLTyped, ToBytes, FromBytes)]
+pub struct DropData {
+    pub creator: Key,
+    pub token_meta: BTreeMap<String, String>,
+    pub start_token_id: U256,
+    pub inventory: Vec<U256>,
+    pub max_supply: u64,
+    pub sold: u64,
+    pub phases: Vec<DropPhase>,
+}
+
+/// Token offered for rent while held in custody. `user` holds the usage rights until
+/// `expiration_time`.
+#[derive(CLTyped, ToBytes, FromBytes)]
+pub struct RentalData {
+    pub owner: Key,
+    pub price_per_day: U512,
+    pub max_days: u64,
+    pub user: Option<Key>,
+    pub expiration_time: Option<u64>,
+}
+
+/// Entry in the append-only sale ledger. `payment_asset` is `None` for sales paid in CSPR, and
+/// `sale_type` is one of the `SALE_TYPE_*` constants in `sales`.
+#[derive(CLTyped, ToBytes, FromBytes)]
+pub struct SaleRecord {
+    pub token_contract: ContractHash,
+    pub token_id: U256,
+    pub seller: Key,
+    pub buyer: Key,
+    pub price: U512,
+    pub payment_asset: Option<ContractHash>,
+    pub timestamp: u64,
+    pub sale_type: u8,
+}
+
+/// Aggregates kept per collection for storefronts. `daily_volume` covers the UTC day the query
+/// runs in, earlier days are available through `get_daily_volume`. `active_listings` covers every
+/// indexed listing: an expired one counts until it is taken out with `remove_expired_listing`.
+/// `floor_price` is the cheapest listing that has not expired.
+#[derive(CLTyped, ToBytes, FromBytes)]
+pub struct CollectionStats {
+    pub active_listings: u64,
+    pub floor_price: Option<U512>,
+    pub active_auctions: u64,
+    pub total_sales: u64,
+    pub total_volume: U512,
+    pub daily_volume: U512,
+}
+
+/// How the price of a sale is split once fee overrides, exemptions and royalties are applied.
+#[derive(CLTyped, ToBytes, FromBytes)]
+pub struct FeeQuote {
+    pub fee_basis_points: u32,
+    pub marketplace_fee: U512,
+    pub royalty_recipient: Option<Key>,
+    pub royalty: U512,
+    pub seller_proceeds: U512,
+}
+
 #[no_mangle]
 pub extern "C" fn create_listing() -> () {
-    let caller = Key::Account(runtime::get_caller());
-    let token_contract_string: String = runtime::get_named_arg(ARG_TOKEN_CONTRACT);
-    let token_id: U256 = runtime::get_named_arg(ARG_TOKEN_ID);
-    let price: U512 = runtime::get_named_arg(ARG_PRICE);
-    let duration_in_minutes: u64 = runtime::get_named_arg(ARG_DURATION_MINUTES);
+    lock();
 
-    let current_time: u64 = runtime::get_blocktime().into();
+    let caller = get_caller_key();
+    require_not_blocked(&caller);
+
+    let token_contract_hash: ContractHash = ARG_TOKEN_CONTRACT.read_token_contract();
+    let token_id: U256 = ARG_TOKEN_ID.read();
+    let price: U512 = ARG_PRICE.read();
+    let duration_in_minutes: u64 = ARG_DURATION_MINUTES.read();
+    let custodial: bool = ARG_CUSTODIAL.read_optional().unwrap_or_default();
 
     if price == U512::zero() {
         runtime::revert(Error::PriceSetToZero)
     }
 
-    let token_contract_hash: ContractHash =
-        ContractHash::from_formatted_str(&token_contract_string).unwrap();
+    require_collection_allowed(token_contract_hash);
 
     let owner = get_token_owner(token_contract_hash, token_id).unwrap_or_revert();
 
@@ -105,355 +292,1870 @@ pub extern "C" fn create_listing() -> () {
 
     let key = get_listing_key(token_contract_hash, token_id);
 
-    let expiration_time: Option<u64> = if duration_in_minutes > 0 {
-        Some(current_time + duration_in_minutes * MILLISECONDS_IN_MINUTE)
-    } else {
-        None
-    };
+    // Relisting a token that changed hands moves it to the new seller's index
+    if let Some(previous_listing) = read_listing_data(&key) {
+        deindex_listing(token_contract_hash, token_id, &previous_listing.seller);
+        listing_removed(token_contract_hash, token_id);
+    }
+
+    // The token was minted some other way, so a lazy listing left for it can no longer be sold
+    if read_lazy_listing(&key).is_some() {
+        storage::dictionary_put(get_lazy_listing_dictionary(), &key, None::<LazyListing>);
+    }
 
     let listing_data = ListingData {
         price: price,
         seller: owner,
-        expiration_time: expiration_time,
+        expiration_time: get_expiration_time(duration_in_minutes),
     };
 
-    storage::dictionary_put(get_listing_dictionary(), &key, listing_data)
+    if custodial {
+        deposit_token(token_contract_hash, token_id, owner, &key);
+    }
+
+    storage::dictionary_put(get_listing_dictionary(), &key, listing_data);
+
+    index_listing(token_contract_hash, token_id, &owner);
+    listing_added(token_contract_hash, token_id, price);
+
+    unlock();
 }
 
 #[no_mangle]
-pub extern "C" fn cancel_listing() -> () {
-    let caller = Key::Account(runtime::get_caller());
-    let token_contract_string: String = runtime::get_named_arg(ARG_TOKEN_CONTRACT);
-    let token_id: U256 = runtime::get_named_arg(ARG_TOKEN_ID);
+pub extern "C" fn update_listing() -> () {
+    let caller = get_caller_key();
+    require_not_blocked(&caller);
 
-    let token_contract_hash: ContractHash =
-        ContractHash::from_formatted_str(&token_contract_string).unwrap();
+    let token_contract_hash: ContractHash = ARG_TOKEN_CONTRACT.read_token_contract();
+    let token_id: U256 = ARG_TOKEN_ID.read();
+    let price: U512 = ARG_PRICE.read();
+    let duration_in_minutes: u64 = ARG_DURATION_MINUTES.read();
+
+    if price == U512::zero() {
+        runtime::revert(Error::PriceSetToZero)
+    }
 
-    let owner: Key = get_token_owner(token_contract_hash, token_id).unwrap_or_revert();
+    let key = get_listing_key(token_contract_hash, token_id);
+    let mut listing_data: ListingData = get_listing_data(&key);
 
-    if owner != caller {
+    if listing_data.seller != caller {
         runtime::revert(Error::PermissionDenied)
     }
 
-    let key = get_listing_key(token_contract_hash, token_id);
-    storage::dictionary_put(get_listing_dictionary(), &key, None::<ListingData>)
+    // A token held in custody is owned by the marketplace and a lazy listed one does not exist
+    // yet, there is no approval to check
+    if read_custodian(&key) != Some(caller) && read_lazy_listing(&key).is_none() {
+        let owner = get_token_owner(token_contract_hash, token_id).unwrap_or_revert();
+
+        if owner != caller {
+            runtime::revert(Error::PermissionDenied)
+        }
+
+        if !transfer_approved(token_contract_hash, token_id, caller) {
+            runtime::revert(Error::NeedsTransferApproval);
+        }
+    }
+
+    let price_change = PriceChangeRecord {
+        token_contract: token_contract_hash,
+        token_id,
+        seller: caller,
+        old_price: listing_data.price,
+        new_price: price,
+        expiration_time: get_expiration_time(duration_in_minutes),
+        timestamp: runtime::get_blocktime().into(),
+    };
+
+    listing_data.price = price;
+    listing_data.expiration_time = price_change.expiration_time;
+
+    storage::dictionary_put(get_listing_dictionary(), &key, listing_data);
+    listing_repriced(token_contract_hash, token_id, price_change.old_price, price);
+
+    let change_id = next_id(PRICE_CHANGE_COUNT_KEY);
+    storage::dictionary_put(
+        get_price_change_dictionary(),
+        &change_id.to_string(),
+        price_change,
+    );
 }
 
+/// Lists a token that has not been minted yet. Only the creator the curator assigned to the
+/// collection can do it, and the marketplace package needs minter rights on the collection.
 #[no_mangle]
-pub extern "C" fn buy_listing() -> () {
-    let buyer = Key::Account(runtime::get_caller());
-    let token_contract_string: String = runtime::get_named_arg(ARG_TOKEN_CONTRACT);
-    let token_contract_hash: ContractHash =
-        ContractHash::from_formatted_str(&token_contract_string).unwrap();
-    let token_id: U256 = runtime::get_named_arg(ARG_TOKEN_ID);
-    let buyer_purse: URef = runtime::get_named_arg(ARG_BUY_PURSE);
-    let purse_balance: U512 = system::get_purse_balance(buyer_purse).unwrap();
+pub extern "C" fn create_lazy_listing() -> () {
+    let caller = get_caller_key();
+    require_not_blocked(&caller);
 
-    let key = get_listing_key(token_contract_hash, token_id);
-    let listing_data: ListingData = get_listing_data(&key);
+    let token_contract_hash: ContractHash = ARG_TOKEN_CONTRACT.read_token_contract();
+    let token_id: U256 = ARG_TOKEN_ID.read();
+    let price: U512 = ARG_PRICE.read();
+    let duration_in_minutes: u64 = ARG_DURATION_MINUTES.read();
+    let token_meta: BTreeMap<String, String> = ARG_TOKEN_META.read();
 
-    if listing_data.price == U512::zero() {
-        runtime::revert(Error::OfferDoesntExistOrCancelled)
+    if price == U512::zero() {
+        runtime::revert(Error::PriceSetToZero)
     }
 
-    if purse_balance < listing_data.price {
-        runtime::revert(Error::BalanceInsufficient);
-    }
+    require_collection_allowed(token_contract_hash);
 
-    match listing_data.expiration_time {
-        Some(val) => {
-            let current_time: u64 = runtime::get_blocktime().into();
+    if read_collection_creator(token_contract_hash) != Some(caller) {
+        runtime::revert(Error::PermissionDenied)
+    }
 
-            if current_time > val {
-                runtime::revert(Error::ListingExpired)
-            }
-        }
-        None => {}
+    if get_token_owner(token_contract_hash, token_id).is_some() {
+        runtime::revert(Error::TokenAlreadyMinted)
     }
 
-    let owner = get_token_owner(token_contract_hash, token_id).unwrap();
+    let key = get_listing_key(token_contract_hash, token_id);
 
-    if owner != listing_data.seller {
-        runtime::revert(Error::PermissionDenied)
+    if let Some(previous_listing) = read_listing_data(&key) {
+        deindex_listing(token_contract_hash, token_id, &previous_listing.seller);
+        listing_removed(token_contract_hash, token_id);
     }
 
-    system::transfer_from_purse_to_account(
-        buyer_purse,
-        owner.into_account().unwrap_or_revert(),
-        listing_data.price,
-        None,
-    )
-    .unwrap_or_revert();
+    let listing_data = ListingData {
+        price,
+        seller: caller,
+        expiration_time: get_expiration_time(duration_in_minutes),
+    };
 
-    runtime::call_contract::<()>(
-        token_contract_hash,
-        "transfer_from",
-        runtime_args! {
-          "sender" => owner,
-          "recipient" => buyer,
-          "token_ids" => vec![token_id],
-        },
-    );
+    let lazy_listing = LazyListing {
+        creator: caller,
+        token_meta,
+    };
 
-    storage::dictionary_put(get_listing_dictionary(), &key, None::<ListingData>);
+    storage::dictionary_put(get_lazy_listing_dictionary(), &key, lazy_listing);
+    storage::dictionary_put(get_listing_dictionary(), &key, listing_data);
+
+    index_listing(token_contract_hash, token_id, &caller);
+    listing_added(token_contract_hash, token_id, price);
+}
+
+/// Listings created with a zero duration never expire.
+fn get_expiration_time(duration_in_minutes: u64) -> Option<u64> {
+    if duration_in_minutes > 0 {
+        let current_time: u64 = runtime::get_blocktime().into();
+        Some(current_time + duration_in_minutes * MILLISECONDS_IN_MINUTE)
+    } else {
+        None
+    }
 }
 
 #[no_mangle]
-pub extern "C" fn make_offer() -> () {
-    let token_contract_string: String = runtime::get_named_arg(ARG_TOKEN_CONTRACT);
-    let token_contract_hash: ContractHash =
-        ContractHash::from_formatted_str(&token_contract_string).unwrap();
-    let token_id: U256 = runtime::get_named_arg(ARG_TOKEN_ID);
-    let offerer_purse: URef = runtime::get_named_arg(ARG_BUY_PURSE);
-    let purse_balance: U512 = system::get_purse_balance(offerer_purse).unwrap();
+pub extern "C" fn cancel_listing() -> () {
+    lock();
 
-    let key = get_offer_key(token_contract_hash, token_id, runtime::get_caller());
+    let caller = get_caller_key();
+    let token_contract_hash: ContractHash = ARG_TOKEN_CONTRACT.read_token_contract();
+    let token_id: U256 = ARG_TOKEN_ID.read();
 
-    let offers_purse: URef = get_purse("offers_purse");
+    let key = get_listing_key(token_contract_hash, token_id);
 
-    match storage::dictionary_get::<OfferData>(get_offer_dictionary(), &key) {
-        Ok(d) => match d {
-            Some(offer_data) => {
-                system::transfer_from_purse_to_account(
-                    offers_purse,
-                    runtime::get_caller(),
-                    offer_data.price,
-                    None,
-                )
-                .unwrap_or_revert();
+    match (read_custodian(&key), read_lazy_listing(&key)) {
+        // Cancelling a custodial listing hands the token back to the seller
+        (Some(custodian), _) if custodian == caller && read_listing_data(&key).is_some() => {
+            release_token(token_contract_hash, token_id, caller, &key);
+        }
+        (Some(_custodian), _) => runtime::revert(Error::PermissionDenied),
+        (None, Some(lazy_listing)) => {
+            if lazy_listing.creator != caller {
+                runtime::revert(Error::PermissionDenied)
             }
-            None => {},
-        },
-        Err(_error) => {}
-        
+
+            storage::dictionary_put(get_lazy_listing_dictionary(), &key, None::<LazyListing>);
+        }
+        (None, None) => {
+            let owner: Key = get_token_owner(token_contract_hash, token_id).unwrap_or_revert();
+
+            if owner != caller {
+                runtime::revert(Error::PermissionDenied)
+            }
+        }
     }
 
-    system::transfer_from_purse_to_purse(offerer_purse, offers_purse, purse_balance, None)
-        .unwrap_or_revert();
+    if let Some(listing_data) = read_listing_data(&key) {
+        deindex_listing(token_contract_hash, token_id, &listing_data.seller);
+        listing_removed(token_contract_hash, token_id);
+    }
 
-    let offer = OfferData {
-        price: purse_balance,
-        expiration_time: 0u64,
-    };
+    storage::dictionary_put(get_listing_dictionary(), &key, None::<ListingData>);
 
-    storage::dictionary_put(get_offer_dictionary(), &key, offer);
+    unlock();
 }
 
 #[no_mangle]
-pub extern "C" fn accept_offer() -> () {
-    let token_contract_string: String = runtime::get_named_arg(ARG_TOKEN_CONTRACT);
-    let token_contract_hash: ContractHash =
-        ContractHash::from_formatted_str(&token_contract_string).unwrap();
+pub extern "C" fn remove_expired_listing() -> () {
+    lock();
 
-    let offerer_account_string: String = runtime::get_named_arg("offerer");
-    let offerer_account_hash: AccountHash =
-        AccountHash::from_formatted_str(&offerer_account_string).unwrap();
-    let token_id: U256 = runtime::get_named_arg(ARG_TOKEN_ID);
+    let token_contract_hash: ContractHash = ARG_TOKEN_CONTRACT.read_token_contract();
+    let token_id: U256 = ARG_TOKEN_ID.read();
 
-    let key = get_offer_key(token_contract_hash, token_id, offerer_account_hash);
+    let key = get_listing_key(token_contract_hash, token_id);
+    let listing_data: ListingData = get_listing_data(&key);
 
-    let offers_purse = get_purse("offers_purse");
+    let current_time: u64 = runtime::get_blocktime().into();
 
-    let owner = get_token_owner(token_contract_hash, token_id).unwrap_or_revert();
+    match listing_data.expiration_time {
+        Some(val) if current_time > val => {}
+        _ => runtime::revert(Error::ListingNotExpired),
+    }
 
-    if owner != Key::Account(runtime::get_caller()) {
-        runtime::revert(Error::PermissionDenied);
+    if read_custodian(&key).is_some() {
+        release_token(token_contract_hash, token_id, listing_data.seller, &key);
     }
 
-    match storage::dictionary_get::<OfferData>(get_offer_dictionary(), &key).unwrap_or_revert_with(Error::OfferCancelledOrFinished) {
-        Some(offer_data) => {
-            system::transfer_from_purse_to_account(
-                offers_purse,
-                runtime::get_caller(),
-                offer_data.price,
-                None,
-            )
-            .unwrap_or_revert();
-        }
-        None => runtime::revert(Error::OfferDoesntExistOrCancelled),
+    if read_lazy_listing(&key).is_some() {
+        storage::dictionary_put(get_lazy_listing_dictionary(), &key, None::<LazyListing>);
     }
 
-    runtime::call_contract::<()>(
-        token_contract_hash,
-        "transfer_from",
-        runtime_args! {
-          "sender" => Key::Account(runtime::get_caller()),
-          "recipient" => Key::Account(offerer_account_hash),
-          "token_ids" => vec![token_id],
-        },
-    );
+    deindex_listing(token_contract_hash, token_id, &listing_data.seller);
+    listing_removed(token_contract_hash, token_id);
+    storage::dictionary_put(get_listing_dictionary(), &key, None::<ListingData>);
 
-    storage::dictionary_put(get_offer_dictionary(), &key, None::<OfferData>);
+    unlock();
 }
 
 #[no_mangle]
-pub extern "C" fn cancel_offer() -> () {
-    let token_contract_string: String = runtime::get_named_arg(ARG_TOKEN_CONTRACT);
-    let token_contract_hash: ContractHash =
-        ContractHash::from_formatted_str(&token_contract_string).unwrap();
-    let token_id: U256 = runtime::get_named_arg(ARG_TOKEN_ID);
-
-    let key = get_offer_key(token_contract_hash, token_id, runtime::get_caller());
+pub extern "C" fn get_collection_listings() -> () {
+    let token_contract_hash: ContractHash = ARG_TOKEN_CONTRACT.read_token_contract();
+    let offset: u64 = ARG_OFFSET.read();
+    let limit: u64 = ARG_LIMIT.read();
+
+    let page = index_page(
+        COLLECTION_INDEX,
+        &get_collection_owner(token_contract_hash),
+        offset,
+        limit,
+    );
 
-    let offers_purse = get_purse("offers_purse");
+    runtime::ret(CLValue::from_t(page).unwrap_or_revert())
+}
 
-    let current_offer: OfferData = storage::dictionary_get(get_offer_dictionary(), &key)
-        .unwrap_or_revert()
-        .unwrap_or_revert_with(Error::OfferDoesntExistOrCancelled);
+#[no_mangle]
+pub extern "C" fn get_seller_listings() -> () {
+    let seller: Key = ARG_SELLER.read();
+    let offset: u64 = ARG_OFFSET.read();
+    let limit: u64 = ARG_LIMIT.read();
 
-    system::transfer_from_purse_to_account(
-        offers_purse,
-        runtime::get_caller(),
-        current_offer.price,
-        None,
-    )
-    .unwrap_or_revert();
+    let page = index_page(SELLER_INDEX, &get_seller_owner(&seller), offset, limit);
 
-    storage::dictionary_put(get_offer_dictionary(), &key, None::<OfferData>);
+    runtime::ret(CLValue::from_t(page).unwrap_or_revert())
 }
 
 #[no_mangle]
-pub extern "C" fn start_auction() -> () {
-    let caller = Key::Account(runtime::get_caller());
-    let token_contract_string: String = runtime::get_named_arg(ARG_TOKEN_CONTRACT);
-    let token_id: U256 = runtime::get_named_arg(ARG_TOKEN_ID);
-    let starting_price: U512 = runtime::get_named_arg(ARG_PRICE);
-    let duration_in_minutes: u64 = runtime::get_named_arg(ARG_DURATION_MINUTES);
-
-    let token_contract_hash: ContractHash =
-        ContractHash::from_formatted_str(&token_contract_string).unwrap();
-
-    let current_time: u64 = runtime::get_blocktime().into();
+pub extern "C" fn buy_listing() -> () {
+    lock();
 
-    let key = get_listing_key(token_contract_hash, token_id);
+    let buyer = get_caller_key();
+    require_not_blocked(&buyer);
 
-    let approved = transfer_approved(token_contract_hash, token_id, caller);
+    let token_contract_hash: ContractHash = ARG_TOKEN_CONTRACT.read_token_contract();
+    let token_id: U256 = ARG_TOKEN_ID.read();
+    let buyer_purse: URef = ARG_BUY_PURSE.read();
+    let referrer: Option<Key> = ARG_REFERRER.read_optional();
 
-    if (approved == false) {
-        runtime::revert(Error::NeedsTransferApproval);
-    }
+    let listing_data =
+        check_listing_available(token_contract_hash, token_id, buyer_purse).unwrap_or_revert();
 
-    let auction_data = AuctionData {
-        current_bid: starting_price,
-        starting_price: starting_price,
-        seller: runtime::get_caller(),
-        current_winner: runtime::get_caller(),
-        end_time: current_time + (duration_in_minutes * MILLISECONDS_IN_MINUTE),
-    };
+    settle_listing(
+        token_contract_hash,
+        token_id,
+        listing_data,
+        buyer,
+        buyer_purse,
+        referrer,
+    );
 
-    storage::dictionary_put(get_acutin_dictionary(), &key, auction_data)
+    unlock();
 }
 
+/// Buys several listings in one call. With `skip_unavailable`, listings that fail the checks in
+/// `check_listing_available` (including ownership and transfer approval, read with `owner_of` and
+/// `get_approved`) or that would exceed `max_spend` are skipped. A token contract that reverts
+/// inside one of those calls or in `transfer_from` still reverts the whole batch, since a revert
+/// in a called contract cannot be caught.
 #[no_mangle]
-pub extern "C" fn place_bid() -> () {
-    let token_contract_string: String = runtime::get_named_arg(ARG_TOKEN_CONTRACT);
-    let token_contract_hash: ContractHash =
-        ContractHash::from_formatted_str(&token_contract_string).unwrap();
-    let token_id: U256 = runtime::get_named_arg(ARG_TOKEN_ID);
-    let buyer_purse: URef = runtime::get_named_arg(ARG_BUY_PURSE);
-    let purse_balance: U512 = system::get_purse_balance(buyer_purse).unwrap();
+pub extern "C" fn buy_listings_batch() -> () {
+    lock();
+
+    let buyer = get_caller_key();
+    require_not_blocked(&buyer);
+
+    let tokens: Vec<(ContractHash, U256)> = ARG_TOKENS.read_token_list();
+    let max_spend: U512 = ARG_MAX_SPEND.read();
+    let skip_unavailable: bool = ARG_SKIP_UNAVAILABLE.read();
+    let buyer_purse: URef = ARG_BUY_PURSE.read();
+    let referrer: Option<Key> = ARG_REFERRER.read_optional();
+
+    let mut spent = U512::zero();
+
+    for (token_contract_hash, token_id) in tokens {
+        let listing_data = match check_listing_available(token_contract_hash, token_id, buyer_purse)
+        {
+            Ok(listing_data) => listing_data,
+            Err(_error) if skip_unavailable => continue,
+            Err(error) => runtime::revert(error),
+        };
+
+        if spent + listing_data.price > max_spend {
+            if skip_unavailable {
+                continue;
+            }
+            runtime::revert(Error::MaxSpendExceeded);
+        }
 
-    let key = get_listing_key(token_contract_hash, token_id);
-    let mut auction_data: AuctionData = get_auction_data(&key);
- 
-    if (purse_balance <= auction_data.current_bid) {
-        revert(Error::BidTooLow)
+        spent += listing_data.price;
+        settle_listing(
+            token_contract_hash,
+            token_id,
+            listing_data,
+            buyer,
+            buyer_purse,
+            referrer,
+        );
     }
 
-    let current_time: u64 = runtime::get_blocktime().into();
+    unlock();
+}
 
-    if (current_time > auction_data.end_time) {
-        runtime::revert(Error::AuctionEnded);
+/// Validates that a listing can be bought right now with the funds in `buyer_purse`, without
+/// changing any state, so batch purchases can skip unavailable items.
+fn check_listing_available(
+    token_contract_hash: ContractHash,
+    token_id: U256,
+    buyer_purse: URef,
+) -> Result<ListingData, Error> {
+    let key = get_listing_key(token_contract_hash, token_id);
+    let listing_data: ListingData = match storage::dictionary_get(get_listing_dictionary(), &key) {
+        Ok(Some(value)) => value,
+        Ok(None) => return Err(Error::ListingDoesntExist),
+        Err(_error) => return Err(Error::ListingCancelledOrFinished),
+    };
+
+    if listing_data.price == U512::zero() {
+        return Err(Error::OfferDoesntExistOrCancelled);
     }
 
-    if (current_time - auction_data.end_time < 10 * MILLISECONDS_IN_MINUTE) {
-        auction_data.end_time += 10 * MILLISECONDS_IN_MINUTE;
+    if is_blocked(&listing_data.seller) {
+        return Err(Error::AccountBlocked);
     }
 
-    let auctions_purse: URef = get_purse("auctions_purse");
+    let purse_balance: U512 = system::get_purse_balance(buyer_purse).unwrap_or_revert();
 
-    if (auction_data.current_bid != auction_data.starting_price) {
-        system::transfer_from_purse_to_account(
-            auctions_purse,
-            auction_data.seller,
-            auction_data.current_bid,
-            None,
-        )
-        .unwrap_or_revert();
+    if purse_balance < listing_data.price {
+        return Err(Error::BalanceInsufficient);
     }
 
-    system::transfer_from_purse_to_purse(buyer_purse, auctions_purse, purse_balance, None)
-        .unwrap_or_revert();
+    if let Some(val) = listing_data.expiration_time {
+        let current_time: u64 = runtime::get_blocktime().into();
 
-    auction_data.current_bid = purse_balance;
-    auction_data.current_winner = runtime::get_caller();
+        if current_time > val {
+            return Err(Error::ListingExpired);
+        }
+    }
 
-    storage::dictionary_put(get_acutin_dictionary(), &key, auction_data)
-}
+    // Custodial listings are backed by the token the marketplace already holds
+    if read_custodian(&key) == Some(listing_data.seller) {
+        return Ok(listing_data);
+    }
 
-#[no_mangle]
-pub extern "C" fn end_auction() -> () {
-    let token_contract_string: String = runtime::get_named_arg(ARG_TOKEN_CONTRACT);
-    let token_contract_hash: ContractHash =
-        ContractHash::from_formatted_str(&token_contract_string).unwrap();
-    let token_id: U256 = runtime::get_named_arg(ARG_TOKEN_ID);
+    let owner = get_token_owner(token_contract_hash, token_id);
 
-    let key = get_listing_key(token_contract_hash, token_id);
-    let mut auction_data: AuctionData = get_auction_data(&key);
+    // Lazy listed tokens are minted on purchase, as long as nobody minted them in the meantime
+    if read_lazy_listing(&key).is_some() {
+        return match owner {
+            None => Ok(listing_data),
+            Some(_owner) => Err(Error::TokenAlreadyMinted),
+        };
+    }
 
-    let current_time: u64 = runtime::get_blocktime().into();
+    if owner != Some(listing_data.seller) {
+        return Err(Error::PermissionDenied);
+    }
 
-    if (current_time < auction_data.end_time) {
-        runtime::revert(Error::AuctionNotFinished);
+    if !transfer_approved(token_contract_hash, token_id, listing_data.seller) {
+        return Err(Error::NeedsTransferApproval);
     }
 
-    // listing musi nie istniec
+    Ok(listing_data)
+}
 
-    //sprawdzic approved i ownership, jak nie zgadza sie to zwrocic winnerowi kase
+/// Pays for the listing out of `buyer_purse`. Only the listing price is taken, the session code
+/// returns whatever is left in the purse to the buyer.
+fn settle_listing(
+    token_contract_hash: ContractHash,
+    token_id: U256,
+    listing_data: ListingData,
+    buyer: Key,
+    buyer_purse: URef,
+    referrer: Option<Key>,
+) {
+    let owner = listing_data.seller;
 
-    let auctions_purse = get_purse("auctions_purse");
+    let key = get_listing_key(token_contract_hash, token_id);
 
-    if (auction_data.current_bid != auction_data.starting_price) {
-        system::transfer_from_purse_to_account(
-            auctions_purse,
-            auction_data.current_winner.into(),
-            auction_data.current_bid,
-            None,
+    pay_seller(
+        buyer_purse,
+        token_contract_hash,
+        owner,
+        buyer,
+        listing_data.price,
+        referrer,
+    );
+
+    if let Some(lazy_listing) = read_lazy_listing(&key) {
+        runtime::call_contract::<()>(
+            token_contract_hash,
+            "mint",
+            runtime_args! {
+              "recipient" => buyer,
+              "token_ids" => vec![token_id],
+              "token_metas" => vec![lazy_listing.token_meta],
+            },
+        );
+
+        storage::dictionary_put(get_lazy_listing_dictionary(), &key, None::<LazyListing>);
+    } else if read_custodian(&key).is_some() {
+        release_token(token_contract_hash, token_id, buyer, &key);
+    } else {
+        runtime::call_contract::<()>(
+            token_contract_hash,
+            "transfer_from",
+            runtime_args! {
+              "sender" => owner,
+              "recipient" => buyer,
+              "token_ids" => vec![token_id],
+            },
+        );
+    }
+
+    deindex_listing(token_contract_hash, token_id, &owner);
+    listing_removed(token_contract_hash, token_id);
+    record_sale(
+        token_contract_hash,
+        token_id,
+        owner,
+        buyer,
+        listing_data.price,
+        SALE_TYPE_LISTING,
+    );
+
+    storage::dictionary_put(get_listing_dictionary(), &key, None::<ListingData>);
+}
+
+/// Moves a token into the marketplace's custody. Settlement of a custodial listing or auction no
+/// longer depends on the seller keeping the token and the approval in place.
+fn deposit_token(token_contract_hash: ContractHash, token_id: U256, depositor: Key, key: &str) {
+    runtime::call_contract::<()>(
+        token_contract_hash,
+        "transfer_from",
+        runtime_args! {
+          "sender" => depositor,
+          "recipient" => custody_key(),
+          "token_ids" => vec![token_id],
+        },
+    );
+
+    storage::dictionary_put(get_custody_dictionary(), key, depositor);
+}
+
+/// Sends a token held in custody to `recipient`, the buyer or the seller taking it back.
+fn release_token(token_contract_hash: ContractHash, token_id: U256, recipient: Key, key: &str) {
+    runtime::call_contract::<()>(
+        token_contract_hash,
+        "transfer",
+        runtime_args! {
+          "recipient" => recipient,
+          "token_ids" => vec![token_id],
+        },
+    );
+
+    storage::dictionary_put(get_custody_dictionary(), key, None::<Key>);
+}
+
+/// Takes down the listing of a token that changed hands some other way, so it cannot be bought
+/// from its new owner at the old seller's price.
+fn clear_listing(token_contract_hash: ContractHash, token_id: U256) {
+    let key = get_listing_key(token_contract_hash, token_id);
+
+    if let Some(listing_data) = read_listing_data(&key) {
+        deindex_listing(token_contract_hash, token_id, &listing_data.seller);
+        listing_removed(token_contract_hash, token_id);
+        storage::dictionary_put(get_listing_dictionary(), &key, None::<ListingData>);
+    }
+}
+
+/// Splits the proceeds of a sale between the marketplace fee recipient, the collection's royalty
+/// recipient, if one is registered, and the seller, as quoted by `quote_sale`. A referrer gets the
+/// configured share of the marketplace fee, unless it is a party to the sale or blocked.
+fn pay_seller(
+    source_purse: URef,
+    token_contract_hash: ContractHash,
+    seller: Key,
+    buyer: Key,
+    price: U512,
+    referrer: Option<Key>,
+) {
+    let quote = quote_sale(token_contract_hash, &seller, &buyer, price);
+
+    if quote.marketplace_fee > U512::zero() {
+        let fee_recipient: Key = read_config(FEE_RECIPIENT_KEY).unwrap_or_revert();
+        let mut fee = quote.marketplace_fee;
+
+        // Neither side of the sale can win back part of the fee by naming itself
+        let referrer = referrer
+            .filter(|referrer| *referrer != buyer && *referrer != seller && !is_blocked(referrer));
+
+        if let Some(referrer) = referrer {
+            let referral_share: u32 = read_config(REFERRAL_SHARE_KEY).unwrap_or_default();
+            let referral = fee * U512::from(referral_share) / U512::from(MAX_FEE_BASIS_POINTS);
+            credit_referral(source_purse, referrer, referral);
+            fee -= referral;
+        }
+
+        credit_balance(source_purse, fee_recipient, fee);
+    }
+
+    if let Some(royalty_recipient) = quote.royalty_recipient {
+        credit_balance(source_purse, royalty_recipient, quote.royalty);
+    }
+
+    credit_balance(source_purse, seller, quote.seller_proceeds);
+}
+
+/// Splits the proceeds of a primary sale between the marketplace fee recipient and the creator,
+/// see `primary_sale_fee`.
+fn pay_creator(source_purse: URef, creator: Key, price: U512) {
+    let fee = primary_sale_fee(price);
+
+    if fee > U512::zero() {
+        let fee_recipient: Key = read_config(FEE_RECIPIENT_KEY).unwrap_or_revert();
+        credit_balance(source_purse, fee_recipient, fee);
+    }
+
+    credit_balance(source_purse, creator, price - fee);
+}
+
+#[no_mangle]
+pub extern "C" fn make_offer() -> () {
+    lock();
+
+    let token_contract_hash: ContractHash = ARG_TOKEN_CONTRACT.read_token_contract();
+    let token_id: U256 = ARG_TOKEN_ID.read();
+    let offerer_purse: URef = ARG_BUY_PURSE.read();
+    let purse_balance: U512 = system::get_purse_balance(offerer_purse).unwrap();
+    let offerer = get_caller_key();
+
+    require_not_blocked(&offerer);
+    require_collection_allowed(token_contract_hash);
+
+    migrate_offer(token_contract_hash, token_id, &offerer);
+    let key = get_offer_key(token_contract_hash, token_id, &offerer);
+
+    let offers_purse: URef = get_purse("offers_purse");
+
+    match storage::dictionary_get::<OfferData>(get_offer_dictionary(), &key) {
+        Ok(d) => match d {
+            Some(offer_data) => {
+                pay_out(offers_purse, offerer, offer_data.price);
+            }
+            None => {},
+        },
+        Err(_error) => {}
+        
+    }
+
+    system::transfer_from_purse_to_purse(offerer_purse, offers_purse, purse_balance, None)
+        .unwrap_or_revert();
+
+    let offer = OfferData {
+        price: purse_balance,
+        expiration_time: 0u64,
+    };
+
+    storage::dictionary_put(get_offer_dictionary(), &key, offer);
+
+    unlock();
+}
+
+#[no_mangle]
+pub extern "C" fn accept_offer() -> () {
+    lock();
+
+    let token_contract_hash: ContractHash = ARG_TOKEN_CONTRACT.read_token_contract();
+
+    let offerer: Key = ARG_OFFERER.read_key();
+    let token_id: U256 = ARG_TOKEN_ID.read();
+    let referrer: Option<Key> = ARG_REFERRER.read_optional();
+
+    migrate_offer(token_contract_hash, token_id, &offerer);
+    let key = get_offer_key(token_contract_hash, token_id, &offerer);
+
+    let offers_purse = get_purse("offers_purse");
+
+    let owner = get_token_owner(token_contract_hash, token_id).unwrap_or_revert();
+
+    if owner != get_caller_key() {
+        runtime::revert(Error::PermissionDenied);
+    }
+
+    require_not_blocked(&owner);
+    require_not_blocked(&offerer);
+
+    match storage::dictionary_get::<OfferData>(get_offer_dictionary(), &key).unwrap_or_revert_with(Error::OfferCancelledOrFinished) {
+        Some(offer_data) => {
+            pay_seller(
+                offers_purse,
+                token_contract_hash,
+                owner,
+                offerer,
+                offer_data.price,
+                referrer,
+            );
+            record_sale(
+                token_contract_hash,
+                token_id,
+                owner,
+                offerer,
+                offer_data.price,
+                SALE_TYPE_OFFER,
+            );
+        }
+        None => runtime::revert(Error::OfferDoesntExistOrCancelled),
+    }
+
+    runtime::call_contract::<()>(
+        token_contract_hash,
+        "transfer_from",
+        runtime_args! {
+          "sender" => get_caller_key(),
+          "recipient" => offerer,
+          "token_ids" => vec![token_id],
+        },
+    );
+    clear_listing(token_contract_hash, token_id);
+
+    storage::dictionary_put(get_offer_dictionary(), &key, None::<OfferData>);
+
+    unlock();
+}
+
+#[no_mangle]
+pub extern "C" fn cancel_offer() -> () {
+    lock();
+
+    let token_contract_hash: ContractHash = ARG_TOKEN_CONTRACT.read_token_contract();
+    let token_id: U256 = ARG_TOKEN_ID.read();
+
+    let offerer = get_caller_key();
+
+    migrate_offer(token_contract_hash, token_id, &offerer);
+    let key = get_offer_key(token_contract_hash, token_id, &offerer);
+
+    let offers_purse = get_purse("offers_purse");
+
+    let current_offer: OfferData = storage::dictionary_get(get_offer_dictionary(), &key)
+        .unwrap_or_revert()
+        .unwrap_or_revert_with(Error::OfferDoesntExistOrCancelled);
+
+    pay_out(offers_purse, offerer, current_offer.price);
+
+    storage::dictionary_put(get_offer_dictionary(), &key, None::<OfferData>);
+
+    unlock();
+}
+
+#[no_mangle]
+pub extern "C" fn start_auction() -> () {
+    lock();
+
+    let caller = get_caller_key();
+    require_not_blocked(&caller);
+
+    let token_contract_hash: ContractHash = ARG_TOKEN_CONTRACT.read_token_contract();
+    let token_id: U256 = ARG_TOKEN_ID.read();
+    let starting_price: U512 = ARG_PRICE.read();
+    let duration_in_minutes: u64 = ARG_DURATION_MINUTES.read();
+    let custodial: bool = ARG_CUSTODIAL.read_optional().unwrap_or_default();
+
+    require_collection_allowed(token_contract_hash);
+
+    let current_time: u64 = runtime::get_blocktime().into();
+
+    let key = get_listing_key(token_contract_hash, token_id);
+
+    let approved = transfer_approved(token_contract_hash, token_id, caller);
+
+    if (approved == false) {
+        runtime::revert(Error::NeedsTransferApproval);
+    }
+
+    let auction_data = AuctionData {
+        current_bid: starting_price,
+        starting_price: starting_price,
+        seller: caller,
+        current_winner: caller,
+        end_time: current_time + (duration_in_minutes * MILLISECONDS_IN_MINUTE),
+    };
+
+    // A stale auction in the legacy layout must not reappear once this one is cleared
+    migrate_auction(&key);
+    let previous_auction = read_auction_data(&key);
+    let replaces_auction = previous_auction.is_some();
+
+    // Only the seller may restart an auction, and not while bids on it are still open
+    if let Some(previous_auction) = &previous_auction {
+        if previous_auction.seller != caller {
+            runtime::revert(Error::PermissionDenied);
+        }
+        if previous_auction.current_winner != previous_auction.seller
+            && current_time < previous_auction.end_time
+        {
+            runtime::revert(Error::AuctionNotFinished);
+        }
+    }
+
+    if custodial {
+        // The token cannot back a listing and an auction once it is held in escrow
+        if read_listing_data(&key).is_some() {
+            runtime::revert(Error::TokenAlreadyListed);
+        }
+
+        deposit_token(token_contract_hash, token_id, caller, &key);
+    }
+
+    // The highest bid of the auction being replaced goes back to its bidder
+    if let Some(previous_auction) = previous_auction {
+        if previous_auction.current_winner != previous_auction.seller {
+            credit_balance(
+                get_purse("auctions_purse"),
+                previous_auction.current_winner,
+                previous_auction.current_bid,
+            );
+            storage::dictionary_put(get_auction_referrer_dictionary(), &key, None::<Key>);
+        }
+    }
+
+    storage::dictionary_put(get_acutin_dictionary(), &key, auction_data);
+
+    // Restarting an auction replaces the running one, which is already counted
+    if !replaces_auction {
+        auction_started(token_contract_hash);
+    }
+
+    unlock();
+}
+
+#[no_mangle]
+pub extern "C" fn place_bid() -> () {
+    lock();
+
+    let token_contract_hash: ContractHash = ARG_TOKEN_CONTRACT.read_token_contract();
+    let token_id: U256 = ARG_TOKEN_ID.read();
+    let buyer_purse: URef = ARG_BUY_PURSE.read();
+    let purse_balance: U512 = system::get_purse_balance(buyer_purse).unwrap();
+    let referrer: Option<Key> = ARG_REFERRER.read_optional();
+
+    let key = get_listing_key(token_contract_hash, token_id);
+    let mut auction_data: AuctionData = get_auction_data(&key);
+
+    let bidder = get_caller_key();
+
+    require_not_blocked(&bidder);
+    require_not_blocked(&auction_data.seller);
+
+    if (purse_balance <= auction_data.current_bid) {
+        revert(Error::BidTooLow)
+    }
+
+    let current_time: u64 = runtime::get_blocktime().into();
+
+    if (current_time > auction_data.end_time) {
+        runtime::revert(Error::AuctionEnded);
+    }
+
+    if (auction_data.end_time - current_time < 10 * MILLISECONDS_IN_MINUTE) {
+        auction_data.end_time += 10 * MILLISECONDS_IN_MINUTE;
+    }
+
+    let auctions_purse: URef = get_purse("auctions_purse");
+
+    // Outbid bidder gets the previous bid back
+    if (auction_data.current_bid != auction_data.starting_price) {
+        credit_balance(
+            auctions_purse,
+            auction_data.current_winner,
+            auction_data.current_bid,
+        );
+    }
+
+    system::transfer_from_purse_to_purse(buyer_purse, auctions_purse, purse_balance, None)
+        .unwrap_or_revert();
+
+    auction_data.current_bid = purse_balance;
+    auction_data.current_winner = bidder;
+
+    storage::dictionary_put(get_acutin_dictionary(), &key, auction_data);
+
+    // The referrer that brought in the highest bid is paid when the auction settles
+    match referrer {
+        Some(referrer) => {
+            storage::dictionary_put(get_auction_referrer_dictionary(), &key, referrer)
+        }
+        None => storage::dictionary_put(get_auction_referrer_dictionary(), &key, None::<Key>),
+    }
+
+    unlock();
+}
+
+#[no_mangle]
+pub extern "C" fn end_auction() -> () {
+    lock();
+
+    let token_contract_hash: ContractHash = ARG_TOKEN_CONTRACT.read_token_contract();
+    let token_id: U256 = ARG_TOKEN_ID.read();
+
+    let key = get_listing_key(token_contract_hash, token_id);
+    let auction_data: AuctionData = get_auction_data(&key);
+
+    let current_time: u64 = runtime::get_blocktime().into();
+
+    if current_time < auction_data.end_time {
+        runtime::revert(Error::AuctionNotFinished);
+    }
+
+    // listing musi nie istniec
+
+    //sprawdzic approved i ownership, jak nie zgadza sie to zwrocic winnerowi kase
+
+    let auctions_purse = get_purse("auctions_purse");
+
+    let seller = auction_data.seller;
+    let winner = auction_data.current_winner;
+    let custodial = read_custodian(&key).is_some();
+
+    if auction_data.current_bid != auction_data.starting_price
+        && (is_blocked(&seller) || is_blocked(&winner))
+    {
+        // Settlement with a blocked party is called off, the winner gets the bid back
+        credit_balance(auctions_purse, winner, auction_data.current_bid);
+
+        if custodial {
+            release_token(token_contract_hash, token_id, seller, &key);
+        }
+    } else if auction_data.current_bid != auction_data.starting_price {
+        pay_seller(
+            auctions_purse,
+            token_contract_hash,
+            seller,
+            winner,
+            auction_data.current_bid,
+            read_auction_referrer(&key),
+        );
+
+        if custodial {
+            release_token(token_contract_hash, token_id, winner, &key);
+        } else {
+            runtime::call_contract::<()>(
+                token_contract_hash,
+                "transfer_from",
+                runtime_args! {
+                  "sender" => seller,
+                  "recipient" => winner,
+                  "token_ids" => vec![token_id],
+                },
+            );
+        }
+        clear_listing(token_contract_hash, token_id);
+
+        record_sale(
+            token_contract_hash,
+            token_id,
+            seller,
+            winner,
+            auction_data.current_bid,
+            SALE_TYPE_AUCTION,
+        );
+    } else if custodial {
+        // Nobody bid, the token goes back to the seller
+        release_token(token_contract_hash, token_id, seller, &key);
+    }
+
+    storage::dictionary_put(get_acutin_dictionary(), &key, None::<AuctionData>);
+    storage::dictionary_put(get_auction_referrer_dictionary(), &key, None::<Key>);
+    auction_ended(token_contract_hash);
+
+    unlock();
+}
+
+#[no_mangle]
+pub extern "C" fn propose_swap() -> () {
+    lock();
+
+    let proposer = get_caller_key();
+    let counterparty: Key = ARG_COUNTERPARTY.read();
+    let offered = ARG_OFFERED_TOKENS.read_token_list();
+    let requested = ARG_REQUESTED_TOKENS.read_token_list();
+    let duration_in_minutes: u64 = ARG_DURATION_MINUTES.read();
+    let sweetener_purse: Option<URef> = ARG_SWEETENER_PURSE.read_optional();
+
+    require_not_blocked(&proposer);
+    require_not_blocked(&counterparty);
+
+    if offered.is_empty() || requested.is_empty() || counterparty == proposer {
+        runtime::revert(Error::InvalidSwap);
+    }
+
+    require_tokens_transferable(&offered, proposer);
+
+    // The counterparty only has to approve the marketplace when accepting
+    for (token_contract_hash, token_id) in &requested {
+        if get_token_owner(*token_contract_hash, *token_id) != Some(counterparty) {
+            runtime::revert(Error::PermissionDenied);
+        }
+    }
+
+    let cspr_amount = match sweetener_purse {
+        Some(purse) => system::get_purse_balance(purse).unwrap_or_revert(),
+        None => U512::zero(),
+    };
+
+    if cspr_amount > U512::zero() {
+        system::transfer_from_purse_to_purse(
+            sweetener_purse.unwrap_or_revert(),
+            get_purse("offers_purse"),
+            cspr_amount,
+            None,
         )
         .unwrap_or_revert();
+    }
+
+    let swap = SwapProposal {
+        proposer,
+        counterparty,
+        offered,
+        requested,
+        cspr_amount,
+        expiration_time: get_expiration_time(duration_in_minutes),
+    };
+
+    let swap_id = next_id(SWAP_COUNT_KEY);
+    storage::dictionary_put(get_swap_dictionary(), &swap_id.to_string(), swap);
+
+    unlock();
+
+    runtime::ret(CLValue::from_t(swap_id).unwrap_or_revert())
+}
+
+#[no_mangle]
+pub extern "C" fn accept_swap() -> () {
+    lock();
+
+    let caller = get_caller_key();
+    let swap_id: u64 = ARG_SWAP_ID.read();
+
+    let swap =
+        read_swap_proposal(&swap_id.to_string()).unwrap_or_revert_with(Error::SwapDoesntExist);
+
+    if swap.counterparty != caller {
+        runtime::revert(Error::PermissionDenied);
+    }
+
+    if let Some(val) = swap.expiration_time {
+        let current_time: u64 = runtime::get_blocktime().into();
+
+        if current_time > val {
+            runtime::revert(Error::SwapExpired);
+        }
+    }
+
+    require_not_blocked(&swap.proposer);
+    require_not_blocked(&swap.counterparty);
+
+    require_tokens_transferable(&swap.offered, swap.proposer);
+    require_tokens_transferable(&swap.requested, swap.counterparty);
+
+    transfer_tokens(&swap.offered, swap.proposer, swap.counterparty);
+    transfer_tokens(&swap.requested, swap.counterparty, swap.proposer);
+
+    for (token_contract_hash, token_id) in swap.offered.iter().chain(swap.requested.iter()) {
+        clear_listing(*token_contract_hash, *token_id);
+    }
+
+    credit_balance(
+        get_purse("offers_purse"),
+        swap.counterparty,
+        swap.cspr_amount,
+    );
+
+    storage::dictionary_put(
+        get_swap_dictionary(),
+        &swap_id.to_string(),
+        None::<SwapProposal>,
+    );
+
+    unlock();
+}
+
+#[no_mangle]
+pub extern "C" fn cancel_swap() -> () {
+    lock();
+
+    let caller = get_caller_key();
+    let swap_id: u64 = ARG_SWAP_ID.read();
+
+    let swap =
+        read_swap_proposal(&swap_id.to_string()).unwrap_or_revert_with(Error::SwapDoesntExist);
+
+    if swap.proposer != caller {
+        runtime::revert(Error::PermissionDenied);
+    }
+
+    if swap.cspr_amount > U512::zero() {
+        pay_out(get_purse("offers_purse"), caller, swap.cspr_amount);
+    }
+
+    storage::dictionary_put(
+        get_swap_dictionary(),
+        &swap_id.to_string(),
+        None::<SwapProposal>,
+    );
+
+    unlock();
+}
+
+#[no_mangle]
+pub extern "C" fn get_swap() -> () {
+    let swap_id: u64 = ARG_SWAP_ID.read();
+
+    runtime::ret(CLValue::from_t(read_swap_proposal(&swap_id.to_string())).unwrap_or_revert())
+}
+
+#[no_mangle]
+pub extern "C" fn list_for_rent() -> () {
+    lock();
+
+    let caller = get_caller_key();
+    require_not_blocked(&caller);
+
+    let token_contract_hash: ContractHash = ARG_TOKEN_CONTRACT.read_token_contract();
+    let token_id: U256 = ARG_TOKEN_ID.read();
+    let price_per_day: U512 = ARG_PRICE_PER_DAY.read();
+    let max_days: u64 = ARG_MAX_DAYS.read();
+
+    if price_per_day == U512::zero() {
+        runtime::revert(Error::PriceSetToZero)
+    }
+
+    if max_days == 0 {
+        runtime::revert(Error::InvalidRentalDuration);
+    }
+
+    require_collection_allowed(token_contract_hash);
+
+    let owner = get_token_owner(token_contract_hash, token_id).unwrap_or_revert();
+
+    if owner != caller {
+        runtime::revert(Error::PermissionDenied)
+    }
+
+    if !transfer_approved(token_contract_hash, token_id, caller) {
+        runtime::revert(Error::NeedsTransferApproval);
+    }
+
+    let key = get_listing_key(token_contract_hash, token_id);
+
+    // The rented token sits in custody, it cannot back a listing or an auction at the same time
+    if read_listing_data(&key).is_some() || read_auction_data(&key).is_some() {
+        runtime::revert(Error::TokenAlreadyListed);
+    }
+
+    deposit_token(token_contract_hash, token_id, owner, &key);
+
+    let rental = RentalData {
+        owner,
+        price_per_day,
+        max_days,
+        user: None,
+        expiration_time: None,
+    };
+
+    storage::dictionary_put(get_rental_dictionary(), &key, rental);
+
+    unlock();
+}
+
+/// Rents the token for `days`, paying the full price upfront out of `buy_purse`. Only the rental
+/// price is taken, the session code returns whatever is left in the purse to the renter.
+#[no_mangle]
+pub extern "C" fn rent() -> () {
+    lock();
+
+    let renter = get_caller_key();
+    require_not_blocked(&renter);
+
+    let token_contract_hash: ContractHash = ARG_TOKEN_CONTRACT.read_token_contract();
+    let token_id: U256 = ARG_TOKEN_ID.read();
+    let days: u64 = ARG_DAYS.read();
+    let renter_purse: URef = ARG_BUY_PURSE.read();
+
+    let key = get_listing_key(token_contract_hash, token_id);
+    let mut rental = read_rental_data(&key).unwrap_or_revert_with(Error::RentalDoesntExist);
+
+    require_not_blocked(&rental.owner);
+
+    if rental.user.is_some() {
+        runtime::revert(Error::RentalActive);
+    }
+
+    if days == 0 || days > rental.max_days {
+        runtime::revert(Error::InvalidRentalDuration);
+    }
+
+    let price = rental.price_per_day * U512::from(days);
+    let purse_balance: U512 = system::get_purse_balance(renter_purse).unwrap_or_revert();
+
+    if purse_balance < price {
+        runtime::revert(Error::BalanceInsufficient);
+    }
+
+    pay_seller(
+        renter_purse,
+        token_contract_hash,
+        rental.owner,
+        renter,
+        price,
+        None,
+    );
+
+    let current_time: u64 = runtime::get_blocktime().into();
+
+    rental.user = Some(renter);
+    rental.expiration_time = Some(current_time + days * MILLISECONDS_IN_DAY);
+
+    storage::dictionary_put(get_rental_dictionary(), &key, rental);
+
+    unlock();
+}
+
+/// Returns the token to its owner. Anyone can do it once the rental expired, the owner can also
+/// take back a token nobody rented.
+#[no_mangle]
+pub extern "C" fn end_rental() -> () {
+    lock();
+
+    let caller = get_caller_key();
+
+    let token_contract_hash: ContractHash = ARG_TOKEN_CONTRACT.read_token_contract();
+    let token_id: U256 = ARG_TOKEN_ID.read();
+
+    let key = get_listing_key(token_contract_hash, token_id);
+    let rental = read_rental_data(&key).unwrap_or_revert_with(Error::RentalDoesntExist);
+
+    if rental.user.is_none() && rental.owner != caller {
+        runtime::revert(Error::PermissionDenied);
+    }
+
+    if get_rental_user(&rental).is_some() {
+        runtime::revert(Error::RentalActive);
+    }
+
+    release_token(token_contract_hash, token_id, rental.owner, &key);
+
+    storage::dictionary_put(get_rental_dictionary(), &key, None::<RentalData>);
+
+    unlock();
+}
+
+#[no_mangle]
+pub extern "C" fn get_rental() -> () {
+    let token_contract_hash: ContractHash = ARG_TOKEN_CONTRACT.read_token_contract();
+    let token_id: U256 = ARG_TOKEN_ID.read();
+
+    let key = get_listing_key(token_contract_hash, token_id);
+
+    runtime::ret(CLValue::from_t(read_rental_data(&key)).unwrap_or_revert())
+}
+
+/// Lets games check who may use a token right now.
+#[no_mangle]
+pub extern "C" fn get_user() -> () {
+    let token_contract_hash: ContractHash = ARG_TOKEN_CONTRACT.read_token_contract();
+    let token_id: U256 = ARG_TOKEN_ID.read();
+
+    let key = get_listing_key(token_contract_hash, token_id);
+    let user = read_rental_data(&key).and_then(|rental| get_rental_user(&rental));
+
+    runtime::ret(CLValue::from_t(user).unwrap_or_revert())
+}
+
+#[no_mangle]
+pub extern "C" fn create_drop() -> () {
+    let caller = get_caller_key();
+    require_not_blocked(&caller);
+
+    let token_contract_hash: ContractHash = ARG_TOKEN_CONTRACT.read_token_contract();
+    let token_meta: BTreeMap<String, String> = ARG_TOKEN_META.read();
+    let start_token_id: U256 = ARG_START_TOKEN_ID.read();
+    let inventory: Vec<U256> = ARG_INVENTORY.read();
+    let max_supply: u64 = ARG_MAX_SUPPLY.read();
+
+    require_collection_allowed(token_contract_hash);
+
+    if read_collection_creator(token_contract_hash) != Some(caller) {
+        runtime::revert(Error::PermissionDenied)
+    }
+
+    // A drop that already sold tokens cannot be replaced
+    if let Some(drop) = read_drop_data(token_contract_hash) {
+        if drop.sold > 0 {
+            runtime::revert(Error::PermissionDenied)
+        }
+    }
+
+    let max_supply = if inventory.is_empty() {
+        max_supply
+    } else {
+        let tokens: Vec<(ContractHash, U256)> = inventory
+            .iter()
+            .map(|token_id| (token_contract_hash, *token_id))
+            .collect();
+        require_tokens_transferable(&tokens, caller);
+
+        inventory.len() as u64
+    };
+
+    let drop = DropData {
+        creator: caller,
+        token_meta,
+        start_token_id,
+        inventory,
+        max_supply,
+        sold: 0,
+        phases: Vec::new(),
+    };
+
+    storage::dictionary_put(
+        get_drop_dictionary(),
+        &get_collection_key(token_contract_hash),
+        drop,
+    )
+}
+
+#[no_mangle]
+pub extern "C" fn add_drop_phase() -> () {
+    let caller = get_caller_key();
+
+    let token_contract_hash: ContractHash = ARG_TOKEN_CONTRACT.read_token_contract();
+
+    let phase = DropPhase {
+        allowlist_only: ARG_ALLOWLIST_ONLY.read(),
+        price: ARG_PRICE.read(),
+        start_time: ARG_START_TIME.read(),
+        end_time: ARG_END_TIME.read(),
+        wallet_limit: ARG_WALLET_LIMIT.read(),
+    };
+
+    let mut drop =
+        read_drop_data(token_contract_hash).unwrap_or_revert_with(Error::DropDoesntExist);
+
+    if drop.creator != caller {
+        runtime::revert(Error::PermissionDenied)
+    }
+
+    // Both ends of a phase are inclusive, so phases may not even share their first or last moment
+    let overlaps = drop
+        .phases
+        .iter()
+        .any(|other| phase.start_time <= other.end_time && other.start_time <= phase.end_time);
+
+    if phase.start_time >= phase.end_time || overlaps {
+        runtime::revert(Error::InvalidDropPhase)
+    }
+
+    drop.phases.push(phase);
+
+    storage::dictionary_put(
+        get_drop_dictionary(),
+        &get_collection_key(token_contract_hash),
+        drop,
+    )
+}
+
+#[no_mangle]
+pub extern "C" fn set_drop_allowlist() -> () {
+    let caller = get_caller_key();
+
+    let token_contract_hash: ContractHash = ARG_TOKEN_CONTRACT.read_token_contract();
+    let accounts: Vec<Key> = ARG_ACCOUNTS.read();
+    let allowed: bool = ARG_ALLOWED.read();
+
+    let drop = read_drop_data(token_contract_hash).unwrap_or_revert_with(Error::DropDoesntExist);
+
+    if drop.creator != caller {
+        runtime::revert(Error::PermissionDenied)
+    }
+
+    for account in accounts {
+        set_allowlisted(token_contract_hash, &account, allowed);
+    }
+}
+
+/// Buys `quantity` tokens in the first open phase the buyer is eligible for. Only the total price
+/// is taken from `buy_purse`, the session code returns whatever is left to the buyer.
+#[no_mangle]
+pub extern "C" fn buy_drop() -> () {
+    lock();
+
+    let buyer = get_caller_key();
+    require_not_blocked(&buyer);
+
+    let token_contract_hash: ContractHash = ARG_TOKEN_CONTRACT.read_token_contract();
+    let quantity: u64 = ARG_QUANTITY.read();
+    let buyer_purse: URef = ARG_BUY_PURSE.read();
+
+    if quantity == 0 {
+        runtime::revert(Error::InvalidQuantity);
+    }
+
+    let mut drop =
+        read_drop_data(token_contract_hash).unwrap_or_revert_with(Error::DropDoesntExist);
 
+    require_not_blocked(&drop.creator);
+
+    let current_time: u64 = runtime::get_blocktime().into();
+
+    let (phase_index, phase) = find_active_phase(&drop, token_contract_hash, &buyer, current_time)
+        .unwrap_or_revert_with(Error::DropNotActive);
+
+    let sold = drop
+        .sold
+        .checked_add(quantity)
+        .unwrap_or_revert_with(Error::DropSoldOut);
+
+    if sold > drop.max_supply {
+        runtime::revert(Error::DropSoldOut);
+    }
+
+    let phase_mints = get_phase_mints(token_contract_hash, phase_index, &buyer)
+        .checked_add(quantity)
+        .unwrap_or_revert_with(Error::DropSoldOut);
+
+    if phase.wallet_limit > 0 && phase_mints > phase.wallet_limit {
+        runtime::revert(Error::WalletLimitReached);
+    }
+
+    let total_price = phase.price * U512::from(quantity);
+    let purse_balance: U512 = system::get_purse_balance(buyer_purse).unwrap_or_revert();
+
+    if purse_balance < total_price {
+        runtime::revert(Error::BalanceInsufficient);
+    }
+
+    pay_creator(buyer_purse, drop.creator, total_price);
+
+    let first = drop.sold as usize;
+    let last = sold as usize;
+
+    if !drop.inventory.is_empty() && last > drop.inventory.len() {
+        runtime::revert(Error::DropSoldOut);
+    }
+
+    let token_ids: Vec<U256> = if drop.inventory.is_empty() {
+        (first..last)
+            .map(|index| drop.start_token_id + U256::from(index))
+            .collect()
+    } else {
+        drop.inventory[first..last].to_vec()
+    };
+
+    if drop.inventory.is_empty() {
+        runtime::call_contract::<()>(
+            token_contract_hash,
+            "mint",
+            runtime_args! {
+              "recipient" => buyer,
+              "token_metas" => vec![drop.token_meta.clone(); token_ids.len()],
+              "token_ids" => token_ids.clone(),
+            },
+        );
+    } else {
         runtime::call_contract::<()>(
             token_contract_hash,
             "transfer_from",
             runtime_args! {
-              "sender" => Key::Account(auction_data.seller),
-              "recipient" => Key::Account(auction_data.current_winner),
-              "token_ids" => vec![token_id],
+              "sender" => drop.creator,
+              "recipient" => buyer,
+              "token_ids" => token_ids.clone(),
             },
         );
     }
 
-    storage::dictionary_put(get_listing_dictionary(), &key, None::<AuctionData>)
+    for token_id in token_ids {
+        record_sale(
+            token_contract_hash,
+            token_id,
+            drop.creator,
+            buyer,
+            phase.price,
+            SALE_TYPE_DROP,
+        );
+    }
+
+    drop.sold = sold;
+    set_phase_mints(token_contract_hash, phase_index, &buyer, phase_mints);
+
+    storage::dictionary_put(
+        get_drop_dictionary(),
+        &get_collection_key(token_contract_hash),
+        drop,
+    );
+
+    unlock();
+}
+
+#[no_mangle]
+pub extern "C" fn get_drop() -> () {
+    let token_contract_hash: ContractHash = ARG_TOKEN_CONTRACT.read_token_contract();
+
+    runtime::ret(CLValue::from_t(read_drop_data(token_contract_hash)).unwrap_or_revert())
+}
+
+#[no_mangle]
+pub extern "C" fn withdraw() -> () {
+    lock();
+
+    let caller = get_caller_key();
+    let target_purse: Option<URef> = ARG_TARGET_PURSE.read_optional();
+
+    let amount = get_pending_balance(&caller);
+
+    if amount == U512::zero() {
+        runtime::revert(Error::NothingToWithdraw);
+    }
+
+    storage::dictionary_put(get_balance_dictionary(), &key_to_str(&caller), U512::zero());
+
+    let proceeds_purse = get_purse(PROCEEDS_PURSE);
+
+    match target_purse {
+        Some(purse) => {
+            system::transfer_from_purse_to_purse(proceeds_purse, purse, amount, None)
+                .unwrap_or_revert();
+        }
+        None => match caller {
+            Key::Account(account_hash) => {
+                system::transfer_from_purse_to_account(proceeds_purse, account_hash, amount, None)
+                    .unwrap_or_revert();
+            }
+            // Contracts have no main purse to fall back to
+            _ => runtime::revert(Error::TargetPurseRequired),
+        },
+    }
+
+    unlock();
+}
+
+#[no_mangle]
+pub extern "C" fn get_balance() -> () {
+    let owner: Key = ARG_OWNER.read();
+
+    runtime::ret(CLValue::from_t(get_pending_balance(&owner)).unwrap_or_revert())
+}
+
+#[no_mangle]
+pub extern "C" fn set_marketplace_fee() -> () {
+    require_admin();
+
+    let fee_basis_points: u32 = ARG_FEE_BASIS_POINTS.read();
+    let fee_recipient: Key = ARG_FEE_RECIPIENT.read();
+
+    if fee_basis_points > MAX_FEE_BASIS_POINTS {
+        runtime::revert(Error::InvalidFee);
+    }
+
+    write_config(MARKETPLACE_FEE_KEY, fee_basis_points);
+    write_config(FEE_RECIPIENT_KEY, fee_recipient);
+}
+
+/// Share of the marketplace fee, in basis points of the fee, paid to the referrer of a sale.
+#[no_mangle]
+pub extern "C" fn set_referral_share() -> () {
+    require_admin();
+
+    let referral_share: u32 = ARG_REFERRAL_SHARE_BASIS_POINTS.read();
+
+    if referral_share > MAX_FEE_BASIS_POINTS {
+        runtime::revert(Error::InvalidFee);
+    }
+
+    write_config(REFERRAL_SHARE_KEY, referral_share);
+}
+
+#[no_mangle]
+pub extern "C" fn get_referral_earnings() -> () {
+    let referrer: Key = ARG_REFERRAL_ACCOUNT.read();
+
+    runtime::ret(CLValue::from_t(read_referral_earnings(&referrer)).unwrap_or_revert())
+}
+
+/// Overrides the marketplace fee for one collection, `None` falls back to the global fee again.
+#[no_mangle]
+pub extern "C" fn set_collection_fee() -> () {
+    require_admin();
+
+    let token_contract_hash: ContractHash = ARG_TOKEN_CONTRACT.read_token_contract();
+    let fee_basis_points: Option<u32> = ARG_COLLECTION_FEE_BASIS_POINTS.read();
+
+    let key = get_collection_key(token_contract_hash);
+
+    match fee_basis_points {
+        Some(fee_basis_points) => {
+            if fee_basis_points > MAX_FEE_BASIS_POINTS {
+                runtime::revert(Error::InvalidFee);
+            }
+
+            storage::dictionary_put(get_collection_fee_dictionary(), &key, fee_basis_points)
+        }
+        None => storage::dictionary_put(get_collection_fee_dictionary(), &key, None::<u32>),
+    }
+}
+
+/// Sales where the seller or the buyer is exempt pay no marketplace fee.
+#[no_mangle]
+pub extern "C" fn set_fee_exempt() -> () {
+    require_admin();
+
+    let account: Key = ARG_ACCOUNT.read();
+    let exempt: bool = ARG_EXEMPT.read();
+
+    storage::dictionary_put(get_fee_exempt_dictionary(), &key_to_str(&account), exempt);
+}
+
+#[no_mangle]
+pub extern "C" fn get_fee_quote() -> () {
+    let token_contract_hash: ContractHash = ARG_TOKEN_CONTRACT.read_token_contract();
+    let price: U512 = ARG_PRICE.read();
+    let seller: Key = ARG_SELLER.read();
+    let buyer: Key = ARG_BUYER.read();
+
+    let quote = quote_sale(token_contract_hash, &seller, &buyer, price);
+    runtime::ret(CLValue::from_t(quote).unwrap_or_revert())
+}
+
+#[no_mangle]
+pub extern "C" fn get_sale() -> () {
+    let sale_id: u64 = ARG_SALE_ID.read();
+
+    runtime::ret(CLValue::from_t(read_sale(&sale_id.to_string())).unwrap_or_revert())
+}
+
+#[no_mangle]
+pub extern "C" fn get_sale_count() -> () {
+    runtime::ret(CLValue::from_t(read_sale_count()).unwrap_or_revert())
+}
+
+#[no_mangle]
+pub extern "C" fn get_last_sale_price() -> () {
+    let token_contract_hash: ContractHash = ARG_TOKEN_CONTRACT.read_token_contract();
+    let token_id: U256 = ARG_TOKEN_ID.read();
+
+    let last_sale_price = read_last_sale_price(token_contract_hash, token_id);
+    runtime::ret(CLValue::from_t(last_sale_price).unwrap_or_revert())
+}
+
+#[no_mangle]
+pub extern "C" fn get_collection_volume() -> () {
+    let token_contract_hash: ContractHash = ARG_TOKEN_CONTRACT.read_token_contract();
+
+    runtime::ret(CLValue::from_t(read_collection_volume(token_contract_hash)).unwrap_or_revert())
+}
+
+#[no_mangle]
+pub extern "C" fn get_collection_stats() -> () {
+    let token_contract_hash: ContractHash = ARG_TOKEN_CONTRACT.read_token_contract();
+
+    runtime::ret(CLValue::from_t(read_collection_stats(token_contract_hash)).unwrap_or_revert())
+}
+
+/// Volume of the collection on the given UTC day, counted in days since the Unix epoch.
+#[no_mangle]
+pub extern "C" fn get_daily_volume() -> () {
+    let token_contract_hash: ContractHash = ARG_TOKEN_CONTRACT.read_token_contract();
+    let day: u64 = ARG_DAY.read();
+
+    runtime::ret(CLValue::from_t(read_daily_volume(token_contract_hash, day)).unwrap_or_revert())
+}
+
+#[no_mangle]
+pub extern "C" fn set_curator() -> () {
+    require_admin();
+
+    let curator: Key = ARG_CURATOR.read();
+    write_config(CURATOR_KEY, curator);
+}
+
+#[no_mangle]
+pub extern "C" fn set_allowlist_mode() -> () {
+    require_admin();
+
+    let enabled: bool = ARG_ENABLED.read();
+    write_config(ALLOWLIST_MODE_KEY, enabled);
+}
+
+#[no_mangle]
+pub extern "C" fn register_collection() -> () {
+    require_curator();
+
+    let token_contract_hash: ContractHash = ARG_TOKEN_CONTRACT.read_token_contract();
+    let name: String = ARG_NAME.read();
+    let verified: bool = ARG_VERIFIED.read();
+    let royalty_recipient: Option<Key> = ARG_ROYALTY_RECIPIENT.read();
+    let royalties_percentage: u8 = ARG_ROYALTIES_PERCENTAGE.read();
+
+    if royalties_percentage > MAX_ROYALTIES_PERCENTAGE {
+        runtime::revert(Error::InvalidRoyalties);
+    }
+
+    let collection = CollectionData {
+        name,
+        verified,
+        royalty_recipient,
+        royalties_percentage,
+    };
+
+    storage::dictionary_put(
+        get_collection_dictionary(),
+        &get_collection_key(token_contract_hash),
+        collection,
+    )
 }
 
+/// Changes the royalty a registered collection takes from resales, keeping its recipient.
 #[no_mangle]
 pub extern "C" fn set_royalties() -> () {
-    let _token_contract_string: String = runtime::get_named_arg(ARG_TOKEN_CONTRACT);
-    let _token_id: U256 = runtime::get_named_arg(ARG_ROYALTIES_PERCENTAGE);
+    require_curator();
+
+    let token_contract_hash: ContractHash = ARG_TOKEN_CONTRACT.read_token_contract();
+    let royalties_percentage: u8 = ARG_ROYALTIES_PERCENTAGE.read();
+
+    if royalties_percentage > MAX_ROYALTIES_PERCENTAGE {
+        runtime::revert(Error::InvalidRoyalties);
+    }
+
+    let mut collection = read_collection_data(token_contract_hash)
+        .unwrap_or_revert_with(Error::CollectionNotAllowed);
+    collection.royalties_percentage = royalties_percentage;
+
+    storage::dictionary_put(
+        get_collection_dictionary(),
+        &get_collection_key(token_contract_hash),
+        collection,
+    )
+}
+
+#[no_mangle]
+pub extern "C" fn remove_collection() -> () {
+    require_curator();
+
+    let token_contract_hash: ContractHash = ARG_TOKEN_CONTRACT.read_token_contract();
+
+    storage::dictionary_put(
+        get_collection_dictionary(),
+        &get_collection_key(token_contract_hash),
+        None::<CollectionData>,
+    )
+}
+
+#[no_mangle]
+pub extern "C" fn get_collection() -> () {
+    let token_contract_hash: ContractHash = ARG_TOKEN_CONTRACT.read_token_contract();
+
+    runtime::ret(CLValue::from_t(read_collection_data(token_contract_hash)).unwrap_or_revert())
+}
+
+#[no_mangle]
+pub extern "C" fn set_collection_creator() -> () {
+    require_curator();
+
+    let token_contract_hash: ContractHash = ARG_TOKEN_CONTRACT.read_token_contract();
+    let creator: Option<Key> = ARG_CREATOR.read();
+
+    let key = get_collection_key(token_contract_hash);
+
+    match creator {
+        Some(creator) => storage::dictionary_put(get_creator_dictionary(), &key, creator),
+        None => storage::dictionary_put(get_creator_dictionary(), &key, None::<Key>),
+    }
+}
+
+/// Records `contract_hash` as the current version of the collection package `package_hash`, so
+/// the collection may be passed by its package hash. Has to be repeated when the collection is
+/// upgraded.
+#[no_mangle]
+pub extern "C" fn set_collection_package() -> () {
+    require_curator();
+
+    let package_hash: ContractPackageHash = ARG_PACKAGE_HASH.read();
+    let token_contract_hash: ContractHash = ARG_TOKEN_CONTRACT.read_token_contract();
+
+    storage::dictionary_put(
+        get_collection_package_dictionary(),
+        &hex::encode(package_hash.value()),
+        Some(token_contract_hash),
+    )
+}
+
+#[no_mangle]
+pub extern "C" fn set_blocked() -> () {
+    require_admin();
+
+    let account: Key = ARG_ACCOUNT.read();
+    let blocked: bool = ARG_BLOCKED.read();
+
+    storage::dictionary_put(get_blocklist_dictionary(), &key_to_str(&account), blocked)
+}
+
+#[no_mangle]
+pub extern "C" fn get_blocked() -> () {
+    let account: Key = ARG_ACCOUNT.read();
+
+    runtime::ret(CLValue::from_t(is_blocked(&account)).unwrap_or_revert())
+}
+
+#[no_mangle]
+pub extern "C" fn get_listing() -> () {
+    let token_contract_hash: ContractHash = ARG_TOKEN_CONTRACT.read_token_contract();
+    let token_id: U256 = ARG_TOKEN_ID.read();
+
+    let key = get_listing_key(token_contract_hash, token_id);
+
+    runtime::ret(CLValue::from_t(read_listing_data(&key)).unwrap_or_revert())
+}
+
+#[no_mangle]
+pub extern "C" fn get_lazy_listing() -> () {
+    let token_contract_hash: ContractHash = ARG_TOKEN_CONTRACT.read_token_contract();
+    let token_id: U256 = ARG_TOKEN_ID.read();
+
+    let key = get_listing_key(token_contract_hash, token_id);
+
+    runtime::ret(CLValue::from_t(read_lazy_listing(&key)).unwrap_or_revert())
+}
+
+#[no_mangle]
+pub extern "C" fn get_offer() -> () {
+    let token_contract_hash: ContractHash = ARG_TOKEN_CONTRACT.read_token_contract();
+    let token_id: U256 = ARG_TOKEN_ID.read();
+    let offerer: Key = ARG_OFFERER.read_key();
+
+    let key = get_offer_key(token_contract_hash, token_id, &offerer);
+    let offer = read_offer_data(&key)
+        .or_else(|| read_legacy_offer(token_contract_hash, token_id, &offerer));
+
+    runtime::ret(CLValue::from_t(offer).unwrap_or_revert())
+}
+
+#[no_mangle]
+pub extern "C" fn get_auction() -> () {
+    let token_contract_hash: ContractHash = ARG_TOKEN_CONTRACT.read_token_contract();
+    let token_id: U256 = ARG_TOKEN_ID.read();
+
+    let key = get_listing_key(token_contract_hash, token_id);
+
+    runtime::ret(CLValue::from_t(read_auction_data(&key)).unwrap_or_revert())
+}
+
+#[no_mangle]
+pub extern "C" fn get_token_state() -> () {
+    let token_contract_hash: ContractHash = ARG_TOKEN_CONTRACT.read_token_contract();
+    let token_id: U256 = ARG_TOKEN_ID.read();
+
+    let key = get_listing_key(token_contract_hash, token_id);
+
+    let token_state = TokenState {
+        owner: get_token_owner(token_contract_hash, token_id),
+        listing: read_listing_data(&key),
+        auction: read_auction_data(&key),
+    };
+
+    runtime::ret(CLValue::from_t(token_state).unwrap_or_revert())
+}
+
+/// Rewrites auctions and offers stored before every party became a `Key`. Dictionaries cannot be
+/// enumerated on chain, so the admin names the records. Any left out are still read and get
+/// migrated the first time they change.
+#[no_mangle]
+pub extern "C" fn migrate_storage() -> () {
+    require_admin();
+
+    for (token_contract_hash, token_id) in ARG_AUCTIONS.read_token_list() {
+        migrate_auction(&get_listing_key(token_contract_hash, token_id));
+    }
+
+    for (token_contract_hash, token_id, offerer) in ARG_OFFERS.read_offer_list() {
+        migrate_offer(token_contract_hash, token_id, &offerer);
+    }
 }
 
 #[no_mangle]
 pub extern "C" fn call() {
     let mut counter_named_keys = NamedKeys::new();
-
-    let (stored_contract_hash, contract_version) = storage::new_contract(
-        get_entry_points(),
-        Some(counter_named_keys),
-        Some(CONTRACT_PACKAGE_NAME.to_string()),
-        Some(CONTRACT_ACCESS_UREF.to_string()),
+    counter_named_keys.insert(
+        ADMIN_KEY.to_string(),
+        storage::new_uref(Key::Account(runtime::get_caller())).into(),
     );
 
-    let version_uref = storage::new_uref(contract_version);
-    runtime::put_key(CONTRACT_VERSION_KEY, version_uref.into());
-    runtime::put_key(CONTRACT_KEY, stored_contract_hash.into());
+    match runtime::get_key(CONTRACT_PACKAGE_NAME) {
+        // Upgrade. The new version inherits the named keys of the current one, whose values win
+        // over the ones passed here, so the admin is only set if the old version had none.
+        Some(package_key) => {
+            let contract_package_hash =
+                ContractPackageHash::new(package_key.into_hash().unwrap_or_revert());
+            // Passed on as sent, `migrate_storage` parses them. Both default to an empty list.
+            let mut migrate_args = RuntimeArgs::new();
+
+            for param in [ARG_AUCTIONS, ARG_OFFERS] {
+                let bytes = get_named_arg_bytes(param.0.name)
+                    .unwrap_or_else(|| Vec::<Key>::new().into_bytes().unwrap_or_revert());
+
+                migrate_args
+                    .insert_cl_value(param.0.name, CLValue::from_components(CLType::Any, bytes));
+            }
+
+            let previous_contract_hash = runtime::get_key(CONTRACT_KEY)
+                .and_then(Key::into_hash)
+                .map(ContractHash::new)
+                .unwrap_or_revert();
+
+            let (stored_contract_hash, contract_version) = storage::add_contract_version(
+                contract_package_hash,
+                get_entry_points(),
+                counter_named_keys,
+            );
+
+            // Callers of the old version would bypass the migrated storage layout
+            storage::disable_contract_version(contract_package_hash, previous_contract_hash)
+                .unwrap_or_revert();
+
+            let version_uref = storage::new_uref(contract_version);
+            runtime::put_key(CONTRACT_VERSION_KEY, version_uref.into());
+            runtime::put_key(CONTRACT_KEY, stored_contract_hash.into());
+
+            runtime::call_contract::<()>(stored_contract_hash, "migrate_storage", migrate_args);
+        }
+        None => {
+            let (stored_contract_hash, contract_version) = storage::new_contract(
+                get_entry_points(),
+                Some(counter_named_keys),
+                Some(CONTRACT_PACKAGE_NAME.to_string()),
+                Some(CONTRACT_ACCESS_UREF.to_string()),
+            );
+
+            let version_uref = storage::new_uref(contract_version);
+            runtime::put_key(CONTRACT_VERSION_KEY, version_uref.into());
+            runtime::put_key(CONTRACT_KEY, stored_contract_hash.into());
+        }
+    }
 }
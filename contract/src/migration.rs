@@ -0,0 +1,136 @@
+use alloc::{format, string::String, vec::Vec};
+use casper_contract::contract_api::{runtime, storage};
+use casper_types::{account::AccountHash, ContractHash, Key, URef, U256, U512};
+use casper_types_derive::{CLTyped, FromBytes, ToBytes};
+
+use crate::{
+    utils::{get_acutin_dictionary, get_dictionary_uref, get_offer_dictionary, get_offer_key},
+    AuctionData, OfferData,
+};
+
+// Before every party was stored as a `Key`, auctions recorded the seller and the winner as an
+// `AccountHash` and offers were keyed by the offerer's `AccountHash`. Such records are still read,
+// and are rewritten in the current schema the first time they change or when the admin migrates
+// them through `migrate_storage`.
+//
+// The first release never cleared an auction once it ended, so one that was settled cannot be told
+// apart from one nobody ended. Legacy auctions past their end time are dropped instead of
+// migrated: settling them again would pay the winner twice out of `auctions_purse`.
+
+/// Auction as it was stored before parties became `Key`s.
+#[derive(CLTyped, ToBytes, FromBytes)]
+pub struct LegacyAuctionData {
+    pub seller: AccountHash,
+    pub starting_price: U512,
+    pub current_bid: U512,
+    pub current_winner: AccountHash,
+    pub end_time: u64,
+}
+
+impl From<LegacyAuctionData> for AuctionData {
+    fn from(auction: LegacyAuctionData) -> Self {
+        AuctionData {
+            seller: Key::Account(auction.seller),
+            starting_price: auction.starting_price,
+            current_bid: auction.current_bid,
+            current_winner: Key::Account(auction.current_winner),
+            end_time: auction.end_time,
+        }
+    }
+}
+
+fn get_legacy_auction_dictionary() -> URef {
+    get_dictionary_uref("auctions")
+}
+
+/// Offer key the marketplace used while offers could only be made by accounts.
+pub fn get_legacy_offer_key(
+    token_contract_hash: ContractHash,
+    token_id: U256,
+    offerer: AccountHash,
+) -> String {
+    let key_string = format!("{}_{}_{}", token_contract_hash, offerer, token_id);
+    hex::encode(runtime::blake2b(key_string))
+}
+
+fn read_legacy_auction_entry(key: &str) -> Option<LegacyAuctionData> {
+    match storage::dictionary_get(get_legacy_auction_dictionary(), key) {
+        Ok(item) => item,
+        Err(_error) => None,
+    }
+}
+
+fn legacy_auction_ended(auction: &LegacyAuctionData) -> bool {
+    let current_time: u64 = runtime::get_blocktime().into();
+    current_time >= auction.end_time
+}
+
+/// Legacy auction stored under `key`, unless it has already ended.
+pub fn read_legacy_auction(key: &str) -> Option<LegacyAuctionData> {
+    read_legacy_auction_entry(key).filter(|auction| !legacy_auction_ended(auction))
+}
+
+fn legacy_offer_key(
+    token_contract_hash: ContractHash,
+    token_id: U256,
+    offerer: &Key,
+) -> Option<String> {
+    offerer
+        .into_account()
+        .map(|account_hash| get_legacy_offer_key(token_contract_hash, token_id, account_hash))
+}
+
+pub fn read_legacy_offer(
+    token_contract_hash: ContractHash,
+    token_id: U256,
+    offerer: &Key,
+) -> Option<OfferData> {
+    let legacy_key = legacy_offer_key(token_contract_hash, token_id, offerer)?;
+
+    match storage::dictionary_get(get_offer_dictionary(), &legacy_key) {
+        Ok(item) => item,
+        Err(_error) => None,
+    }
+}
+
+/// Moves the auction stored under `key` from the legacy dictionary into the current one, dropping
+/// it if it has already ended. Returns whether there was anything to move.
+pub fn migrate_auction(key: &str) -> bool {
+    match read_legacy_auction_entry(key) {
+        Some(auction) => {
+            let ended = legacy_auction_ended(&auction);
+            if !ended {
+                storage::dictionary_put(get_acutin_dictionary(), key, AuctionData::from(auction));
+            }
+            storage::dictionary_put(
+                get_legacy_auction_dictionary(),
+                key,
+                None::<LegacyAuctionData>,
+            );
+            !ended
+        }
+        None => false,
+    }
+}
+
+/// Moves an offer from its legacy key to the key derived from the offerer's `Key`. Returns whether
+/// there was anything to move.
+pub fn migrate_offer(token_contract_hash: ContractHash, token_id: U256, offerer: &Key) -> bool {
+    let legacy_key = match legacy_offer_key(token_contract_hash, token_id, offerer) {
+        Some(legacy_key) => legacy_key,
+        None => return false,
+    };
+
+    match read_legacy_offer(token_contract_hash, token_id, offerer) {
+        Some(offer) => {
+            storage::dictionary_put(
+                get_offer_dictionary(),
+                &get_offer_key(token_contract_hash, token_id, offerer),
+                offer,
+            );
+            storage::dictionary_put(get_offer_dictionary(), &legacy_key, None::<OfferData>);
+            true
+        }
+        None => false,
+    }
+}
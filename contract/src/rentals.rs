@@ -0,0 +1,27 @@
+use casper_contract::contract_api::{runtime, storage};
+use casper_types::{Key, URef};
+
+use crate::{utils::get_dictionary_uref, RentalData};
+
+pub fn get_rental_dictionary() -> URef {
+    get_dictionary_uref("rentals")
+}
+
+/// Rental offer stored under a listing key, `None` once the token went back to its owner.
+pub fn read_rental_data(key: &str) -> Option<RentalData> {
+    match storage::dictionary_get(get_rental_dictionary(), key) {
+        Ok(item) => item,
+        Err(_error) => None,
+    }
+}
+
+/// Whoever holds the usage rights to the token right now. Expired rentals have no user even
+/// before the token is returned.
+pub fn get_rental_user(rental: &RentalData) -> Option<Key> {
+    let current_time: u64 = runtime::get_blocktime().into();
+
+    match (rental.user, rental.expiration_time) {
+        (Some(user), Some(expiration_time)) if current_time <= expiration_time => Some(user),
+        _ => None,
+    }
+}
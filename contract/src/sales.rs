@@ -0,0 +1,107 @@
+use alloc::string::ToString;
+use casper_contract::{
+    contract_api::{runtime, storage},
+    unwrap_or_revert::UnwrapOrRevert,
+};
+use casper_types::{ContractHash, Key, URef, U256, U512};
+
+use crate::{
+    stats::sale_completed,
+    utils::{get_collection_key, get_dictionary_uref, get_listing_key, next_id},
+    SaleRecord,
+};
+
+pub const SALE_COUNT_KEY: &str = "sale_count";
+
+pub const SALE_TYPE_LISTING: u8 = 0;
+pub const SALE_TYPE_OFFER: u8 = 1;
+pub const SALE_TYPE_AUCTION: u8 = 2;
+pub const SALE_TYPE_DROP: u8 = 3;
+
+pub fn get_sale_dictionary() -> URef {
+    get_dictionary_uref("sales")
+}
+
+pub fn get_last_sale_dictionary() -> URef {
+    get_dictionary_uref("last_sale_prices")
+}
+
+pub fn get_collection_volume_dictionary() -> URef {
+    get_dictionary_uref("collection_volume")
+}
+
+pub fn read_sale(sale_id: &str) -> Option<SaleRecord> {
+    match storage::dictionary_get(get_sale_dictionary(), sale_id) {
+        Ok(item) => item,
+        Err(_error) => None,
+    }
+}
+
+/// Number of sales recorded so far, which is also the id the next sale gets.
+pub fn read_sale_count() -> u64 {
+    match runtime::get_key(SALE_COUNT_KEY) {
+        Some(key) => storage::read(key.into_uref().unwrap_or_revert())
+            .unwrap_or_revert()
+            .unwrap_or_default(),
+        None => 0,
+    }
+}
+
+pub fn read_last_sale_price(token_contract_hash: ContractHash, token_id: U256) -> Option<U512> {
+    match storage::dictionary_get(
+        get_last_sale_dictionary(),
+        &get_listing_key(token_contract_hash, token_id),
+    ) {
+        Ok(item) => item,
+        Err(_error) => None,
+    }
+}
+
+pub fn read_collection_volume(token_contract_hash: ContractHash) -> U512 {
+    storage::dictionary_get(
+        get_collection_volume_dictionary(),
+        &get_collection_key(token_contract_hash),
+    )
+    .unwrap_or_revert()
+    .unwrap_or_default()
+}
+
+/// Appends a sale to the ledger and updates the last sale price of the token and the volume and
+/// statistics of its collection. Returns the id of the sale.
+pub fn record_sale(
+    token_contract_hash: ContractHash,
+    token_id: U256,
+    seller: Key,
+    buyer: Key,
+    price: U512,
+    sale_type: u8,
+) -> u64 {
+    let sale = SaleRecord {
+        token_contract: token_contract_hash,
+        token_id,
+        seller,
+        buyer,
+        price,
+        payment_asset: None,
+        timestamp: runtime::get_blocktime().into(),
+        sale_type,
+    };
+
+    let sale_id = next_id(SALE_COUNT_KEY);
+    storage::dictionary_put(get_sale_dictionary(), &sale_id.to_string(), sale);
+
+    storage::dictionary_put(
+        get_last_sale_dictionary(),
+        &get_listing_key(token_contract_hash, token_id),
+        price,
+    );
+
+    storage::dictionary_put(
+        get_collection_volume_dictionary(),
+        &get_collection_key(token_contract_hash),
+        read_collection_volume(token_contract_hash) + price,
+    );
+    sale_completed(token_contract_hash, price);
+
+    sale_id
+}
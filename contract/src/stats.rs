@@ -0,0 +1,329 @@
+use alloc::{
+    format,
+    string::{String, ToString},
+    vec::Vec,
+};
+use casper_contract::{
+    contract_api::{runtime, storage},
+    unwrap_or_revert::UnwrapOrRevert,
+};
+use casper_types::{ContractHash, URef, U256, U512};
+
+use crate::{
+    listing_index::{get_collection_owner, index_len, is_expired, COLLECTION_INDEX, MAX_PAGE_SIZE},
+    sales::read_collection_volume,
+    utils::{get_collection_key, get_dictionary_uref, get_listing_key, read_listing_data},
+    CollectionStats, MILLISECONDS_IN_DAY,
+};
+
+// The listings of a collection are also kept in a binary min-heap ordered by price, so the floor
+// is found without walking the collection index. The heap is split over three dictionaries like
+// the listing indexes: `collection_floor_len` holds its size, `collection_floor_items` the price
+// and token at each position and `collection_floor_pos` the position of each token.
+
+fn get_floor_len_dictionary() -> URef {
+    get_dictionary_uref("collection_floor_len")
+}
+
+fn get_floor_items_dictionary() -> URef {
+    get_dictionary_uref("collection_floor_items")
+}
+
+fn get_floor_positions_dictionary() -> URef {
+    get_dictionary_uref("collection_floor_pos")
+}
+
+fn get_sales_count_dictionary() -> URef {
+    get_dictionary_uref("collection_sales")
+}
+
+fn get_auction_count_dictionary() -> URef {
+    get_dictionary_uref("collection_auctions")
+}
+
+fn get_daily_volume_dictionary() -> URef {
+    get_dictionary_uref("collection_daily_volume")
+}
+
+/// Dictionary item keys are limited to 64 bytes, so composite keys are hashed.
+fn get_day_key(token_contract_hash: ContractHash, day: u64) -> String {
+    let key_string = format!("{}_{}", get_collection_key(token_contract_hash), day);
+    hex::encode(runtime::blake2b(key_string))
+}
+
+/// Index of the UTC day the current block belongs to.
+pub fn current_day() -> u64 {
+    let current_time: u64 = runtime::get_blocktime().into();
+    current_time / MILLISECONDS_IN_DAY
+}
+
+fn read_counter(dictionary: URef, token_contract_hash: ContractHash) -> u64 {
+    storage::dictionary_get(dictionary, &get_collection_key(token_contract_hash))
+        .unwrap_or_revert()
+        .unwrap_or_default()
+}
+
+fn write_counter(dictionary: URef, token_contract_hash: ContractHash, value: u64) {
+    storage::dictionary_put(dictionary, &get_collection_key(token_contract_hash), value)
+}
+
+/// Dictionary item keys are limited to 64 bytes, so composite keys are hashed.
+fn get_floor_key(token_contract_hash: ContractHash, suffix: &str) -> String {
+    let key_string = format!("{}_{}", get_collection_key(token_contract_hash), suffix);
+    hex::encode(runtime::blake2b(key_string))
+}
+
+fn floor_len(token_contract_hash: ContractHash) -> u64 {
+    storage::dictionary_get(
+        get_floor_len_dictionary(),
+        &get_collection_key(token_contract_hash),
+    )
+    .unwrap_or_revert()
+    .unwrap_or_default()
+}
+
+fn set_floor_len(token_contract_hash: ContractHash, len: u64) {
+    storage::dictionary_put(
+        get_floor_len_dictionary(),
+        &get_collection_key(token_contract_hash),
+        len,
+    )
+}
+
+fn floor_position(token_contract_hash: ContractHash, token_id: U256) -> Option<u64> {
+    storage::dictionary_get::<Option<u64>>(
+        get_floor_positions_dictionary(),
+        &get_floor_key(token_contract_hash, &format!("token_{}", token_id)),
+    )
+    .unwrap_or_revert()
+    .flatten()
+}
+
+fn set_floor_position(token_contract_hash: ContractHash, token_id: U256, position: Option<u64>) {
+    storage::dictionary_put(
+        get_floor_positions_dictionary(),
+        &get_floor_key(token_contract_hash, &format!("token_{}", token_id)),
+        position,
+    )
+}
+
+/// Price and token id at `position` of the heap.
+fn floor_item(token_contract_hash: ContractHash, position: u64) -> (U512, U256) {
+    storage::dictionary_get(
+        get_floor_items_dictionary(),
+        &get_floor_key(token_contract_hash, &position.to_string()),
+    )
+    .unwrap_or_revert()
+    .unwrap_or_revert()
+}
+
+fn set_floor_item(token_contract_hash: ContractHash, position: u64, item: (U512, U256)) {
+    storage::dictionary_put(
+        get_floor_items_dictionary(),
+        &get_floor_key(token_contract_hash, &position.to_string()),
+        item,
+    );
+    set_floor_position(token_contract_hash, item.1, Some(position));
+}
+
+/// Moves the item at `position` towards the root while it is cheaper than its parent.
+fn sift_up(token_contract_hash: ContractHash, mut position: u64) {
+    let item = floor_item(token_contract_hash, position);
+
+    while position > 0 {
+        let parent_position = (position - 1) / 2;
+        let parent = floor_item(token_contract_hash, parent_position);
+
+        if parent.0 <= item.0 {
+            break;
+        }
+
+        set_floor_item(token_contract_hash, position, parent);
+        position = parent_position;
+    }
+
+    set_floor_item(token_contract_hash, position, item);
+}
+
+/// Moves the item at `position` towards the leaves while one of its children is cheaper.
+fn sift_down(token_contract_hash: ContractHash, mut position: u64, len: u64) {
+    let item = floor_item(token_contract_hash, position);
+
+    loop {
+        let mut cheapest = (position, item);
+
+        for child_position in [2 * position + 1, 2 * position + 2] {
+            if child_position < len {
+                let child = floor_item(token_contract_hash, child_position);
+
+                if child.0 < cheapest.1 .0 {
+                    cheapest = (child_position, child);
+                }
+            }
+        }
+
+        if cheapest.0 == position {
+            break;
+        }
+
+        set_floor_item(token_contract_hash, position, cheapest.1);
+        position = cheapest.0;
+    }
+
+    set_floor_item(token_contract_hash, position, item);
+}
+
+/// Price of the cheapest listing that has not expired, `None` when there is none. Expired
+/// listings stay in the heap until `remove_expired_listing` is called, so the heap is searched
+/// cheapest first past them, visiting at most `MAX_PAGE_SIZE` entries. When that many expired
+/// listings are cheaper, the cheapest entry left unchecked is reported; it may be expired too.
+fn read_floor_price(token_contract_hash: ContractHash) -> Option<U512> {
+    let len = floor_len(token_contract_hash);
+    let current_time: u64 = runtime::get_blocktime().into();
+
+    let mut candidates: Vec<(u64, (U512, U256))> = Vec::new();
+
+    if len > 0 {
+        candidates.push((0, floor_item(token_contract_hash, 0)));
+    }
+
+    for _ in 0..MAX_PAGE_SIZE {
+        let cheapest = candidates
+            .iter()
+            .enumerate()
+            .min_by_key(|(_index, (_position, (price, _token_id)))| *price)
+            .map(|(index, _candidate)| index)?;
+        let (position, (price, token_id)) = candidates.swap_remove(cheapest);
+
+        let listing = read_listing_data(&get_listing_key(token_contract_hash, token_id));
+
+        if listing.map_or(false, |listing| !is_expired(&listing, current_time)) {
+            return Some(price);
+        }
+
+        for child_position in [2 * position + 1, 2 * position + 2] {
+            if child_position < len {
+                candidates.push((
+                    child_position,
+                    floor_item(token_contract_hash, child_position),
+                ));
+            }
+        }
+    }
+
+    candidates
+        .iter()
+        .map(|(_position, (price, _token_id))| *price)
+        .min()
+}
+
+/// Called once a listing at `price` was added to the collection index.
+pub fn listing_added(token_contract_hash: ContractHash, token_id: U256, price: U512) {
+    if let Some(position) = floor_position(token_contract_hash, token_id) {
+        let (old_price, _token_id) = floor_item(token_contract_hash, position);
+        listing_repriced(token_contract_hash, token_id, old_price, price);
+        return;
+    }
+
+    let len = floor_len(token_contract_hash);
+
+    set_floor_item(token_contract_hash, len, (price, token_id));
+    set_floor_len(token_contract_hash, len + 1);
+    sift_up(token_contract_hash, len);
+}
+
+/// Called once a listing was removed from the collection index.
+pub fn listing_removed(token_contract_hash: ContractHash, token_id: U256) {
+    let position = match floor_position(token_contract_hash, token_id) {
+        Some(position) => position,
+        None => return,
+    };
+
+    let last = floor_len(token_contract_hash) - 1;
+
+    set_floor_position(token_contract_hash, token_id, None);
+    set_floor_len(token_contract_hash, last);
+
+    if position != last {
+        let (removed_price, _token_id) = floor_item(token_contract_hash, position);
+        let last_item = floor_item(token_contract_hash, last);
+        set_floor_item(token_contract_hash, position, last_item);
+
+        if last_item.0 < removed_price {
+            sift_up(token_contract_hash, position);
+        } else {
+            sift_down(token_contract_hash, position, last);
+        }
+    }
+}
+
+/// Called after the price of an indexed listing was changed from `old_price` to `new_price`.
+pub fn listing_repriced(
+    token_contract_hash: ContractHash,
+    token_id: U256,
+    old_price: U512,
+    new_price: U512,
+) {
+    let position = match floor_position(token_contract_hash, token_id) {
+        Some(position) => position,
+        None => return listing_added(token_contract_hash, token_id, new_price),
+    };
+
+    set_floor_item(token_contract_hash, position, (new_price, token_id));
+
+    if new_price > old_price {
+        sift_down(
+            token_contract_hash,
+            position,
+            floor_len(token_contract_hash),
+        );
+    } else {
+        sift_up(token_contract_hash, position);
+    }
+}
+
+pub fn auction_started(token_contract_hash: ContractHash) {
+    let dictionary = get_auction_count_dictionary();
+    let count = read_counter(dictionary, token_contract_hash);
+    write_counter(dictionary, token_contract_hash, count + 1);
+}
+
+pub fn auction_ended(token_contract_hash: ContractHash) {
+    let dictionary = get_auction_count_dictionary();
+    let count = read_counter(dictionary, token_contract_hash);
+    write_counter(dictionary, token_contract_hash, count.saturating_sub(1));
+}
+
+pub fn read_daily_volume(token_contract_hash: ContractHash, day: u64) -> U512 {
+    storage::dictionary_get(
+        get_daily_volume_dictionary(),
+        &get_day_key(token_contract_hash, day),
+    )
+    .unwrap_or_revert()
+    .unwrap_or_default()
+}
+
+/// Adds a sale to the collection's sale count and to the volume of the current day.
+pub fn sale_completed(token_contract_hash: ContractHash, price: U512) {
+    let sales_dictionary = get_sales_count_dictionary();
+    let total_sales = read_counter(sales_dictionary, token_contract_hash);
+    write_counter(sales_dictionary, token_contract_hash, total_sales + 1);
+
+    let day = current_day();
+    storage::dictionary_put(
+        get_daily_volume_dictionary(),
+        &get_day_key(token_contract_hash, day),
+        read_daily_volume(token_contract_hash, day) + price,
+    );
+}
+
+pub fn read_collection_stats(token_contract_hash: ContractHash) -> CollectionStats {
+    CollectionStats {
+        active_listings: index_len(COLLECTION_INDEX, &get_collection_owner(token_contract_hash)),
+        floor_price: read_floor_price(token_contract_hash),
+        active_auctions: read_counter(get_auction_count_dictionary(), token_contract_hash),
+        total_sales: read_counter(get_sales_count_dictionary(), token_contract_hash),
+        total_volume: read_collection_volume(token_contract_hash),
+        daily_volume: read_daily_volume(token_contract_hash, current_day()),
+    }
+}
@@ -0,0 +1,52 @@
+use alloc::vec;
+use casper_contract::contract_api::{runtime, storage};
+use casper_types::{runtime_args, ContractHash, Key, RuntimeArgs, URef, U256};
+
+use crate::{
+    error::Error,
+    utils::{get_dictionary_uref, get_token_owner, require_collection_allowed, transfer_approved},
+    SwapProposal,
+};
+
+pub const SWAP_COUNT_KEY: &str = "swap_count";
+
+pub fn get_swap_dictionary() -> URef {
+    get_dictionary_uref("swaps")
+}
+
+/// Returns the open proposal stored under `swap_id`, or `None` once it was accepted or cancelled.
+pub fn read_swap_proposal(swap_id: &str) -> Option<SwapProposal> {
+    match storage::dictionary_get(get_swap_dictionary(), swap_id) {
+        Ok(item) => item,
+        Err(_error) => None,
+    }
+}
+
+/// Reverts unless every token is owned by `owner` and approved for transfer by the marketplace.
+pub fn require_tokens_transferable(tokens: &[(ContractHash, U256)], owner: Key) {
+    for (token_contract_hash, token_id) in tokens {
+        require_collection_allowed(*token_contract_hash);
+
+        if get_token_owner(*token_contract_hash, *token_id) != Some(owner) {
+            runtime::revert(Error::PermissionDenied);
+        }
+
+        if !transfer_approved(*token_contract_hash, *token_id, owner) {
+            runtime::revert(Error::NeedsTransferApproval);
+        }
+    }
+}
+
+pub fn transfer_tokens(tokens: &[(ContractHash, U256)], sender: Key, recipient: Key) {
+    for (token_contract_hash, token_id) in tokens {
+        runtime::call_contract::<()>(
+            *token_contract_hash,
+            "transfer_from",
+            runtime_args! {
+              "sender" => sender,
+              "recipient" => recipient,
+              "token_ids" => vec![*token_id],
+            },
+        );
+    }
+}
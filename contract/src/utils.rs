@@ -1,10 +1,22 @@
 use alloc::{format, str, string::{String, ToString}, vec::{self, Vec}};
-use casper_contract::{contract_api::{runtime, storage, system}, unwrap_or_revert::UnwrapOrRevert};
+use casper_contract::{
+    contract_api::{self, runtime, storage, system},
+    ext_ffi,
+    unwrap_or_revert::UnwrapOrRevert,
+};
 use casper_types::{
-    account::AccountHash, runtime_args, system::CallStackElement, ContractHash, ContractPackageHash, Key, RuntimeArgs, URef, U256
+    api_error,
+    bytesrepr::{self, FromBytes, ToBytes},
+    runtime_args,
+    system::CallStackElement,
+    ApiError, CLTyped, ContractHash, ContractPackageHash, Key, RuntimeArgs, URef, U256, U512,
 };
 
-use crate::{error::Error, AuctionData, ListingData, OfferData};
+use crate::{
+    error::Error,
+    migration::{migrate_auction, read_legacy_auction},
+    AuctionData, CollectionData, LazyListing, ListingData, OfferData,
+};
 use hex::encode;
 
 pub fn contract_package_hash() -> ContractPackageHash {
@@ -27,11 +39,12 @@ pub fn transfer_approved(token_contract_hash: ContractHash, token_id: U256, owne
           "owner" => owner,
           "token_id" => token_id
         },
-    )
-    .unwrap_or_revert_with(Error::NeedsTransferApproval);
-    let approved_hash = approved.into_hash().unwrap_or_revert();
+    );
 
-    contract_package_hash().value() == approved_hash
+    match approved.and_then(|key| key.into_hash()) {
+        Some(approved_hash) => contract_package_hash().value() == approved_hash,
+        None => false,
+    }
 }
 
 pub fn get_token_owner(token_contract_hash: ContractHash, token_id: U256) -> Option<Key> {
@@ -44,6 +57,54 @@ pub fn get_token_owner(token_contract_hash: ContractHash, token_id: U256) -> Opt
     )
 }
 
+/// Returns the raw bytes of a named argument, or `None` if the caller did not pass it.
+pub fn get_named_arg_bytes(name: &str) -> Option<Vec<u8>> {
+    let mut arg_size: usize = 0;
+    let ret = unsafe {
+        ext_ffi::casper_get_named_arg_size(
+            name.as_bytes().as_ptr(),
+            name.len(),
+            &mut arg_size as *mut usize,
+        )
+    };
+
+    match api_error::result_from(ret) {
+        Ok(_) => {}
+        Err(ApiError::MissingArgument) => return None,
+        Err(error) => runtime::revert(error),
+    }
+
+    if arg_size == 0 {
+        return Some(Vec::new());
+    }
+
+    let data_non_null_ptr = contract_api::alloc_bytes(arg_size);
+    let ret = unsafe {
+        ext_ffi::casper_get_named_arg(
+            name.as_bytes().as_ptr(),
+            name.len(),
+            data_non_null_ptr.as_ptr(),
+            arg_size,
+        )
+    };
+    let data = unsafe { Vec::from_raw_parts(data_non_null_ptr.as_ptr(), arg_size, arg_size) };
+    api_error::result_from(ret).unwrap_or_revert();
+
+    Some(data)
+}
+
+/// Like `runtime::get_named_arg`, but for arguments callers are allowed to omit.
+pub fn get_optional_named_arg<T: FromBytes>(name: &str) -> Option<T> {
+    get_named_arg_bytes(name)
+        .map(|bytes| bytesrepr::deserialize(bytes).unwrap_or_revert_with(ApiError::InvalidArgument))
+}
+
+/// Dictionary item key derived from a `Key`, whose formatted form is too long to be used directly.
+pub fn key_to_str(key: &Key) -> String {
+    let bytes = key.to_bytes().unwrap_or_revert();
+    hex::encode(runtime::blake2b(bytes))
+}
+
 pub fn get_dictionary_uref(key: &str) -> URef {
     match runtime::get_key(key) {
         Some(uref_key) => uref_key.into_uref().unwrap_or_revert(),
@@ -61,8 +122,13 @@ pub fn get_listing_dictionary() -> URef {
     get_dictionary_uref("listings")
 }
 
-pub fn get_offer_key(token_contract_hash : ContractHash, token_id: U256, bidder: AccountHash) -> String {
-    let key_string = format!("{}_{bidder}_{}", token_contract_hash.to_string(),token_id.to_string());
+pub fn get_offer_key(token_contract_hash: ContractHash, token_id: U256, bidder: &Key) -> String {
+    let key_string = format!(
+        "{}_{}_{}",
+        token_contract_hash,
+        bidder.to_formatted_string(),
+        token_id
+    );
     let hashed = runtime::blake2b(key_string);
     hex::encode(hashed)
 }
@@ -71,8 +137,305 @@ pub fn get_offer_dictionary() -> URef {
     get_dictionary_uref("offers")
 }
 
+/// Auctions recorded since parties became `Key`s. Older ones are still in the legacy `auctions`
+/// dictionary until they are migrated.
 pub fn get_acutin_dictionary() -> URef {
-    get_dictionary_uref("auctions")
+    get_dictionary_uref("auctions_v2")
+}
+
+pub const ADMIN_KEY: &str = "admin";
+pub const CURATOR_KEY: &str = "curator";
+pub const ALLOWLIST_MODE_KEY: &str = "allowlist_mode";
+pub const MARKETPLACE_FEE_KEY: &str = "marketplace_fee";
+pub const FEE_RECIPIENT_KEY: &str = "fee_recipient";
+pub const REFERRAL_SHARE_KEY: &str = "referral_share";
+
+/// Reads a setting stored behind a named key, `None` if it was never set.
+pub fn read_config<T: CLTyped + FromBytes>(name: &str) -> Option<T> {
+    runtime::get_key(name).map(|key| {
+        storage::read(key.into_uref().unwrap_or_revert())
+            .unwrap_or_revert()
+            .unwrap_or_revert()
+    })
+}
+
+pub fn write_config<T: CLTyped + ToBytes>(name: &str, value: T) {
+    match runtime::get_key(name) {
+        Some(key) => storage::write(key.into_uref().unwrap_or_revert(), value),
+        None => runtime::put_key(name, storage::new_uref(value).into()),
+    }
+}
+
+/// Party calling the marketplace. Deploys and session code act for their account, a contract
+/// calling in acts for its package, the identity CEP-47 records for tokens owned by contracts.
+pub fn get_caller_key() -> Key {
+    let call_stack = runtime::get_call_stack();
+
+    match call_stack.iter().rev().nth(1) {
+        Some(CallStackElement::StoredContract {
+            contract_package_hash,
+            ..
+        }) => Key::Hash(contract_package_hash.value()),
+        _ => Key::Account(runtime::get_caller()),
+    }
+}
+
+/// Sends `amount` straight to an account's main purse. Contracts have no main purse, so their
+/// share is credited to the ledger and withdrawn into a purse of their own.
+pub fn pay_out(source_purse: URef, recipient: Key, amount: U512) {
+    match recipient {
+        Key::Account(account_hash) => {
+            system::transfer_from_purse_to_account(source_purse, account_hash, amount, None)
+                .unwrap_or_revert();
+        }
+        _ => credit_balance(source_purse, recipient, amount),
+    }
+}
+
+pub fn require_admin() {
+    let caller = get_caller_key();
+
+    if read_config::<Key>(ADMIN_KEY) != Some(caller) {
+        runtime::revert(Error::PermissionDenied);
+    }
+}
+
+/// The admin can always act as curator.
+pub fn require_curator() {
+    let caller = get_caller_key();
+
+    if read_config::<Key>(ADMIN_KEY) != Some(caller)
+        && read_config::<Key>(CURATOR_KEY) != Some(caller)
+    {
+        runtime::revert(Error::PermissionDenied);
+    }
+}
+
+pub fn get_collection_dictionary() -> URef {
+    get_dictionary_uref("collections")
+}
+
+pub fn get_collection_key(token_contract_hash: ContractHash) -> String {
+    hex::encode(token_contract_hash.value())
+}
+
+pub fn read_collection_data(token_contract_hash: ContractHash) -> Option<CollectionData> {
+    match storage::dictionary_get(
+        get_collection_dictionary(),
+        &get_collection_key(token_contract_hash),
+    ) {
+        Ok(item) => item,
+        Err(_error) => None,
+    }
+}
+
+/// In allowlist mode only collections registered by the curator can be traded.
+pub fn require_collection_allowed(token_contract_hash: ContractHash) {
+    let allowlist_mode: bool = read_config(ALLOWLIST_MODE_KEY).unwrap_or_default();
+
+    if allowlist_mode && read_collection_data(token_contract_hash).is_none() {
+        runtime::revert(Error::CollectionNotAllowed);
+    }
+}
+
+pub fn get_collection_package_dictionary() -> URef {
+    get_dictionary_uref("collection_packages")
+}
+
+/// Contract hash the curator recorded as the current version of a collection's package.
+pub fn read_collection_package(package_hash: ContractPackageHash) -> Option<ContractHash> {
+    storage::dictionary_get::<Option<ContractHash>>(
+        get_collection_package_dictionary(),
+        &hex::encode(package_hash.value()),
+    )
+    .unwrap_or_revert()
+    .flatten()
+}
+
+pub fn get_creator_dictionary() -> URef {
+    get_dictionary_uref("collection_creators")
+}
+
+/// Creator the curator allowed to lazy-list unminted tokens of a collection.
+pub fn read_collection_creator(token_contract_hash: ContractHash) -> Option<Key> {
+    match storage::dictionary_get(
+        get_creator_dictionary(),
+        &get_collection_key(token_contract_hash),
+    ) {
+        Ok(item) => item,
+        Err(_error) => None,
+    }
+}
+
+pub fn get_lazy_listing_dictionary() -> URef {
+    get_dictionary_uref("lazy_listings")
+}
+
+/// Metadata of a listed token that is only minted when somebody buys it.
+pub fn read_lazy_listing(key: &str) -> Option<LazyListing> {
+    match storage::dictionary_get(get_lazy_listing_dictionary(), key) {
+        Ok(item) => item,
+        Err(_error) => None,
+    }
+}
+
+pub fn get_blocklist_dictionary() -> URef {
+    get_dictionary_uref("blocklist")
+}
+
+pub fn is_blocked(key: &Key) -> bool {
+    storage::dictionary_get(get_blocklist_dictionary(), &key_to_str(key))
+        .unwrap_or_revert()
+        .unwrap_or_default()
+}
+
+/// Blocked keys cannot trade, but can still cancel their own offers and withdraw their balance.
+pub fn require_not_blocked(key: &Key) {
+    if is_blocked(key) {
+        runtime::revert(Error::AccountBlocked);
+    }
+}
+
+const REENTRANCY_LOCK: &str = "reentrancy_lock";
+
+fn get_reentrancy_lock() -> URef {
+    match runtime::get_key(REENTRANCY_LOCK) {
+        Some(key) => key.into_uref().unwrap_or_revert(),
+        None => {
+            let uref = storage::new_uref(false);
+            runtime::put_key(REENTRANCY_LOCK, uref.into());
+            uref
+        }
+    }
+}
+
+/// Takes the contract-wide lock held by every entry point that moves funds or tokens. Token
+/// contracts are called while escrow state is live, so any call back into such an entry point
+/// reverts with `Error::Reentrancy`.
+pub fn lock() {
+    let lock = get_reentrancy_lock();
+    let locked: bool = storage::read(lock).unwrap_or_revert().unwrap_or_default();
+
+    if locked {
+        runtime::revert(Error::Reentrancy);
+    }
+
+    storage::write(lock, true);
+}
+
+pub fn unlock() {
+    storage::write(get_reentrancy_lock(), false);
+}
+
+pub const PROCEEDS_PURSE: &str = "proceeds_purse";
+
+pub fn get_balance_dictionary() -> URef {
+    get_dictionary_uref("balances")
+}
+
+/// Amount held in the proceeds purse that `owner` can withdraw.
+pub fn get_pending_balance(owner: &Key) -> U512 {
+    storage::dictionary_get(get_balance_dictionary(), &key_to_str(owner))
+        .unwrap_or_revert()
+        .unwrap_or_default()
+}
+
+/// Moves `amount` from `source_purse` into the proceeds purse and credits it to `recipient`, who
+/// collects it later through `withdraw`. Used for every payout to a party other than the caller.
+pub fn credit_balance(source_purse: URef, recipient: Key, amount: U512) {
+    if amount == U512::zero() {
+        return;
+    }
+
+    system::transfer_from_purse_to_purse(source_purse, get_purse(PROCEEDS_PURSE), amount, None)
+        .unwrap_or_revert();
+
+    let balance = get_pending_balance(&recipient);
+    storage::dictionary_put(
+        get_balance_dictionary(),
+        &key_to_str(&recipient),
+        balance + amount,
+    );
+}
+
+pub fn get_custody_dictionary() -> URef {
+    get_dictionary_uref("custody")
+}
+
+/// Key under which the marketplace itself owns tokens held in escrow. CEP-47 sees the package
+/// hash as the caller of `transfer` and `transfer_from`.
+pub fn custody_key() -> Key {
+    Key::Hash(contract_package_hash().value())
+}
+
+/// Returns who deposited the token behind a listing or auction key into custody, if anyone.
+pub fn read_custodian(key: &str) -> Option<Key> {
+    match storage::dictionary_get(get_custody_dictionary(), key) {
+        Ok(item) => item,
+        Err(_error) => None,
+    }
+}
+
+pub fn get_referral_earnings_dictionary() -> URef {
+    get_dictionary_uref("referral_earnings")
+}
+
+/// Total referral fees ever credited to `referrer`, withdrawn or not.
+pub fn read_referral_earnings(referrer: &Key) -> U512 {
+    storage::dictionary_get(get_referral_earnings_dictionary(), &key_to_str(referrer))
+        .unwrap_or_revert()
+        .unwrap_or_default()
+}
+
+/// Credits a referral fee like any other payout and adds it to the referrer's running total.
+pub fn credit_referral(source_purse: URef, referrer: Key, amount: U512) {
+    if amount == U512::zero() {
+        return;
+    }
+
+    credit_balance(source_purse, referrer, amount);
+
+    let earnings = read_referral_earnings(&referrer);
+    storage::dictionary_put(
+        get_referral_earnings_dictionary(),
+        &key_to_str(&referrer),
+        earnings + amount,
+    );
+}
+
+pub fn get_auction_referrer_dictionary() -> URef {
+    get_dictionary_uref("auction_referrers")
+}
+
+/// Referrer passed along with the current highest bid of an auction.
+pub fn read_auction_referrer(key: &str) -> Option<Key> {
+    match storage::dictionary_get(get_auction_referrer_dictionary(), key) {
+        Ok(item) => item,
+        Err(_error) => None,
+    }
+}
+
+pub fn get_price_change_dictionary() -> URef {
+    get_dictionary_uref("price_changes")
+}
+
+/// Returns the next value of a sequential counter stored under `counter_name`, starting at 0.
+pub fn next_id(counter_name: &str) -> u64 {
+    let counter_uref = match runtime::get_key(counter_name) {
+        Some(key) => key.into_uref().unwrap_or_revert(),
+        None => {
+            let uref = storage::new_uref(0u64);
+            runtime::put_key(counter_name, uref.into());
+            uref
+        }
+    };
+
+    let id: u64 = storage::read(counter_uref)
+        .unwrap_or_revert()
+        .unwrap_or_revert();
+    storage::write(counter_uref, id + 1);
+
+    id
 }
 
 
@@ -94,6 +457,14 @@ pub fn get_purse(purse_name: &str) -> URef {
 }
 
 
+/// Returns the listing stored under `key`, or `None` if it was never created or has been cleared.
+pub fn read_listing_data(key: &str) -> Option<ListingData> {
+    match storage::dictionary_get(get_listing_dictionary(), key) {
+        Ok(item) => item,
+        Err(_error) => None,
+    }
+}
+
 pub fn get_listing_data(key: &str) -> ListingData {
     let listing : ListingData =
         match storage::dictionary_get(get_listing_dictionary(), &key)  {
@@ -107,21 +478,27 @@ pub fn get_listing_data(key: &str) -> ListingData {
     listing
 }
 
-pub fn get_offer_data(key: &str) -> OfferData {
-    let offer : OfferData =
-        match storage::dictionary_get(get_offer_dictionary(), &key)  {
-            Ok(item) => match item {
-                None => runtime::revert(Error::OfferDoesntExist),
-                Some(value) => value,
-            },
-            Err(_error) => runtime::revert(Error::OfferCancelledOrFinished)
-        };
-
-        offer
+pub fn read_offer_data(key: &str) -> Option<OfferData> {
+    match storage::dictionary_get(get_offer_dictionary(), key) {
+        Ok(item) => item,
+        Err(_error) => None,
+    }
 }
 
+pub fn read_auction_data(key: &str) -> Option<AuctionData> {
+    let auction = match storage::dictionary_get(get_acutin_dictionary(), key) {
+        Ok(item) => item,
+        Err(_error) => None,
+    };
+
+    auction.or_else(|| read_legacy_auction(key).map(AuctionData::from))
+}
 
+/// Loads an auction that is about to change, moving it out of the legacy dictionary first so the
+/// change is written over the only copy.
 pub fn get_auction_data(key: &str) -> AuctionData {
+    migrate_auction(key);
+
     let auction : AuctionData =
         match storage::dictionary_get(get_acutin_dictionary(), &key)  {
             Ok(item) => match item {
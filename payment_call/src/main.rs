@@ -13,31 +13,67 @@ use alloc::{
 
 use casper_contract::{
     contract_api::{runtime, storage, system, account},
+    ext_ffi,
     unwrap_or_revert::UnwrapOrRevert,
 };
 use casper_types::{
-    contracts::NamedKeys, runtime_args, ApiError, ContractHash, Key, RuntimeArgs, URef, U256, U512,
+    api_error, bytesrepr::FromBytes, contracts::NamedKeys, runtime_args, ApiError, ContractHash,
+    Key, RuntimeArgs, URef, U256, U512,
 };
 
+/// Like `runtime::get_named_arg`, but `None` when the caller did not pass the argument.
+fn get_optional_named_arg<T: FromBytes>(name: &str) -> Option<T> {
+    let mut arg_size: usize = 0;
+    let ret = unsafe {
+        ext_ffi::casper_get_named_arg_size(
+            name.as_bytes().as_ptr(),
+            name.len(),
+            &mut arg_size as *mut usize,
+        )
+    };
+
+    match api_error::result_from(ret) {
+        Ok(()) => Some(runtime::get_named_arg(name)),
+        Err(ApiError::MissingArgument) => None,
+        Err(error) => runtime::revert(error),
+    }
+}
+
 #[no_mangle]
 pub extern "C" fn call() {
     let token_id: U256 = runtime::get_named_arg("token_id");
     let contract_hash: String = runtime::get_named_arg("buy_contract_hash");
     let marketplace_hash: String = runtime::get_named_arg("marketplace_hash");
     let amount: U512 = runtime::get_named_arg("amount");
+    let referrer: Option<Key> = get_optional_named_arg("referrer");
 
     let deposit_purse: URef = system::create_purse();
+    let main_purse: URef = account::get_main_purse();
 
-    system::transfer_from_purse_to_purse(account::get_main_purse(), deposit_purse, amount, None)
+    system::transfer_from_purse_to_purse(main_purse, deposit_purse, amount, None)
         .unwrap_or_revert();
 
-    runtime::call_contract(
+    let mut args = runtime_args! {
+        "contract_hash" => contract_hash,
+        "token_id" => token_id,
+        "buy_purse" => deposit_purse
+    };
+
+    if let Some(referrer) = referrer {
+        args.insert("referrer", referrer).unwrap_or_revert();
+    }
+
+    runtime::call_contract::<()>(
         ContractHash::from_formatted_str(&marketplace_hash).unwrap(),
         "buy_listing",
-        runtime_args! {
-         "contract_hash" => contract_hash,
-         "token_id" => token_id,
-         "buy_purse" => deposit_purse
-        },
-    )
+        args,
+    );
+
+    // buy_listing only takes the listing price, anything above it goes back to the buyer.
+    let remaining: U512 = system::get_purse_balance(deposit_purse).unwrap_or_revert();
+
+    if remaining > U512::zero() {
+        system::transfer_from_purse_to_purse(deposit_purse, main_purse, remaining, None)
+            .unwrap_or_revert();
+    }
 }
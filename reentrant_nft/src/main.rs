@@ -0,0 +1,122 @@
+//! Test-only stand-in for a CEP-47 collection. It reports the installer as the owner of every
+//! token and the marketplace package as approved, and when asked to `transfer_from` it calls back
+//! into the marketplace, the way a malicious collection would.
+
+#![no_std]
+#![no_main]
+
+#[cfg(not(target_arch = "wasm32"))]
+compile_error!("target arch should be wasm32: compile with '--target wasm32-unknown-unknown'");
+
+extern crate alloc;
+
+use alloc::{string::String, vec, vec::Vec};
+
+use casper_contract::{
+    contract_api::{runtime, storage},
+    unwrap_or_revert::UnwrapOrRevert,
+};
+use casper_types::{
+    bytesrepr::FromBytes, contracts::NamedKeys, runtime_args, CLType, CLTyped, CLValue,
+    ContractHash, EntryPoint, EntryPointAccess, EntryPointType, EntryPoints, Key, Parameter,
+    RuntimeArgs, U256,
+};
+
+const OWNER_KEY: &str = "owner";
+const SPENDER_KEY: &str = "spender";
+const MARKETPLACE_KEY: &str = "marketplace";
+const REENTRY_ENTRY_POINT_KEY: &str = "reentry_entry_point";
+
+fn read_named<T: CLTyped + FromBytes>(name: &str) -> T {
+    let uref = runtime::get_key(name)
+        .unwrap_or_revert()
+        .into_uref()
+        .unwrap_or_revert();
+    storage::read(uref).unwrap_or_revert().unwrap_or_revert()
+}
+
+#[no_mangle]
+pub extern "C" fn owner_of() {
+    let _token_id: U256 = runtime::get_named_arg("token_id");
+    let owner: Key = read_named(OWNER_KEY);
+
+    runtime::ret(CLValue::from_t(Some(owner)).unwrap_or_revert())
+}
+
+#[no_mangle]
+pub extern "C" fn get_approved() {
+    let spender: Key = read_named(SPENDER_KEY);
+
+    runtime::ret(CLValue::from_t(Some(spender)).unwrap_or_revert())
+}
+
+#[no_mangle]
+pub extern "C" fn transfer_from() {
+    let marketplace_hash: String = read_named(MARKETPLACE_KEY);
+    let entry_point: String = read_named(REENTRY_ENTRY_POINT_KEY);
+
+    runtime::call_contract::<()>(
+        ContractHash::from_formatted_str(&marketplace_hash).unwrap(),
+        &entry_point,
+        runtime_args! {},
+    );
+}
+
+#[no_mangle]
+pub extern "C" fn call() {
+    let marketplace_hash: String = runtime::get_named_arg("marketplace_hash");
+    let marketplace_package_hash: Key = runtime::get_named_arg("marketplace_package_hash");
+    let reentry_entry_point: String = runtime::get_named_arg("reentry_entry_point");
+
+    let mut named_keys = NamedKeys::new();
+    named_keys.insert(
+        OWNER_KEY.into(),
+        storage::new_uref(Key::Account(runtime::get_caller())).into(),
+    );
+    named_keys.insert(
+        SPENDER_KEY.into(),
+        storage::new_uref(marketplace_package_hash).into(),
+    );
+    named_keys.insert(
+        MARKETPLACE_KEY.into(),
+        storage::new_uref(marketplace_hash).into(),
+    );
+    named_keys.insert(
+        REENTRY_ENTRY_POINT_KEY.into(),
+        storage::new_uref(reentry_entry_point).into(),
+    );
+
+    let mut entry_points = EntryPoints::new();
+    entry_points.add_entry_point(EntryPoint::new(
+        "owner_of",
+        vec![Parameter::new("token_id", U256::cl_type())],
+        Option::<Key>::cl_type(),
+        EntryPointAccess::Public,
+        EntryPointType::Contract,
+    ));
+    entry_points.add_entry_point(EntryPoint::new(
+        "get_approved",
+        vec![
+            Parameter::new("owner", Key::cl_type()),
+            Parameter::new("token_id", U256::cl_type()),
+        ],
+        Option::<Key>::cl_type(),
+        EntryPointAccess::Public,
+        EntryPointType::Contract,
+    ));
+    entry_points.add_entry_point(EntryPoint::new(
+        "transfer_from",
+        vec![
+            Parameter::new("sender", Key::cl_type()),
+            Parameter::new("recipient", Key::cl_type()),
+            Parameter::new("token_ids", Vec::<U256>::cl_type()),
+        ],
+        CLType::Unit,
+        EntryPointAccess::Public,
+        EntryPointType::Contract,
+    ));
+
+    let (contract_hash, _version) =
+        storage::new_contract(entry_points, Some(named_keys), None, None);
+    runtime::put_key("reentrant_nft_contract_hash", contract_hash.into());
+}
@@ -0,0 +1,49 @@
+#![no_std]
+#![no_main]
+
+#[cfg(not(target_arch = "wasm32"))]
+compile_error!("target arch should be wasm32: compile with '--target wasm32-unknown-unknown'");
+
+extern crate alloc;
+
+use alloc::string::String;
+
+use casper_contract::{
+    contract_api::{account, runtime, system},
+    unwrap_or_revert::UnwrapOrRevert,
+};
+use casper_types::{runtime_args, ContractHash, RuntimeArgs, URef, U256, U512};
+
+#[no_mangle]
+pub extern "C" fn call() {
+    let token_id: U256 = runtime::get_named_arg("token_id");
+    let contract_hash: String = runtime::get_named_arg("rent_contract_hash");
+    let marketplace_hash: String = runtime::get_named_arg("marketplace_hash");
+    let days: u64 = runtime::get_named_arg("days");
+    let amount: U512 = runtime::get_named_arg("amount");
+
+    let deposit_purse: URef = system::create_purse();
+    let main_purse: URef = account::get_main_purse();
+
+    system::transfer_from_purse_to_purse(main_purse, deposit_purse, amount, None)
+        .unwrap_or_revert();
+
+    runtime::call_contract::<()>(
+        ContractHash::from_formatted_str(&marketplace_hash).unwrap(),
+        "rent",
+        runtime_args! {
+         "contract_hash" => contract_hash,
+         "token_id" => token_id,
+         "days" => days,
+         "buy_purse" => deposit_purse
+        },
+    );
+
+    // rent only takes the rental price, anything above it goes back to the renter.
+    let remaining: U512 = system::get_purse_balance(deposit_purse).unwrap_or_revert();
+
+    if remaining > U512::zero() {
+        system::transfer_from_purse_to_purse(deposit_purse, main_purse, remaining, None)
+            .unwrap_or_revert();
+    }
+}
@@ -0,0 +1,43 @@
+//! Test-only session that reads a collection's stats. Deploys cannot return a value, so the stats
+//! are stored under the `collection_stats` named key of the calling account.
+
+#![no_std]
+#![no_main]
+
+#[cfg(not(target_arch = "wasm32"))]
+compile_error!("target arch should be wasm32: compile with '--target wasm32-unknown-unknown'");
+
+extern crate alloc;
+
+use alloc::{string::String, vec::Vec};
+
+use casper_contract::contract_api::{runtime, storage};
+use casper_types::{runtime_args, ContractHash, RuntimeArgs, U512};
+use casper_types_derive::{CLTyped, FromBytes, ToBytes};
+
+/// Mirror of the marketplace's `CollectionStats`.
+#[derive(CLTyped, ToBytes, FromBytes)]
+pub struct CollectionStats {
+    pub active_listings: u64,
+    pub floor_price: Option<U512>,
+    pub active_auctions: u64,
+    pub total_sales: u64,
+    pub total_volume: U512,
+    pub daily_volume: U512,
+}
+
+#[no_mangle]
+pub extern "C" fn call() {
+    let marketplace_hash: String = runtime::get_named_arg("marketplace_hash");
+    let contract_hash: String = runtime::get_named_arg("contract_hash");
+
+    let stats: CollectionStats = runtime::call_contract(
+        ContractHash::from_formatted_str(&marketplace_hash).unwrap(),
+        "get_collection_stats",
+        runtime_args! {
+            "contract_hash" => contract_hash,
+        },
+    );
+
+    runtime::put_key("collection_stats", storage::new_uref(stats).into());
+}
@@ -0,0 +1,50 @@
+#![no_std]
+#![no_main]
+
+#[cfg(not(target_arch = "wasm32"))]
+compile_error!("target arch should be wasm32: compile with '--target wasm32-unknown-unknown'");
+
+extern crate alloc;
+
+use alloc::{string::String, vec::Vec};
+
+use casper_contract::{
+    contract_api::{account, runtime, system},
+    unwrap_or_revert::UnwrapOrRevert,
+};
+use casper_types::{runtime_args, ContractHash, Key, RuntimeArgs, URef, U256, U512};
+
+#[no_mangle]
+pub extern "C" fn call() {
+    let marketplace_hash: String = runtime::get_named_arg("marketplace_hash");
+    let counterparty: Key = runtime::get_named_arg("counterparty");
+    let offered_tokens: Vec<(String, U256)> = runtime::get_named_arg("offered_tokens");
+    let requested_tokens: Vec<(String, U256)> = runtime::get_named_arg("requested_tokens");
+    let duration_minutes: u64 = runtime::get_named_arg("duration_minutes");
+    let amount: U512 = runtime::get_named_arg("amount");
+
+    // The CSPR sweetener is escrowed by the marketplace until the swap is accepted or cancelled
+    let deposit_purse: URef = system::create_purse();
+
+    if amount > U512::zero() {
+        system::transfer_from_purse_to_purse(
+            account::get_main_purse(),
+            deposit_purse,
+            amount,
+            None,
+        )
+        .unwrap_or_revert();
+    }
+
+    let _swap_id: u64 = runtime::call_contract(
+        ContractHash::from_formatted_str(&marketplace_hash).unwrap(),
+        "propose_swap",
+        runtime_args! {
+         "counterparty" => counterparty,
+         "offered_tokens" => offered_tokens,
+         "requested_tokens" => requested_tokens,
+         "duration_minutes" => duration_minutes,
+         "buy_purse" => deposit_purse
+        },
+    );
+}
@@ -3,6 +3,7 @@ mod tests {
 
     mod cep47_helpers;
     mod marketplace_actions;
+    mod marketplace_state;
 
     use std::{thread, time::Duration};
 
@@ -10,34 +11,66 @@ mod tests {
         ExecuteRequestBuilder, InMemoryWasmTestBuilder, DEFAULT_ACCOUNTS, DEFAULT_ACCOUNT_ADDR,
         PRODUCTION_RUN_GENESIS_REQUEST,
     };
-    use casper_execution_engine::core::engine_state::ExecuteRequest;
+    use casper_execution_engine::core::{
+        engine_state::{Error as EngineError, ExecuteRequest},
+        execution::Error as ExecError,
+    };
     use casper_types::{
-        account::AccountHash, runtime_args, ContractHash, Key, RuntimeArgs, U256, U512,
+        account::AccountHash, runtime_args, ApiError, CLType, CLValue, ContractHash,
+        ContractPackageHash, Key, RuntimeArgs, URef, U256, U512,
+    };
+    use cep47_helpers::{
+        approve_cep_47, deploy_cep_47, get_cep_47_owner, mint_cep_47, transfer_cep_47,
     };
-    use cep47_helpers::{approve_cep_47, deploy_cep_47, mint_cep_47};
     use marketplace_actions::{
-        build_create_listing_request, create_buy_nft_request, create_make_offer_request,
+        build_accept_swap_request, build_add_drop_phase_request, build_buy_drop_request,
+        build_cancel_swap_request, build_collection_stats_request, build_create_drop_request,
+        build_create_lazy_listing_request, build_create_listing_request,
+        build_custodial_auction_request, build_custodial_listing_request, build_end_rental_request,
+        build_get_collection_listings_request, build_get_seller_listings_request,
+        build_list_for_rent_request, build_propose_swap_request, build_query_request,
+        build_register_collection_request, build_remove_collection_request,
+        build_remove_expired_listing_request, build_rent_request, build_set_allowlist_mode_request,
+        build_set_blocked_request, build_set_collection_creator_request,
+        build_set_collection_fee_request, build_set_curator_request,
+        build_set_drop_allowlist_request, build_set_fee_exempt_request,
+        build_set_marketplace_fee_request, build_set_referral_share_request,
+        build_set_royalties_request, build_update_listing_request, build_withdraw_request,
+        create_batch_buy_request, create_buy_nft_request, create_make_offer_request,
+        create_referred_batch_buy_request, create_referred_buy_nft_request,
+        create_referred_place_bid_request,
+    };
+    use marketplace_state::{
+        collection_day_key, dump_marketplace_state, get_collection_listing_count,
+        get_collection_stats, get_floor_listing, get_marketplace_dictionary_item,
+        get_pending_balance, key_to_str, legacy_offer_key, listing_key, offer_key, AuctionData,
+        LegacyAuctionData, ListingData, OfferData, PriceChangeRecord, RentalData, SaleRecord,
     };
 
     use self::marketplace_actions::{
-        build_accept_offer_request, build_cancel_listing_request, build_cancel_offer_request, build_end_auction_request, build_start_auction_request, create_place_bid_request
+        build_accept_offer_request, build_cancel_listing_request, build_cancel_offer_request,
+        build_end_auction_request, build_legacy_end_auction_request,
+        build_legacy_start_auction_request, build_start_auction_request, create_place_bid_request,
     };
 
     // Contract Wasm File Paths (Constants)
     const MARKETPLACE_WASM: &str = "contract.wasm";
+    const LEGACY_MARKETPLACE_WASM: &str = "contract-v1.wasm";
     const CEP47_WASM: &str = "cep47-token.wasm";
     const PAYMENT_WASM: &str = "payment-call.wasm";
     const OFFER_WASM: &str = "make-offer-call.wasm";
     const BID_WASM: &str = "bid-call.wasm";
+    const BATCH_PAYMENT_WASM: &str = "batch-payment-call.wasm";
+    const REENTRANT_NFT_WASM: &str = "reentrant-nft.wasm";
+    const SWAP_WASM: &str = "swap-call.wasm";
+    const RENT_WASM: &str = "rent-call.wasm";
+    const DROP_WASM: &str = "drop-call.wasm";
+    const VAULT_WASM: &str = "vault-contract.wasm";
+    const STATS_WASM: &str = "stats-call.wasm";
 
     // Contract Storage Keys (Constants)
     const CONTRACT_KEY: &str = "mystra_marketplace777";
 
-    // Contract Entry Points (Constants)
-    const ENTRY_POINT_CREATE_LISTING: &str = "create_listing";
-    const ENTRY_POINT_ACCEPT_OFFER: &str = "accept_offer";
-    const ENTRY_POINT_CANCEL_OFFER: &str = "cancel_offer";
-
     /// Deploys a contract version to the InMemoryWasmTestBuilder
     fn deploy_marketplace(builder: &mut InMemoryWasmTestBuilder, wasm_code: &str) -> ContractHash {
         let request =
@@ -66,6 +99,83 @@ mod tests {
     }
 
 
+    /// Asserts that the last executed deploy reverted with the given marketplace `Error` code.
+    fn assert_user_error(builder: &InMemoryWasmTestBuilder, code: u16) {
+        let error = builder.get_error().expect("should have failed");
+        assert!(
+            matches!(
+                error,
+                EngineError::Exec(ExecError::Revert(ApiError::User(user_code))) if user_code == code
+            ),
+            "expected user error {}, got {:?}",
+            code,
+            error
+        );
+    }
+
+    /// Whether `error` comes from an entry point failing to read one of its arguments.
+    fn is_argument_error(error: &EngineError) -> bool {
+        const ERROR_INVALID_TOKEN_CONTRACT: u16 = 37;
+        const ERROR_INVALID_ACCOUNT: u16 = 38;
+
+        matches!(
+            error,
+            EngineError::Exec(ExecError::Revert(
+                ApiError::MissingArgument | ApiError::InvalidArgument
+            )) | EngineError::Exec(ExecError::Revert(ApiError::User(
+                ERROR_INVALID_TOKEN_CONTRACT | ERROR_INVALID_ACCOUNT
+            )))
+        )
+    }
+
+    /// Well-formed value of the given type for an entry point argument. Collections point at the
+    /// test collection, accounts and purses at the caller.
+    fn sample_arg(
+        name: &str,
+        cl_type: &CLType,
+        nft_hash: ContractHash,
+        account_hash: AccountHash,
+        purse: URef,
+    ) -> CLValue {
+        match cl_type {
+            CLType::Bool => CLValue::from_t(false).unwrap(),
+            CLType::U8 => CLValue::from_t(1u8).unwrap(),
+            CLType::U32 => CLValue::from_t(1u32).unwrap(),
+            CLType::U64 => CLValue::from_t(1u64).unwrap(),
+            CLType::U256 => CLValue::from_t(U256::one()).unwrap(),
+            CLType::U512 => CLValue::from_t(U512::one()).unwrap(),
+            CLType::String => CLValue::from_t(String::from("sample")).unwrap(),
+            CLType::Key if name == "contract_hash" => {
+                CLValue::from_t(Key::Hash(nft_hash.value())).unwrap()
+            }
+            CLType::Key => CLValue::from_t(Key::Account(account_hash)).unwrap(),
+            CLType::URef => CLValue::from_t(purse).unwrap(),
+            CLType::ByteArray(32) if name == "package_hash" => {
+                CLValue::from_t(ContractPackageHash::new([1u8; 32])).unwrap()
+            }
+            // Read from the raw bytes, see `FormattedParam`
+            CLType::Any if name == "contract_hash" => {
+                CLValue::from_t(Key::Hash(nft_hash.value())).unwrap()
+            }
+            CLType::Any if name == "offerer" => {
+                CLValue::from_t(Key::Account(account_hash)).unwrap()
+            }
+            CLType::Any => CLValue::from_components(CLType::Any, vec![0, 0, 0, 0]),
+            // `None`
+            CLType::Option(_) => CLValue::from_components(cl_type.clone(), vec![0]),
+            // Empty list or map
+            CLType::List(_) | CLType::Map { .. } => {
+                CLValue::from_components(cl_type.clone(), vec![0, 0, 0, 0])
+            }
+            other => panic!("no sample value for {} of type {:?}", name, other),
+        }
+    }
+
+    fn get_account_balance(builder: &InMemoryWasmTestBuilder, account_hash: AccountHash) -> U512 {
+        let main_purse = builder.get_expected_account(account_hash).main_purse();
+        builder.get_purse_balance(main_purse)
+    }
+
     /// Retrieves the contract hash from the default account's storage by a given key
     fn get_contract_hash_from_account(
         builder: &mut InMemoryWasmTestBuilder,
@@ -81,6 +191,57 @@ mod tests {
             .expect("must get contract hash")
     }
 
+    /// Installs the marketplace and a CEP-47 collection, returning the builder together with the
+    /// marketplace contract hash, the collection hash and the marketplace package hash.
+    fn setup() -> (
+        InMemoryWasmTestBuilder,
+        ContractHash,
+        ContractHash,
+        ContractHash,
+    ) {
+        let mut builder = InMemoryWasmTestBuilder::default();
+        builder
+            .run_genesis(&PRODUCTION_RUN_GENESIS_REQUEST)
+            .commit();
+
+        let marketplace_hash = deploy_marketplace(&mut builder, MARKETPLACE_WASM);
+        let nft_hash = deploy_cep_47(&mut builder);
+
+        let marketplace_contract_package_hash = builder
+            .get_expected_account(*DEFAULT_ACCOUNT_ADDR)
+            .named_keys()
+            .get("mystra_marketplace_package_name777")
+            .expect("must have contract hash key")
+            .into_hash()
+            .map(ContractHash::new)
+            .expect("must get contract hash");
+
+        (
+            builder,
+            marketplace_hash,
+            nft_hash,
+            marketplace_contract_package_hash,
+        )
+    }
+
+    /// Mints `ids` to `owner` and approves the marketplace package to transfer them.
+    fn mint_and_approve(
+        builder: &mut InMemoryWasmTestBuilder,
+        nft_hash: ContractHash,
+        marketplace_package_hash: ContractHash,
+        owner: AccountHash,
+        ids: Vec<U256>,
+    ) {
+        mint_cep_47(builder, nft_hash, owner.into(), ids.clone());
+        approve_cep_47(
+            builder,
+            owner,
+            nft_hash,
+            marketplace_package_hash.into(),
+            ids,
+        );
+    }
+
     #[test]
     fn install_and_test() {
         let mut builder = InMemoryWasmTestBuilder::default();
@@ -233,6 +394,3258 @@ mod tests {
         let req = build_end_auction_request(ACCOUNT_ONE,  marketplace_hash, nft_hash, U256::from(3),  60000 * 100);
         builder.exec(req).expect_success().commit();
     }
+
+    #[test]
+    fn batch_buy_listings() {
+        let (mut builder, marketplace_hash, nft_hash, package_hash) = setup();
+
+        let account_one: AccountHash = *DEFAULT_ACCOUNT_ADDR;
+        let account_two: AccountHash = DEFAULT_ACCOUNTS.get(1).unwrap().account_hash();
+
+        mint_and_approve(
+            &mut builder,
+            nft_hash,
+            package_hash,
+            account_one,
+            vec![U256::from(1), U256::from(2), U256::from(3)],
+        );
+
+        for token_id in [1u64, 2] {
+            let req = build_create_listing_request(
+                account_one,
+                marketplace_hash,
+                nft_hash,
+                U256::from(token_id),
+                U512::from(100u64),
+                0u64,
+            );
+            builder.exec(req).expect_success().commit();
+        }
+
+        let tokens = vec![U256::from(1), U256::from(2), U256::from(3)];
+
+        // Token 3 is not listed, so a strict batch reverts as a whole.
+        let req = create_batch_buy_request(
+            account_two,
+            marketplace_hash,
+            nft_hash,
+            tokens.clone(),
+            U512::from(1000u64),
+            false,
+        );
+        builder.exec(req).expect_failure().commit();
+        assert_eq!(
+            get_cep_47_owner(&builder, nft_hash, U256::from(1)),
+            Some(Key::Account(account_one))
+        );
+
+        // Not enough budget for both listings: only the first one is bought.
+        let req = create_batch_buy_request(
+            account_two,
+            marketplace_hash,
+            nft_hash,
+            tokens.clone(),
+            U512::from(150u64),
+            true,
+        );
+        builder.exec(req).expect_success().commit();
+        assert_eq!(
+            get_cep_47_owner(&builder, nft_hash, U256::from(1)),
+            Some(Key::Account(account_two))
+        );
+        assert_eq!(
+            get_cep_47_owner(&builder, nft_hash, U256::from(2)),
+            Some(Key::Account(account_one))
+        );
+
+        let req = create_batch_buy_request(
+            account_two,
+            marketplace_hash,
+            nft_hash,
+            tokens,
+            U512::from(1000u64),
+            true,
+        );
+        builder.exec(req).expect_success().commit();
+        assert_eq!(
+            get_cep_47_owner(&builder, nft_hash, U256::from(2)),
+            Some(Key::Account(account_two))
+        );
+        assert_eq!(
+            get_cep_47_owner(&builder, nft_hash, U256::from(3)),
+            Some(Key::Account(account_one))
+        );
+    }
+
+    #[test]
+    fn batch_buy_skips_revoked_approval() {
+        const NEEDS_TRANSFER_APPROVAL: u16 = 0;
+
+        let (mut builder, marketplace_hash, nft_hash, package_hash) = setup();
+
+        let account_one: AccountHash = *DEFAULT_ACCOUNT_ADDR;
+        let account_two: AccountHash = DEFAULT_ACCOUNTS.get(1).unwrap().account_hash();
+
+        mint_and_approve(
+            &mut builder,
+            nft_hash,
+            package_hash,
+            account_one,
+            vec![U256::from(1), U256::from(2)],
+        );
+
+        for token_id in [1u64, 2] {
+            let req = build_create_listing_request(
+                account_one,
+                marketplace_hash,
+                nft_hash,
+                U256::from(token_id),
+                U512::from(100u64),
+                0u64,
+            );
+            builder.exec(req).expect_success().commit();
+        }
+
+        // Approving another spender revokes the marketplace's approval for token 1
+        approve_cep_47(
+            &mut builder,
+            account_one,
+            nft_hash,
+            Key::Account(account_two),
+            vec![U256::from(1)],
+        );
+
+        let tokens = vec![U256::from(1), U256::from(2)];
+
+        let req = create_batch_buy_request(
+            account_two,
+            marketplace_hash,
+            nft_hash,
+            tokens.clone(),
+            U512::from(1000u64),
+            false,
+        );
+        builder.exec(req).expect_failure().commit();
+        assert_user_error(&builder, NEEDS_TRANSFER_APPROVAL);
+
+        let req = create_batch_buy_request(
+            account_two,
+            marketplace_hash,
+            nft_hash,
+            tokens,
+            U512::from(1000u64),
+            true,
+        );
+        builder.exec(req).expect_success().commit();
+        assert_eq!(
+            get_cep_47_owner(&builder, nft_hash, U256::from(1)),
+            Some(Key::Account(account_one))
+        );
+        assert_eq!(
+            get_cep_47_owner(&builder, nft_hash, U256::from(2)),
+            Some(Key::Account(account_two))
+        );
+    }
+
+    #[test]
+    fn expired_listings_leave_the_floor_but_stay_indexed_until_removed() {
+        const EXPIRED_BLOCKTIME: u64 = 2 * 60 * 1000;
+
+        let (mut builder, marketplace_hash, nft_hash, package_hash) = setup();
+
+        let account_one: AccountHash = *DEFAULT_ACCOUNT_ADDR;
+
+        mint_and_approve(
+            &mut builder,
+            nft_hash,
+            package_hash,
+            account_one,
+            vec![U256::from(1), U256::from(2)],
+        );
+
+        // Token 1 is the cheaper listing but expires after a minute
+        for (token_id, price, duration_minutes) in [(1u64, 100u64, 1u64), (2, 200, 0)] {
+            let req = build_create_listing_request(
+                account_one,
+                marketplace_hash,
+                nft_hash,
+                U256::from(token_id),
+                U512::from(price),
+                duration_minutes,
+            );
+            builder.exec(req).expect_success().commit();
+        }
+
+        let req = build_collection_stats_request(account_one, marketplace_hash, nft_hash, 0);
+        builder.exec(req).expect_success().commit();
+
+        let stats = get_collection_stats(&builder, account_one);
+        assert_eq!(stats.floor_price, Some(U512::from(100u64)));
+        assert_eq!(stats.active_listings, 2);
+
+        // Once it expires the reported floor skips it, but it stays in the floor heap and the
+        // index until it is removed
+        let req = build_collection_stats_request(
+            account_one,
+            marketplace_hash,
+            nft_hash,
+            EXPIRED_BLOCKTIME,
+        );
+        builder.exec(req).expect_success().commit();
+
+        let stats = get_collection_stats(&builder, account_one);
+        assert_eq!(stats.floor_price, Some(U512::from(200u64)));
+        assert_eq!(stats.active_listings, 2);
+        assert_eq!(
+            get_floor_listing(&builder, marketplace_hash, nft_hash),
+            Some((U512::from(100u64), U256::from(1)))
+        );
+        assert_eq!(
+            get_collection_listing_count(&builder, marketplace_hash, nft_hash),
+            2
+        );
+
+        let req = build_remove_expired_listing_request(
+            account_one,
+            marketplace_hash,
+            nft_hash,
+            U256::from(1),
+            EXPIRED_BLOCKTIME,
+        );
+        builder.exec(req).expect_success().commit();
+
+        let req = build_collection_stats_request(
+            account_one,
+            marketplace_hash,
+            nft_hash,
+            EXPIRED_BLOCKTIME,
+        );
+        builder.exec(req).expect_success().commit();
+
+        let stats = get_collection_stats(&builder, account_one);
+        assert_eq!(stats.floor_price, Some(U512::from(200u64)));
+        assert_eq!(stats.active_listings, 1);
+        assert_eq!(
+            get_floor_listing(&builder, marketplace_hash, nft_hash),
+            Some((U512::from(200u64), U256::from(2)))
+        );
+        assert_eq!(
+            get_collection_listing_count(&builder, marketplace_hash, nft_hash),
+            1
+        );
+    }
+
+    #[test]
+    fn floor_follows_the_cheapest_listing() {
+        let (mut builder, marketplace_hash, nft_hash, package_hash) = setup();
+
+        let account_one: AccountHash = *DEFAULT_ACCOUNT_ADDR;
+        let prices = [500u64, 300, 700, 100, 600, 200];
+
+        mint_and_approve(
+            &mut builder,
+            nft_hash,
+            package_hash,
+            account_one,
+            (1..=prices.len() as u64).map(U256::from).collect(),
+        );
+
+        for (token_id, price) in (1u64..).zip(prices) {
+            let req = build_create_listing_request(
+                account_one,
+                marketplace_hash,
+                nft_hash,
+                U256::from(token_id),
+                U512::from(price),
+                0u64,
+            );
+            builder.exec(req).expect_success().commit();
+        }
+
+        // Removing listings from anywhere in the heap keeps the cheapest one at the root
+        let mut listed: Vec<(u64, u64)> = (1u64..).zip(prices).collect();
+
+        for token_id in [3u64, 4, 1, 6, 2, 5] {
+            let floor = listed
+                .iter()
+                .min_by_key(|(_token_id, price)| *price)
+                .map(|(token_id, price)| (U512::from(*price), U256::from(*token_id)));
+            assert_eq!(
+                get_floor_listing(&builder, marketplace_hash, nft_hash),
+                floor
+            );
+
+            let req = build_cancel_listing_request(
+                account_one,
+                marketplace_hash,
+                nft_hash,
+                U256::from(token_id),
+            );
+            builder.exec(req).expect_success().commit();
+            listed.retain(|(listed_token_id, _price)| *listed_token_id != token_id);
+        }
+
+        assert_eq!(
+            get_floor_listing(&builder, marketplace_hash, nft_hash),
+            None
+        );
+    }
+
+    #[test]
+    fn listings_are_cleared_when_the_token_changes_hands() {
+        const AUCTION_END_BLOCKTIME: u64 = 21 * 60 * 1000;
+
+        let (mut builder, marketplace_hash, nft_hash, package_hash) = setup();
+
+        let account_one: AccountHash = *DEFAULT_ACCOUNT_ADDR;
+        let account_two: AccountHash = DEFAULT_ACCOUNTS.get(1).unwrap().account_hash();
+
+        mint_and_approve(
+            &mut builder,
+            nft_hash,
+            package_hash,
+            account_one,
+            vec![U256::from(1), U256::from(2)],
+        );
+
+        for (token_id, price) in [(1u64, 100u64), (2, 200)] {
+            let req = build_create_listing_request(
+                account_one,
+                marketplace_hash,
+                nft_hash,
+                U256::from(token_id),
+                U512::from(price),
+                0u64,
+            );
+            builder.exec(req).expect_success().commit();
+        }
+
+        let listing = |builder: &InMemoryWasmTestBuilder, token_id: u64| {
+            get_marketplace_dictionary_item::<ListingData>(
+                builder,
+                marketplace_hash,
+                "listings",
+                &listing_key(nft_hash, U256::from(token_id)),
+            )
+        };
+
+        // Accepting an offer takes the listing of the token down
+        let req = create_make_offer_request(account_two, U256::from(1), marketplace_hash, nft_hash);
+        builder.exec(req).expect_success().commit();
+
+        let req =
+            build_accept_offer_request(account_one, marketplace_hash, nft_hash, U256::from(1));
+        builder.exec(req).expect_success().commit();
+
+        assert!(listing(&builder, 1).is_none());
+        assert_eq!(
+            get_collection_listing_count(&builder, marketplace_hash, nft_hash),
+            1
+        );
+        assert_eq!(
+            get_floor_listing(&builder, marketplace_hash, nft_hash),
+            Some((U512::from(200u64), U256::from(2)))
+        );
+
+        // So does settling an auction of it
+        let req =
+            build_start_auction_request(account_one, marketplace_hash, nft_hash, U256::from(2));
+        builder.exec(req).expect_success().commit();
+
+        let req = create_place_bid_request(
+            account_two,
+            U256::from(2),
+            U512::from(60u64),
+            marketplace_hash,
+            nft_hash,
+            5u64,
+        );
+        builder.exec(req).expect_success().commit();
+
+        let req = build_end_auction_request(
+            account_one,
+            marketplace_hash,
+            nft_hash,
+            U256::from(2),
+            AUCTION_END_BLOCKTIME,
+        );
+        builder.exec(req).expect_success().commit();
+
+        assert_eq!(
+            get_cep_47_owner(&builder, nft_hash, U256::from(2)),
+            Some(Key::Account(account_two))
+        );
+        assert!(listing(&builder, 2).is_none());
+        assert_eq!(
+            get_collection_listing_count(&builder, marketplace_hash, nft_hash),
+            0
+        );
+        assert_eq!(
+            get_floor_listing(&builder, marketplace_hash, nft_hash),
+            None
+        );
+
+        let req = build_collection_stats_request(
+            account_one,
+            marketplace_hash,
+            nft_hash,
+            AUCTION_END_BLOCKTIME,
+        );
+        builder.exec(req).expect_success().commit();
+        assert_eq!(
+            get_collection_stats(&builder, account_one).active_listings,
+            0
+        );
+    }
+
+    #[test]
+    fn update_listing_price() {
+        let (mut builder, marketplace_hash, nft_hash, package_hash) = setup();
+
+        let account_one: AccountHash = *DEFAULT_ACCOUNT_ADDR;
+        let account_two: AccountHash = DEFAULT_ACCOUNTS.get(1).unwrap().account_hash();
+
+        mint_and_approve(
+            &mut builder,
+            nft_hash,
+            package_hash,
+            account_one,
+            vec![U256::from(1), U256::from(2)],
+        );
+
+        // Nothing to update before the token is listed
+        let req = build_update_listing_request(
+            account_one,
+            marketplace_hash,
+            nft_hash,
+            U256::from(1),
+            U512::from(200u64),
+            0u64,
+        );
+        builder.exec(req).expect_failure().commit();
+
+        let req = build_create_listing_request(
+            account_one,
+            marketplace_hash,
+            nft_hash,
+            U256::from(1),
+            U512::from(100u64),
+            0u64,
+        );
+        builder.exec(req).expect_success().commit();
+
+        // Only the seller can change the price
+        let req = build_update_listing_request(
+            account_two,
+            marketplace_hash,
+            nft_hash,
+            U256::from(1),
+            U512::from(1u64),
+            0u64,
+        );
+        builder.exec(req).expect_failure().commit();
+
+        let req = build_update_listing_request(
+            account_one,
+            marketplace_hash,
+            nft_hash,
+            U256::from(1),
+            U512::from(0u64),
+            0u64,
+        );
+        builder.exec(req).expect_failure().commit();
+
+        let req = build_update_listing_request(
+            account_one,
+            marketplace_hash,
+            nft_hash,
+            U256::from(1),
+            U512::from(200u64),
+            15u64,
+        );
+        builder.exec(req).expect_success().commit();
+
+        let price_change: PriceChangeRecord =
+            get_marketplace_dictionary_item(&builder, marketplace_hash, "price_changes", "0")
+                .expect("should have recorded the price change");
+        assert_eq!(price_change.token_contract, nft_hash);
+        assert_eq!(price_change.token_id, U256::from(1));
+        assert_eq!(price_change.seller, Key::Account(account_one));
+        assert_eq!(price_change.old_price, U512::from(100u64));
+        assert_eq!(price_change.new_price, U512::from(200u64));
+        assert!(price_change.expiration_time.is_some());
+
+        let req = create_buy_nft_request(account_two, U256::from(1), marketplace_hash, nft_hash);
+        builder.exec(req).expect_success().commit();
+    }
+
+    #[test]
+    fn listing_indexes() {
+        let (mut builder, marketplace_hash, nft_hash, package_hash) = setup();
+
+        let account_one: AccountHash = *DEFAULT_ACCOUNT_ADDR;
+        let account_two: AccountHash = DEFAULT_ACCOUNTS.get(1).unwrap().account_hash();
+
+        mint_and_approve(
+            &mut builder,
+            nft_hash,
+            package_hash,
+            account_one,
+            vec![U256::from(1), U256::from(2), U256::from(3)],
+        );
+
+        for (token_id, duration) in [(1u64, 0u64), (2, 0), (3, 1)] {
+            let req = build_create_listing_request(
+                account_one,
+                marketplace_hash,
+                nft_hash,
+                U256::from(token_id),
+                U512::from(100u64),
+                duration,
+            );
+            builder.exec(req).expect_success().commit();
+        }
+        assert_eq!(
+            get_collection_listing_count(&builder, marketplace_hash, nft_hash),
+            3
+        );
+
+        // Relisting does not add a second entry
+        let req = build_create_listing_request(
+            account_one,
+            marketplace_hash,
+            nft_hash,
+            U256::from(1),
+            U512::from(150u64),
+            0u64,
+        );
+        builder.exec(req).expect_success().commit();
+        assert_eq!(
+            get_collection_listing_count(&builder, marketplace_hash, nft_hash),
+            3
+        );
+
+        let req =
+            build_get_collection_listings_request(account_two, marketplace_hash, nft_hash, 0, 10);
+        builder.exec(req).expect_success().commit();
+
+        let req = build_get_seller_listings_request(
+            account_two,
+            marketplace_hash,
+            Key::Account(account_one),
+            1,
+            1,
+        );
+        builder.exec(req).expect_success().commit();
+
+        let req =
+            build_cancel_listing_request(account_one, marketplace_hash, nft_hash, U256::from(1));
+        builder.exec(req).expect_success().commit();
+        assert_eq!(
+            get_collection_listing_count(&builder, marketplace_hash, nft_hash),
+            2
+        );
+
+        let req = create_buy_nft_request(account_two, U256::from(2), marketplace_hash, nft_hash);
+        builder.exec(req).expect_success().commit();
+        assert_eq!(
+            get_collection_listing_count(&builder, marketplace_hash, nft_hash),
+            1
+        );
+
+        // Token 3 expires one minute after listing
+        let req = build_remove_expired_listing_request(
+            account_two,
+            marketplace_hash,
+            nft_hash,
+            U256::from(3),
+            1000,
+        );
+        builder.exec(req).expect_failure().commit();
+
+        let req = build_remove_expired_listing_request(
+            account_two,
+            marketplace_hash,
+            nft_hash,
+            U256::from(3),
+            60000 * 2,
+        );
+        builder.exec(req).expect_success().commit();
+        assert_eq!(
+            get_collection_listing_count(&builder, marketplace_hash, nft_hash),
+            0
+        );
+    }
+
+    #[test]
+    fn query_entry_points() {
+        let (mut builder, marketplace_hash, nft_hash, package_hash) = setup();
+
+        let account_one: AccountHash = *DEFAULT_ACCOUNT_ADDR;
+        let account_two: AccountHash = DEFAULT_ACCOUNTS.get(1).unwrap().account_hash();
+
+        mint_and_approve(
+            &mut builder,
+            nft_hash,
+            package_hash,
+            account_one,
+            vec![U256::from(1)],
+        );
+
+        let token_args = runtime_args! {
+            "contract_hash" => nft_hash.to_formatted_string(),
+            "token_id" => U256::from(1),
+        };
+        let offer_args = runtime_args! {
+            "contract_hash" => nft_hash.to_formatted_string(),
+            "token_id" => U256::from(1),
+            "offerer" => account_two.to_formatted_string(),
+        };
+
+        // Missing records are reported as `None` rather than reverting
+        for entry_point in ["get_listing", "get_auction", "get_token_state"] {
+            let req = build_query_request(
+                account_two,
+                marketplace_hash,
+                entry_point,
+                token_args.clone(),
+            );
+            builder.exec(req).expect_success().commit();
+        }
+        let req = build_query_request(
+            account_two,
+            marketplace_hash,
+            "get_offer",
+            offer_args.clone(),
+        );
+        builder.exec(req).expect_success().commit();
+
+        let req = build_create_listing_request(
+            account_one,
+            marketplace_hash,
+            nft_hash,
+            U256::from(1),
+            U512::from(100u64),
+            0u64,
+        );
+        builder.exec(req).expect_success().commit();
+
+        let req = create_make_offer_request(account_two, U256::from(1), marketplace_hash, nft_hash);
+        builder.exec(req).expect_success().commit();
+
+        for entry_point in ["get_listing", "get_auction", "get_token_state"] {
+            let req = build_query_request(
+                account_two,
+                marketplace_hash,
+                entry_point,
+                token_args.clone(),
+            );
+            builder.exec(req).expect_success().commit();
+        }
+        let req = build_query_request(account_two, marketplace_hash, "get_offer", offer_args);
+        builder.exec(req).expect_success().commit();
+
+        let listing: ListingData = get_marketplace_dictionary_item(
+            &builder,
+            marketplace_hash,
+            "listings",
+            &listing_key(nft_hash, U256::from(1)),
+        )
+        .expect("should have listing");
+        assert_eq!(listing.seller, Key::Account(account_one));
+        assert_eq!(listing.price, U512::from(100u64));
+    }
+
+    #[test]
+    fn proceeds_are_credited_and_withdrawn() {
+        let (mut builder, marketplace_hash, nft_hash, package_hash) = setup();
+
+        let account_one: AccountHash = *DEFAULT_ACCOUNT_ADDR;
+        let account_two: AccountHash = DEFAULT_ACCOUNTS.get(1).unwrap().account_hash();
+
+        mint_and_approve(
+            &mut builder,
+            nft_hash,
+            package_hash,
+            account_one,
+            vec![U256::from(1), U256::from(2)],
+        );
+
+        let req = build_create_listing_request(
+            account_one,
+            marketplace_hash,
+            nft_hash,
+            U256::from(1),
+            U512::from(100u64),
+            0u64,
+        );
+        builder.exec(req).expect_success().commit();
+
+        let seller_balance_before_sale = get_account_balance(&builder, account_one);
+
+        let req = create_buy_nft_request(account_two, U256::from(1), marketplace_hash, nft_hash);
+        builder.exec(req).expect_success().commit();
+
+        // The sale only credits the ledger, the seller's purse is untouched
+        assert_eq!(
+            get_account_balance(&builder, account_one),
+            seller_balance_before_sale
+        );
+        assert_eq!(
+            get_pending_balance(&builder, marketplace_hash, Key::Account(account_one)),
+            U512::from(100u64)
+        );
+
+        let proposer_balance_before = builder.get_proposer_purse_balance();
+
+        let req = build_withdraw_request(account_one, marketplace_hash);
+        builder.exec(req).expect_success().commit();
+
+        let transaction_fee = builder.get_proposer_purse_balance() - proposer_balance_before;
+        assert_eq!(
+            get_account_balance(&builder, account_one),
+            seller_balance_before_sale - transaction_fee + U512::from(100u64)
+        );
+        assert_eq!(
+            get_pending_balance(&builder, marketplace_hash, Key::Account(account_one)),
+            U512::zero()
+        );
+
+        let req = build_withdraw_request(account_one, marketplace_hash);
+        builder.exec(req).expect_failure().commit();
+
+        // Outbid bidders are refunded through the ledger as well
+        let req =
+            build_start_auction_request(account_one, marketplace_hash, nft_hash, U256::from(2));
+        builder.exec(req).expect_success().commit();
+
+        let req = create_place_bid_request(
+            account_two,
+            U256::from(2),
+            U512::from(2),
+            marketplace_hash,
+            nft_hash,
+            5u64,
+        );
+        builder.exec(req).expect_success().commit();
+
+        let req = create_place_bid_request(
+            account_one,
+            U256::from(2),
+            U512::from(3),
+            marketplace_hash,
+            nft_hash,
+            5u64,
+        );
+        builder.exec(req).expect_success().commit();
+
+        assert_eq!(
+            get_pending_balance(&builder, marketplace_hash, Key::Account(account_two)),
+            U512::from(2u64)
+        );
+    }
+
+    #[test]
+    fn buyer_overpayment_is_refunded() {
+        let (mut builder, marketplace_hash, nft_hash, package_hash) = setup();
+
+        let account_one: AccountHash = *DEFAULT_ACCOUNT_ADDR;
+        let account_two: AccountHash = DEFAULT_ACCOUNTS.get(1).unwrap().account_hash();
+        let price = U512::from(100u64);
+
+        mint_and_approve(
+            &mut builder,
+            nft_hash,
+            package_hash,
+            account_one,
+            vec![U256::from(1), U256::from(2)],
+        );
+
+        for token_id in [1u64, 2] {
+            let req = build_create_listing_request(
+                account_one,
+                marketplace_hash,
+                nft_hash,
+                U256::from(token_id),
+                price,
+                0u64,
+            );
+            builder.exec(req).expect_success().commit();
+        }
+
+        // The payment session funds its purse with far more than the price. Account two is the
+        // block proposer, so the transaction fee it pays comes back to it and only the price is
+        // left as the difference.
+        let buyer_balance_before = get_account_balance(&builder, account_two);
+
+        let req = create_buy_nft_request(account_two, U256::from(1), marketplace_hash, nft_hash);
+        builder.exec(req).expect_success().commit();
+
+        assert_eq!(
+            get_account_balance(&builder, account_two),
+            buyer_balance_before - price
+        );
+
+        // Same for the batch session, which funds its purse with `max_spend`
+        let buyer_balance_before = get_account_balance(&builder, account_two);
+
+        let req = create_batch_buy_request(
+            account_two,
+            marketplace_hash,
+            nft_hash,
+            vec![U256::from(2)],
+            U512::from(1_000_000u64),
+            false,
+        );
+        builder.exec(req).expect_success().commit();
+
+        assert_eq!(
+            get_account_balance(&builder, account_two),
+            buyer_balance_before - price
+        );
+    }
+
+    #[test]
+    fn reentrant_token_contract_is_rejected() {
+        const ERROR_REENTRANCY: u16 = 19;
+
+        let (mut builder, marketplace_hash, _nft_hash, package_hash) = setup();
+
+        let account_one: AccountHash = *DEFAULT_ACCOUNT_ADDR;
+        let account_two: AccountHash = DEFAULT_ACCOUNTS.get(1).unwrap().account_hash();
+
+        // The mock collection calls `buy_listing` again from inside `transfer_from`
+        let request = ExecuteRequestBuilder::standard(
+            account_one,
+            REENTRANT_NFT_WASM,
+            runtime_args! {
+                "marketplace_hash" => marketplace_hash.to_formatted_string(),
+                "marketplace_package_hash" => Key::Hash(package_hash.value()),
+                "reentry_entry_point" => "buy_listing",
+            },
+        )
+        .build();
+        builder.exec(request).expect_success().commit();
+        let reentrant_nft_hash =
+            get_contract_hash_from_account(&mut builder, "reentrant_nft_contract_hash");
+
+        let req = build_create_listing_request(
+            account_one,
+            marketplace_hash,
+            reentrant_nft_hash,
+            U256::from(1),
+            U512::from(100u64),
+            0u64,
+        );
+        builder.exec(req).expect_success().commit();
+
+        let req = create_buy_nft_request(
+            account_two,
+            U256::from(1),
+            marketplace_hash,
+            reentrant_nft_hash,
+        );
+        builder.exec(req).expect_failure();
+        assert_user_error(&builder, ERROR_REENTRANCY);
+        builder.commit();
+
+        assert_eq!(
+            get_pending_balance(&builder, marketplace_hash, Key::Account(account_one)),
+            U512::zero()
+        );
+    }
+
+    #[test]
+    fn collection_allowlist_and_royalties() {
+        const ERROR_PERMISSION_DENIED: u16 = 1;
+        const ERROR_COLLECTION_NOT_ALLOWED: u16 = 20;
+
+        let (mut builder, marketplace_hash, nft_hash, package_hash) = setup();
+
+        let account_one: AccountHash = *DEFAULT_ACCOUNT_ADDR;
+        let account_two: AccountHash = DEFAULT_ACCOUNTS.get(1).unwrap().account_hash();
+        let royalty_recipient = Key::Account(AccountHash::new([7u8; 32]));
+
+        mint_and_approve(
+            &mut builder,
+            nft_hash,
+            package_hash,
+            account_one,
+            vec![U256::from(1), U256::from(2), U256::from(3)],
+        );
+
+        // Only the admin (installer) can configure the marketplace
+        let req = build_set_allowlist_mode_request(account_two, marketplace_hash, true);
+        builder.exec(req).expect_failure();
+        assert_user_error(&builder, ERROR_PERMISSION_DENIED);
+        builder.commit();
+
+        let req = build_set_allowlist_mode_request(account_one, marketplace_hash, true);
+        builder.exec(req).expect_success().commit();
+
+        let req = build_create_listing_request(
+            account_one,
+            marketplace_hash,
+            nft_hash,
+            U256::from(1),
+            U512::from(100u64),
+            0u64,
+        );
+        builder.exec(req).expect_failure();
+        assert_user_error(&builder, ERROR_COLLECTION_NOT_ALLOWED);
+        builder.commit();
+
+        let req = build_register_collection_request(
+            account_two,
+            marketplace_hash,
+            nft_hash,
+            Some(royalty_recipient),
+            10,
+        );
+        builder.exec(req).expect_failure().commit();
+
+        let req = build_register_collection_request(
+            account_one,
+            marketplace_hash,
+            nft_hash,
+            Some(royalty_recipient),
+            101,
+        );
+        builder.exec(req).expect_failure().commit();
+
+        let req = build_register_collection_request(
+            account_one,
+            marketplace_hash,
+            nft_hash,
+            Some(royalty_recipient),
+            10,
+        );
+        builder.exec(req).expect_success().commit();
+
+        let req = build_create_listing_request(
+            account_one,
+            marketplace_hash,
+            nft_hash,
+            U256::from(1),
+            U512::from(100u64),
+            0u64,
+        );
+        builder.exec(req).expect_success().commit();
+
+        let req = create_buy_nft_request(account_two, U256::from(1), marketplace_hash, nft_hash);
+        builder.exec(req).expect_success().commit();
+
+        assert_eq!(
+            get_pending_balance(&builder, marketplace_hash, Key::Account(account_one)),
+            U512::from(90u64)
+        );
+        assert_eq!(
+            get_pending_balance(&builder, marketplace_hash, royalty_recipient),
+            U512::from(10u64)
+        );
+
+        // Only the curator can change a collection's royalties
+        let req = build_set_royalties_request(account_two, marketplace_hash, nft_hash, 20);
+        builder.exec(req).expect_failure();
+        assert_user_error(&builder, ERROR_PERMISSION_DENIED);
+        builder.commit();
+
+        let req = build_set_royalties_request(account_one, marketplace_hash, nft_hash, 101);
+        builder.exec(req).expect_failure().commit();
+
+        let req = build_set_royalties_request(account_one, marketplace_hash, nft_hash, 20);
+        builder.exec(req).expect_success().commit();
+
+        let req = build_create_listing_request(
+            account_one,
+            marketplace_hash,
+            nft_hash,
+            U256::from(2),
+            U512::from(100u64),
+            0u64,
+        );
+        builder.exec(req).expect_success().commit();
+
+        let req = create_buy_nft_request(account_two, U256::from(2), marketplace_hash, nft_hash);
+        builder.exec(req).expect_success().commit();
+
+        assert_eq!(
+            get_pending_balance(&builder, marketplace_hash, Key::Account(account_one)),
+            U512::from(170u64)
+        );
+        assert_eq!(
+            get_pending_balance(&builder, marketplace_hash, royalty_recipient),
+            U512::from(30u64)
+        );
+
+        // A curator appointed by the admin can delist the collection
+        let req =
+            build_set_curator_request(account_one, marketplace_hash, Key::Account(account_two));
+        builder.exec(req).expect_success().commit();
+
+        let req = build_remove_collection_request(account_two, marketplace_hash, nft_hash);
+        builder.exec(req).expect_success().commit();
+
+        let req = build_create_listing_request(
+            account_one,
+            marketplace_hash,
+            nft_hash,
+            U256::from(3),
+            U512::from(100u64),
+            0u64,
+        );
+        builder.exec(req).expect_failure();
+        assert_user_error(&builder, ERROR_COLLECTION_NOT_ALLOWED);
+        builder.commit();
+    }
+
+    #[test]
+    fn blocked_accounts_cannot_trade() {
+        const ERROR_ACCOUNT_BLOCKED: u16 = 22;
+
+        let (mut builder, marketplace_hash, nft_hash, package_hash) = setup();
+
+        let account_one: AccountHash = *DEFAULT_ACCOUNT_ADDR;
+        let account_two: AccountHash = DEFAULT_ACCOUNTS.get(1).unwrap().account_hash();
+
+        mint_and_approve(
+            &mut builder,
+            nft_hash,
+            package_hash,
+            account_one,
+            vec![U256::from(1), U256::from(2)],
+        );
+
+        // Account two has an open offer and an outbid refund before being blocked
+        let req = create_make_offer_request(account_two, U256::from(1), marketplace_hash, nft_hash);
+        builder.exec(req).expect_success().commit();
+
+        let req =
+            build_start_auction_request(account_one, marketplace_hash, nft_hash, U256::from(2));
+        builder.exec(req).expect_success().commit();
+
+        let req = create_place_bid_request(
+            account_two,
+            U256::from(2),
+            U512::from(2),
+            marketplace_hash,
+            nft_hash,
+            5u64,
+        );
+        builder.exec(req).expect_success().commit();
+
+        let req = create_place_bid_request(
+            account_one,
+            U256::from(2),
+            U512::from(3),
+            marketplace_hash,
+            nft_hash,
+            5u64,
+        );
+        builder.exec(req).expect_success().commit();
+
+        let req = build_set_blocked_request(
+            account_two,
+            marketplace_hash,
+            Key::Account(account_two),
+            false,
+        );
+        builder.exec(req).expect_failure().commit();
+
+        let req = build_set_blocked_request(
+            account_one,
+            marketplace_hash,
+            Key::Account(account_two),
+            true,
+        );
+        builder.exec(req).expect_success().commit();
+
+        let req = build_create_listing_request(
+            account_one,
+            marketplace_hash,
+            nft_hash,
+            U256::from(1),
+            U512::from(100u64),
+            0u64,
+        );
+        builder.exec(req).expect_success().commit();
+
+        let req = create_buy_nft_request(account_two, U256::from(1), marketplace_hash, nft_hash);
+        builder.exec(req).expect_failure();
+        assert_user_error(&builder, ERROR_ACCOUNT_BLOCKED);
+        builder.commit();
+
+        // The seller cannot settle with a blocked counterparty either
+        let req =
+            build_accept_offer_request(account_one, marketplace_hash, nft_hash, U256::from(1));
+        builder.exec(req).expect_failure();
+        assert_user_error(&builder, ERROR_ACCOUNT_BLOCKED);
+        builder.commit();
+
+        // Exits stay open: the offer can be cancelled and the refund withdrawn
+        let req =
+            build_cancel_offer_request(account_two, marketplace_hash, nft_hash, U256::from(1));
+        builder.exec(req).expect_success().commit();
+
+        let req = build_withdraw_request(account_two, marketplace_hash);
+        builder.exec(req).expect_success().commit();
+
+        let req = build_set_blocked_request(
+            account_one,
+            marketplace_hash,
+            Key::Account(account_two),
+            false,
+        );
+        builder.exec(req).expect_success().commit();
+
+        let req = create_buy_nft_request(account_two, U256::from(1), marketplace_hash, nft_hash);
+        builder.exec(req).expect_success().commit();
+    }
+
+    #[test]
+    fn only_the_seller_restarts_an_auction_without_open_bids() {
+        const ERROR_PERMISSION_DENIED: u16 = 1;
+        const ERROR_AUCTION_NOT_FINISHED: u16 = 15;
+
+        let (mut builder, marketplace_hash, nft_hash, package_hash) = setup();
+
+        let account_one: AccountHash = *DEFAULT_ACCOUNT_ADDR;
+        let account_two: AccountHash = DEFAULT_ACCOUNTS.get(1).unwrap().account_hash();
+
+        mint_and_approve(
+            &mut builder,
+            nft_hash,
+            package_hash,
+            account_one,
+            vec![U256::from(1)],
+        );
+
+        let req =
+            build_start_auction_request(account_one, marketplace_hash, nft_hash, U256::from(1));
+        builder.exec(req).expect_success().commit();
+
+        let req = create_place_bid_request(
+            account_two,
+            U256::from(1),
+            U512::from(60u64),
+            marketplace_hash,
+            nft_hash,
+            5u64,
+        );
+        builder.exec(req).expect_success().commit();
+
+        // The seller cannot restart the auction while the bid is open
+        let req =
+            build_start_auction_request(account_one, marketplace_hash, nft_hash, U256::from(1));
+        builder.exec(req).expect_failure();
+        assert_user_error(&builder, ERROR_AUCTION_NOT_FINISHED);
+        builder.commit();
+
+        // Nor can a new owner of the token take the auction over
+        transfer_cep_47(
+            &mut builder,
+            account_one,
+            nft_hash,
+            Key::Account(account_two),
+            vec![U256::from(1)],
+        );
+        approve_cep_47(
+            &mut builder,
+            account_two,
+            nft_hash,
+            package_hash.into(),
+            vec![U256::from(1)],
+        );
+
+        let req =
+            build_start_auction_request(account_two, marketplace_hash, nft_hash, U256::from(1));
+        builder.exec(req).expect_failure();
+        assert_user_error(&builder, ERROR_PERMISSION_DENIED);
+        builder.commit();
+
+        // The open bid stays with the auction
+        assert_eq!(
+            get_pending_balance(&builder, marketplace_hash, Key::Account(account_two)),
+            U512::zero()
+        );
+    }
+
+    #[test]
+    fn custodial_listings_and_auctions() {
+        const ERROR_TOKEN_ALREADY_LISTED: u16 = 23;
+        const AUCTION_END_BLOCKTIME: u64 = 21 * 60 * 1000;
+
+        let (mut builder, marketplace_hash, nft_hash, package_hash) = setup();
+
+        let account_one: AccountHash = *DEFAULT_ACCOUNT_ADDR;
+        let account_two: AccountHash = DEFAULT_ACCOUNTS.get(1).unwrap().account_hash();
+        let custody = Key::Hash(package_hash.value());
+
+        mint_and_approve(
+            &mut builder,
+            nft_hash,
+            package_hash,
+            account_one,
+            vec![U256::from(1), U256::from(2), U256::from(3)],
+        );
+
+        // The token moves into the marketplace as soon as it is listed
+        let req = build_custodial_listing_request(
+            account_one,
+            marketplace_hash,
+            nft_hash,
+            U256::from(1),
+            U512::from(100u64),
+        );
+        builder.exec(req).expect_success().commit();
+
+        assert_eq!(
+            get_cep_47_owner(&builder, nft_hash, U256::from(1)),
+            Some(custody)
+        );
+
+        let req = create_buy_nft_request(account_two, U256::from(1), marketplace_hash, nft_hash);
+        builder.exec(req).expect_success().commit();
+
+        assert_eq!(
+            get_cep_47_owner(&builder, nft_hash, U256::from(1)),
+            Some(Key::Account(account_two))
+        );
+        assert_eq!(
+            get_pending_balance(&builder, marketplace_hash, Key::Account(account_one)),
+            U512::from(100u64)
+        );
+
+        // Cancelling returns the token to the seller
+        let req = build_custodial_listing_request(
+            account_one,
+            marketplace_hash,
+            nft_hash,
+            U256::from(2),
+            U512::from(100u64),
+        );
+        builder.exec(req).expect_success().commit();
+
+        let req =
+            build_cancel_listing_request(account_two, marketplace_hash, nft_hash, U256::from(2));
+        builder.exec(req).expect_failure().commit();
+
+        let req =
+            build_cancel_listing_request(account_one, marketplace_hash, nft_hash, U256::from(2));
+        builder.exec(req).expect_success().commit();
+
+        assert_eq!(
+            get_cep_47_owner(&builder, nft_hash, U256::from(2)),
+            Some(Key::Account(account_one))
+        );
+
+        // A token with an open listing cannot be escrowed for an auction
+        let req = build_create_listing_request(
+            account_one,
+            marketplace_hash,
+            nft_hash,
+            U256::from(3),
+            U512::from(100u64),
+            0u64,
+        );
+        builder.exec(req).expect_success().commit();
+
+        let req =
+            build_custodial_auction_request(account_one, marketplace_hash, nft_hash, U256::from(3));
+        builder.exec(req).expect_failure();
+        assert_user_error(&builder, ERROR_TOKEN_ALREADY_LISTED);
+        builder.commit();
+
+        let req =
+            build_cancel_listing_request(account_one, marketplace_hash, nft_hash, U256::from(3));
+        builder.exec(req).expect_success().commit();
+
+        let req =
+            build_custodial_auction_request(account_one, marketplace_hash, nft_hash, U256::from(3));
+        builder.exec(req).expect_success().commit();
+
+        assert_eq!(
+            get_cep_47_owner(&builder, nft_hash, U256::from(3)),
+            Some(custody)
+        );
+
+        let req = create_place_bid_request(
+            account_two,
+            U256::from(3),
+            U512::from(5),
+            marketplace_hash,
+            nft_hash,
+            5u64,
+        );
+        builder.exec(req).expect_success().commit();
+
+        let req = build_end_auction_request(
+            account_one,
+            marketplace_hash,
+            nft_hash,
+            U256::from(3),
+            AUCTION_END_BLOCKTIME,
+        );
+        builder.exec(req).expect_success().commit();
+
+        assert_eq!(
+            get_cep_47_owner(&builder, nft_hash, U256::from(3)),
+            Some(Key::Account(account_two))
+        );
+    }
+
+    #[test]
+    fn nft_swaps() {
+        const ERROR_SWAP_DOESNT_EXIST: u16 = 25;
+
+        let (mut builder, marketplace_hash, nft_hash, package_hash) = setup();
+
+        let account_one: AccountHash = *DEFAULT_ACCOUNT_ADDR;
+        let account_two: AccountHash = DEFAULT_ACCOUNTS.get(1).unwrap().account_hash();
+
+        mint_and_approve(
+            &mut builder,
+            nft_hash,
+            package_hash,
+            account_one,
+            vec![U256::from(1)],
+        );
+        mint_and_approve(
+            &mut builder,
+            nft_hash,
+            package_hash,
+            account_two,
+            vec![U256::from(2)],
+        );
+
+        // Listings of the swapped tokens are taken down
+        for (owner, token_id) in [(account_one, 1u64), (account_two, 2)] {
+            let req = build_create_listing_request(
+                owner,
+                marketplace_hash,
+                nft_hash,
+                U256::from(token_id),
+                U512::from(100u64),
+                0u64,
+            );
+            builder.exec(req).expect_success().commit();
+        }
+
+        // Token 1 plus 10 motes for token 2
+        let req = build_propose_swap_request(
+            account_one,
+            marketplace_hash,
+            Key::Account(account_two),
+            vec![(nft_hash, U256::from(1))],
+            vec![(nft_hash, U256::from(2))],
+            U512::from(10u64),
+        );
+        builder.exec(req).expect_success().commit();
+
+        let req = build_accept_swap_request(account_one, marketplace_hash, 0);
+        builder.exec(req).expect_failure().commit();
+
+        let req = build_accept_swap_request(account_two, marketplace_hash, 0);
+        builder.exec(req).expect_success().commit();
+
+        assert_eq!(
+            get_cep_47_owner(&builder, nft_hash, U256::from(1)),
+            Some(Key::Account(account_two))
+        );
+        assert_eq!(
+            get_cep_47_owner(&builder, nft_hash, U256::from(2)),
+            Some(Key::Account(account_one))
+        );
+        assert_eq!(
+            get_pending_balance(&builder, marketplace_hash, Key::Account(account_two)),
+            U512::from(10u64)
+        );
+        assert_eq!(
+            get_collection_listing_count(&builder, marketplace_hash, nft_hash),
+            0
+        );
+        assert_eq!(
+            get_floor_listing(&builder, marketplace_hash, nft_hash),
+            None
+        );
+
+        // Accepted proposals are gone
+        let req = build_accept_swap_request(account_two, marketplace_hash, 0);
+        builder.exec(req).expect_failure();
+        assert_user_error(&builder, ERROR_SWAP_DOESNT_EXIST);
+        builder.commit();
+
+        // A proposal without CSPR can be cancelled by the proposer only
+        approve_cep_47(
+            &mut builder,
+            account_two,
+            nft_hash,
+            package_hash.into(),
+            vec![U256::from(1)],
+        );
+
+        let req = build_propose_swap_request(
+            account_two,
+            marketplace_hash,
+            Key::Account(account_one),
+            vec![(nft_hash, U256::from(1))],
+            vec![(nft_hash, U256::from(2))],
+            U512::zero(),
+        );
+        builder.exec(req).expect_success().commit();
+
+        let req = build_cancel_swap_request(account_one, marketplace_hash, 1);
+        builder.exec(req).expect_failure().commit();
+
+        let req = build_cancel_swap_request(account_two, marketplace_hash, 1);
+        builder.exec(req).expect_success().commit();
+
+        let req = build_accept_swap_request(account_one, marketplace_hash, 1);
+        builder.exec(req).expect_failure();
+        assert_user_error(&builder, ERROR_SWAP_DOESNT_EXIST);
+        builder.commit();
+    }
+
+    #[test]
+    fn rentals() {
+        const ERROR_RENTAL_ACTIVE: u16 = 28;
+        const ERROR_INVALID_RENTAL_DURATION: u16 = 29;
+        const MILLISECONDS_IN_DAY: u64 = 24 * 60 * 60 * 1000;
+
+        let (mut builder, marketplace_hash, nft_hash, package_hash) = setup();
+
+        let account_one: AccountHash = *DEFAULT_ACCOUNT_ADDR;
+        let account_two: AccountHash = DEFAULT_ACCOUNTS.get(1).unwrap().account_hash();
+
+        mint_and_approve(
+            &mut builder,
+            nft_hash,
+            package_hash,
+            account_one,
+            vec![U256::from(1)],
+        );
+
+        let req = build_list_for_rent_request(
+            account_one,
+            marketplace_hash,
+            nft_hash,
+            U256::from(1),
+            U512::from(10u64),
+            3,
+        );
+        builder.exec(req).expect_success().commit();
+
+        assert_eq!(
+            get_cep_47_owner(&builder, nft_hash, U256::from(1)),
+            Some(Key::Hash(package_hash.value()))
+        );
+
+        let req = build_rent_request(
+            account_two,
+            marketplace_hash,
+            nft_hash,
+            U256::from(1),
+            5,
+            40,
+        );
+        builder.exec(req).expect_failure();
+        assert_user_error(&builder, ERROR_INVALID_RENTAL_DURATION);
+        builder.commit();
+
+        let req = build_rent_request(
+            account_two,
+            marketplace_hash,
+            nft_hash,
+            U256::from(1),
+            2,
+            40,
+        );
+        builder.exec(req).expect_success().commit();
+
+        // The owner is paid upfront and the renter is recorded as the user
+        assert_eq!(
+            get_pending_balance(&builder, marketplace_hash, Key::Account(account_one)),
+            U512::from(20u64)
+        );
+
+        let rental: RentalData = get_marketplace_dictionary_item(
+            &builder,
+            marketplace_hash,
+            "rentals",
+            &listing_key(nft_hash, U256::from(1)),
+        )
+        .expect("should have rental");
+        assert_eq!(rental.user, Some(Key::Account(account_two)));
+        assert_eq!(rental.expiration_time, Some(40 + 2 * MILLISECONDS_IN_DAY));
+
+        let req = build_query_request(
+            account_two,
+            marketplace_hash,
+            "get_user",
+            runtime_args! {
+                "contract_hash" => nft_hash.to_formatted_string(),
+                "token_id" => U256::from(1),
+            },
+        );
+        builder.exec(req).expect_success().commit();
+
+        let req = build_end_rental_request(
+            account_two,
+            marketplace_hash,
+            nft_hash,
+            U256::from(1),
+            MILLISECONDS_IN_DAY,
+        );
+        builder.exec(req).expect_failure();
+        assert_user_error(&builder, ERROR_RENTAL_ACTIVE);
+        builder.commit();
+
+        // Once expired, anyone can send the token back to its owner
+        let req = build_end_rental_request(
+            account_two,
+            marketplace_hash,
+            nft_hash,
+            U256::from(1),
+            3 * MILLISECONDS_IN_DAY,
+        );
+        builder.exec(req).expect_success().commit();
+
+        assert_eq!(
+            get_cep_47_owner(&builder, nft_hash, U256::from(1)),
+            Some(Key::Account(account_one))
+        );
+    }
+
+    #[test]
+    fn lazy_listings_mint_on_purchase() {
+        const ERROR_TOKEN_ALREADY_MINTED: u16 = 30;
+
+        let (mut builder, marketplace_hash, nft_hash, package_hash) = setup();
+
+        let account_one: AccountHash = *DEFAULT_ACCOUNT_ADDR;
+        let account_two: AccountHash = DEFAULT_ACCOUNTS.get(1).unwrap().account_hash();
+
+        mint_and_approve(
+            &mut builder,
+            nft_hash,
+            package_hash,
+            account_one,
+            vec![U256::from(1)],
+        );
+
+        // Only the creator assigned by the curator can lazy list
+        let req = build_create_lazy_listing_request(
+            account_two,
+            marketplace_hash,
+            nft_hash,
+            U256::from(7),
+            U512::from(100u64),
+        );
+        builder.exec(req).expect_failure().commit();
+
+        let req = build_set_collection_creator_request(
+            account_one,
+            marketplace_hash,
+            nft_hash,
+            Some(Key::Account(account_two)),
+        );
+        builder.exec(req).expect_success().commit();
+
+        let req = build_create_lazy_listing_request(
+            account_two,
+            marketplace_hash,
+            nft_hash,
+            U256::from(1),
+            U512::from(100u64),
+        );
+        builder.exec(req).expect_failure();
+        assert_user_error(&builder, ERROR_TOKEN_ALREADY_MINTED);
+        builder.commit();
+
+        let req = build_create_lazy_listing_request(
+            account_two,
+            marketplace_hash,
+            nft_hash,
+            U256::from(7),
+            U512::from(100u64),
+        );
+        builder.exec(req).expect_success().commit();
+
+        assert_eq!(get_cep_47_owner(&builder, nft_hash, U256::from(7)), None);
+        assert_eq!(
+            get_collection_listing_count(&builder, marketplace_hash, nft_hash),
+            1
+        );
+
+        let req = create_buy_nft_request(account_one, U256::from(7), marketplace_hash, nft_hash);
+        builder.exec(req).expect_success().commit();
+
+        // The token is minted straight to the buyer and the creator is paid
+        assert_eq!(
+            get_cep_47_owner(&builder, nft_hash, U256::from(7)),
+            Some(Key::Account(account_one))
+        );
+        assert_eq!(
+            get_pending_balance(&builder, marketplace_hash, Key::Account(account_two)),
+            U512::from(100u64)
+        );
+        assert_eq!(
+            get_collection_listing_count(&builder, marketplace_hash, nft_hash),
+            0
+        );
+
+        // A cancelled lazy listing can no longer be bought
+        let req = build_create_lazy_listing_request(
+            account_two,
+            marketplace_hash,
+            nft_hash,
+            U256::from(8),
+            U512::from(100u64),
+        );
+        builder.exec(req).expect_success().commit();
+
+        let req =
+            build_cancel_listing_request(account_two, marketplace_hash, nft_hash, U256::from(8));
+        builder.exec(req).expect_success().commit();
+
+        let req = create_buy_nft_request(account_one, U256::from(8), marketplace_hash, nft_hash);
+        builder.exec(req).expect_failure().commit();
+
+        assert_eq!(get_cep_47_owner(&builder, nft_hash, U256::from(8)), None);
+
+        // A token minted outside the marketplace can be listed normally over its lazy listing
+        let req = build_create_lazy_listing_request(
+            account_two,
+            marketplace_hash,
+            nft_hash,
+            U256::from(9),
+            U512::from(100u64),
+        );
+        builder.exec(req).expect_success().commit();
+
+        mint_and_approve(
+            &mut builder,
+            nft_hash,
+            package_hash,
+            account_two,
+            vec![U256::from(9)],
+        );
+
+        let req = build_create_listing_request(
+            account_two,
+            marketplace_hash,
+            nft_hash,
+            U256::from(9),
+            U512::from(100u64),
+            0u64,
+        );
+        builder.exec(req).expect_success().commit();
+
+        let req = create_buy_nft_request(account_one, U256::from(9), marketplace_hash, nft_hash);
+        builder.exec(req).expect_success().commit();
+
+        assert_eq!(
+            get_cep_47_owner(&builder, nft_hash, U256::from(9)),
+            Some(Key::Account(account_one))
+        );
+    }
+
+    #[test]
+    fn drops_with_allowlist_phases() {
+        const ERROR_DROP_NOT_ACTIVE: u16 = 32;
+        const ERROR_DROP_SOLD_OUT: u16 = 33;
+        const ERROR_WALLET_LIMIT_REACHED: u16 = 34;
+        const ERROR_INVALID_DROP_PHASE: u16 = 41;
+
+        let (mut builder, marketplace_hash, nft_hash, _package_hash) = setup();
+
+        let account_one: AccountHash = *DEFAULT_ACCOUNT_ADDR;
+        let account_two: AccountHash = DEFAULT_ACCOUNTS.get(1).unwrap().account_hash();
+        let fee_recipient = Key::Account(AccountHash::new([7u8; 32]));
+        let royalty_recipient = Key::Account(AccountHash::new([8u8; 32]));
+
+        let req =
+            build_set_marketplace_fee_request(account_one, marketplace_hash, 1_000, fee_recipient);
+        builder.exec(req).expect_success().commit();
+
+        // Royalties, collection fees and exemptions apply to resales, not to the drop itself
+        let req = build_register_collection_request(
+            account_one,
+            marketplace_hash,
+            nft_hash,
+            Some(royalty_recipient),
+            10,
+        );
+        builder.exec(req).expect_success().commit();
+
+        let req =
+            build_set_collection_fee_request(account_one, marketplace_hash, nft_hash, Some(5_000));
+        builder.exec(req).expect_success().commit();
+
+        let req = build_set_fee_exempt_request(
+            account_one,
+            marketplace_hash,
+            Key::Account(account_two),
+            true,
+        );
+        builder.exec(req).expect_success().commit();
+
+        let req = build_set_collection_creator_request(
+            account_one,
+            marketplace_hash,
+            nft_hash,
+            Some(Key::Account(account_two)),
+        );
+        builder.exec(req).expect_success().commit();
+
+        let req =
+            build_create_drop_request(account_two, marketplace_hash, nft_hash, U256::from(100), 3);
+        builder.exec(req).expect_success().commit();
+
+        // Allowlist phase first, then a public phase without a wallet cap
+        let req = build_add_drop_phase_request(
+            account_two,
+            marketplace_hash,
+            nft_hash,
+            true,
+            U512::from(10u64),
+            0,
+            1_000,
+            1,
+        );
+        builder.exec(req).expect_success().commit();
+
+        let req = build_add_drop_phase_request(
+            account_two,
+            marketplace_hash,
+            nft_hash,
+            false,
+            U512::from(20u64),
+            1_001,
+            100_000,
+            0,
+        );
+        builder.exec(req).expect_success().commit();
+
+        // Phases must not be empty or overlap the ones already added
+        let req = build_add_drop_phase_request(
+            account_two,
+            marketplace_hash,
+            nft_hash,
+            false,
+            U512::from(20u64),
+            200_000,
+            200_000,
+            0,
+        );
+        builder.exec(req).expect_failure();
+        assert_user_error(&builder, ERROR_INVALID_DROP_PHASE);
+        builder.commit();
+
+        let req = build_add_drop_phase_request(
+            account_two,
+            marketplace_hash,
+            nft_hash,
+            false,
+            U512::from(20u64),
+            100_000,
+            200_000,
+            0,
+        );
+        builder.exec(req).expect_failure();
+        assert_user_error(&builder, ERROR_INVALID_DROP_PHASE);
+        builder.commit();
+
+        let req = build_buy_drop_request(account_one, marketplace_hash, nft_hash, 1, 40);
+        builder.exec(req).expect_failure();
+        assert_user_error(&builder, ERROR_DROP_NOT_ACTIVE);
+        builder.commit();
+
+        let req = build_set_drop_allowlist_request(
+            account_two,
+            marketplace_hash,
+            nft_hash,
+            vec![Key::Account(account_one)],
+            true,
+        );
+        builder.exec(req).expect_success().commit();
+
+        let req = build_buy_drop_request(account_one, marketplace_hash, nft_hash, 2, 40);
+        builder.exec(req).expect_failure();
+        assert_user_error(&builder, ERROR_WALLET_LIMIT_REACHED);
+        builder.commit();
+
+        let req = build_buy_drop_request(account_one, marketplace_hash, nft_hash, 1, 40);
+        builder.exec(req).expect_success().commit();
+
+        assert_eq!(
+            get_cep_47_owner(&builder, nft_hash, U256::from(100)),
+            Some(Key::Account(account_one))
+        );
+
+        let req = build_buy_drop_request(account_one, marketplace_hash, nft_hash, 2, 2_000);
+        builder.exec(req).expect_success().commit();
+
+        assert_eq!(
+            get_cep_47_owner(&builder, nft_hash, U256::from(102)),
+            Some(Key::Account(account_one))
+        );
+
+        // 10% of 10 + 2 * 20 goes to the marketplace, the rest to the creator
+        assert_eq!(
+            get_pending_balance(&builder, marketplace_hash, fee_recipient),
+            U512::from(5u64)
+        );
+        assert_eq!(
+            get_pending_balance(&builder, marketplace_hash, Key::Account(account_two)),
+            U512::from(45u64)
+        );
+        assert_eq!(
+            get_pending_balance(&builder, marketplace_hash, royalty_recipient),
+            U512::zero()
+        );
+
+        let req = build_buy_drop_request(account_one, marketplace_hash, nft_hash, 1, 2_000);
+        builder.exec(req).expect_failure();
+        assert_user_error(&builder, ERROR_DROP_SOLD_OUT);
+        builder.commit();
+    }
+
+    #[test]
+    fn referrers_share_the_marketplace_fee() {
+        const AUCTION_END_BLOCKTIME: u64 = 21 * 60 * 1000;
+
+        let (mut builder, marketplace_hash, nft_hash, package_hash) = setup();
+
+        let account_one: AccountHash = *DEFAULT_ACCOUNT_ADDR;
+        let account_two: AccountHash = DEFAULT_ACCOUNTS.get(1).unwrap().account_hash();
+        let fee_recipient = Key::Account(AccountHash::new([7u8; 32]));
+        let referrer = Key::Account(AccountHash::new([9u8; 32]));
+
+        mint_and_approve(
+            &mut builder,
+            nft_hash,
+            package_hash,
+            account_one,
+            vec![
+                U256::from(1),
+                U256::from(2),
+                U256::from(3),
+                U256::from(4),
+                U256::from(5),
+            ],
+        );
+
+        // 10% marketplace fee, half of it goes to the referrer
+        let req =
+            build_set_marketplace_fee_request(account_one, marketplace_hash, 1_000, fee_recipient);
+        builder.exec(req).expect_success().commit();
+
+        let req = build_set_referral_share_request(account_one, marketplace_hash, 5_000);
+        builder.exec(req).expect_success().commit();
+
+        let req = build_create_listing_request(
+            account_one,
+            marketplace_hash,
+            nft_hash,
+            U256::from(1),
+            U512::from(100u64),
+            0u64,
+        );
+        builder.exec(req).expect_success().commit();
+
+        let req = create_referred_buy_nft_request(
+            account_two,
+            U256::from(1),
+            marketplace_hash,
+            nft_hash,
+            Some(referrer),
+        );
+        builder.exec(req).expect_success().commit();
+
+        assert_eq!(
+            get_pending_balance(&builder, marketplace_hash, referrer),
+            U512::from(5u64)
+        );
+        assert_eq!(
+            get_pending_balance(&builder, marketplace_hash, fee_recipient),
+            U512::from(5u64)
+        );
+        assert_eq!(
+            get_pending_balance(&builder, marketplace_hash, Key::Account(account_one)),
+            U512::from(90u64)
+        );
+
+        // Auctions pay the referrer of the winning bid
+        let req =
+            build_start_auction_request(account_one, marketplace_hash, nft_hash, U256::from(2));
+        builder.exec(req).expect_success().commit();
+
+        let req = create_referred_place_bid_request(
+            account_two,
+            U256::from(2),
+            U512::from(60),
+            marketplace_hash,
+            nft_hash,
+            5u64,
+            Some(referrer),
+        );
+        builder.exec(req).expect_success().commit();
+
+        let req = build_end_auction_request(
+            account_one,
+            marketplace_hash,
+            nft_hash,
+            U256::from(2),
+            AUCTION_END_BLOCKTIME,
+        );
+        builder.exec(req).expect_success().commit();
+
+        let earnings: U512 = get_marketplace_dictionary_item(
+            &builder,
+            marketplace_hash,
+            "referral_earnings",
+            &key_to_str(&referrer),
+        )
+        .unwrap_or_default();
+        assert_eq!(earnings, U512::from(8u64));
+
+        let req = build_query_request(
+            account_two,
+            marketplace_hash,
+            "get_referral_earnings",
+            runtime_args! { "referrer" => referrer },
+        );
+        builder.exec(req).expect_success().commit();
+
+        // Referral fees are credited to the ledger like any other payout
+        assert_eq!(
+            get_pending_balance(&builder, marketplace_hash, referrer),
+            U512::from(8u64)
+        );
+        assert_eq!(
+            get_pending_balance(&builder, marketplace_hash, fee_recipient),
+            U512::from(8u64)
+        );
+
+        // The batch session forwards the referrer like a single purchase
+        let req = build_create_listing_request(
+            account_one,
+            marketplace_hash,
+            nft_hash,
+            U256::from(5),
+            U512::from(100u64),
+            0u64,
+        );
+        builder.exec(req).expect_success().commit();
+
+        let req = create_referred_batch_buy_request(
+            account_two,
+            marketplace_hash,
+            nft_hash,
+            vec![U256::from(5)],
+            U512::from(100u64),
+            false,
+            Some(referrer),
+        );
+        builder.exec(req).expect_success().commit();
+
+        assert_eq!(
+            get_pending_balance(&builder, marketplace_hash, referrer),
+            U512::from(13u64)
+        );
+        assert_eq!(
+            get_pending_balance(&builder, marketplace_hash, fee_recipient),
+            U512::from(13u64)
+        );
+
+        // A buyer naming itself, or a blocked referrer, leaves the whole fee to the marketplace
+        let req = build_set_blocked_request(account_one, marketplace_hash, referrer, true);
+        builder.exec(req).expect_success().commit();
+
+        for (token_id, token_referrer) in [(3u64, Key::Account(account_two)), (4, referrer)] {
+            let req = build_create_listing_request(
+                account_one,
+                marketplace_hash,
+                nft_hash,
+                U256::from(token_id),
+                U512::from(100u64),
+                0u64,
+            );
+            builder.exec(req).expect_success().commit();
+
+            let req = create_referred_buy_nft_request(
+                account_two,
+                U256::from(token_id),
+                marketplace_hash,
+                nft_hash,
+                Some(token_referrer),
+            );
+            builder.exec(req).expect_success().commit();
+        }
+
+        assert_eq!(
+            get_pending_balance(&builder, marketplace_hash, referrer),
+            U512::from(13u64)
+        );
+        assert_eq!(
+            get_pending_balance(&builder, marketplace_hash, Key::Account(account_two)),
+            U512::zero()
+        );
+        assert_eq!(
+            get_pending_balance(&builder, marketplace_hash, fee_recipient),
+            U512::from(33u64)
+        );
+    }
+
+    #[test]
+    fn collection_fee_overrides_and_exemptions() {
+        const ERROR_PERMISSION_DENIED: u16 = 1;
+        const ERROR_INVALID_FEE: u16 = 36;
+
+        let (mut builder, marketplace_hash, nft_hash, package_hash) = setup();
+
+        let account_one: AccountHash = *DEFAULT_ACCOUNT_ADDR;
+        let account_two: AccountHash = DEFAULT_ACCOUNTS.get(1).unwrap().account_hash();
+        let fee_recipient = Key::Account(AccountHash::new([7u8; 32]));
+        let seller = Key::Account(account_one);
+
+        mint_and_approve(
+            &mut builder,
+            nft_hash,
+            package_hash,
+            account_one,
+            vec![U256::from(1), U256::from(2), U256::from(3)],
+        );
+
+        let req =
+            build_set_marketplace_fee_request(account_one, marketplace_hash, 1_000, fee_recipient);
+        builder.exec(req).expect_success().commit();
+
+        // Only the admin sets overrides
+        let req =
+            build_set_collection_fee_request(account_two, marketplace_hash, nft_hash, Some(0));
+        builder.exec(req).expect_failure();
+        assert_user_error(&builder, ERROR_PERMISSION_DENIED);
+
+        let req =
+            build_set_collection_fee_request(account_one, marketplace_hash, nft_hash, Some(10_001));
+        builder.exec(req).expect_failure();
+        assert_user_error(&builder, ERROR_INVALID_FEE);
+
+        // Launch partner collection at 0%
+        let req =
+            build_set_collection_fee_request(account_one, marketplace_hash, nft_hash, Some(0));
+        builder.exec(req).expect_success().commit();
+
+        let req = build_create_listing_request(
+            account_one,
+            marketplace_hash,
+            nft_hash,
+            U256::from(1),
+            U512::from(100u64),
+            0u64,
+        );
+        builder.exec(req).expect_success().commit();
+
+        let req = create_buy_nft_request(account_two, U256::from(1), marketplace_hash, nft_hash);
+        builder.exec(req).expect_success().commit();
+
+        assert_eq!(
+            get_pending_balance(&builder, marketplace_hash, seller),
+            U512::from(100u64)
+        );
+        assert_eq!(
+            get_pending_balance(&builder, marketplace_hash, fee_recipient),
+            U512::zero()
+        );
+
+        // Back to the global fee, but the buyer is exempt
+        let req = build_set_collection_fee_request(account_one, marketplace_hash, nft_hash, None);
+        builder.exec(req).expect_success().commit();
+
+        let req = build_set_fee_exempt_request(
+            account_one,
+            marketplace_hash,
+            Key::Account(account_two),
+            true,
+        );
+        builder.exec(req).expect_success().commit();
+
+        let req = build_create_listing_request(
+            account_one,
+            marketplace_hash,
+            nft_hash,
+            U256::from(2),
+            U512::from(100u64),
+            0u64,
+        );
+        builder.exec(req).expect_success().commit();
+
+        let req = create_buy_nft_request(account_two, U256::from(2), marketplace_hash, nft_hash);
+        builder.exec(req).expect_success().commit();
+
+        assert_eq!(
+            get_pending_balance(&builder, marketplace_hash, seller),
+            U512::from(200u64)
+        );
+        assert_eq!(
+            get_pending_balance(&builder, marketplace_hash, fee_recipient),
+            U512::zero()
+        );
+
+        // Lifting the exemption charges the global fee again
+        let req = build_set_fee_exempt_request(
+            account_one,
+            marketplace_hash,
+            Key::Account(account_two),
+            false,
+        );
+        builder.exec(req).expect_success().commit();
+
+        let req = build_query_request(
+            account_two,
+            marketplace_hash,
+            "get_fee_quote",
+            runtime_args! {
+                "contract_hash" => nft_hash.to_formatted_string(),
+                "price" => U512::from(100u64),
+                "seller" => seller,
+                "buyer" => Key::Account(account_two),
+            },
+        );
+        builder.exec(req).expect_success().commit();
+
+        let req = build_create_listing_request(
+            account_one,
+            marketplace_hash,
+            nft_hash,
+            U256::from(3),
+            U512::from(100u64),
+            0u64,
+        );
+        builder.exec(req).expect_success().commit();
+
+        let req = create_buy_nft_request(account_two, U256::from(3), marketplace_hash, nft_hash);
+        builder.exec(req).expect_success().commit();
+
+        assert_eq!(
+            get_pending_balance(&builder, marketplace_hash, seller),
+            U512::from(290u64)
+        );
+        assert_eq!(
+            get_pending_balance(&builder, marketplace_hash, fee_recipient),
+            U512::from(10u64)
+        );
+    }
+
+    #[test]
+    fn sale_history_records_trades() {
+        let (mut builder, marketplace_hash, nft_hash, package_hash) = setup();
+
+        let account_one: AccountHash = *DEFAULT_ACCOUNT_ADDR;
+        let account_two: AccountHash = DEFAULT_ACCOUNTS.get(1).unwrap().account_hash();
+        let offer_price = U512::from(100000000u64);
+
+        mint_and_approve(
+            &mut builder,
+            nft_hash,
+            package_hash,
+            account_one,
+            vec![U256::from(1), U256::from(2)],
+        );
+
+        let req = build_create_listing_request(
+            account_one,
+            marketplace_hash,
+            nft_hash,
+            U256::from(1),
+            U512::from(100u64),
+            0u64,
+        );
+        builder.exec(req).expect_success().commit();
+
+        let req = create_buy_nft_request(account_two, U256::from(1), marketplace_hash, nft_hash);
+        builder.exec(req).expect_success().commit();
+
+        let req = create_make_offer_request(account_two, U256::from(2), marketplace_hash, nft_hash);
+        builder.exec(req).expect_success().commit();
+
+        let req =
+            build_accept_offer_request(account_one, marketplace_hash, nft_hash, U256::from(2));
+        builder.exec(req).expect_success().commit();
+
+        // The listing and the offer are cleared, the ledger keeps both trades
+        let listing_sale: SaleRecord =
+            get_marketplace_dictionary_item(&builder, marketplace_hash, "sales", "0")
+                .expect("should have recorded the listing sale");
+        assert_eq!(listing_sale.token_contract, nft_hash);
+        assert_eq!(listing_sale.token_id, U256::from(1));
+        assert_eq!(listing_sale.seller, Key::Account(account_one));
+        assert_eq!(listing_sale.buyer, Key::Account(account_two));
+        assert_eq!(listing_sale.price, U512::from(100u64));
+        assert_eq!(listing_sale.payment_asset, None);
+        assert_eq!(listing_sale.sale_type, 0);
+
+        let offer_sale: SaleRecord =
+            get_marketplace_dictionary_item(&builder, marketplace_hash, "sales", "1")
+                .expect("should have recorded the offer sale");
+        assert_eq!(offer_sale.token_id, U256::from(2));
+        assert_eq!(offer_sale.buyer, Key::Account(account_two));
+        assert_eq!(offer_sale.price, offer_price);
+        assert_eq!(offer_sale.sale_type, 1);
+
+        let last_sale_price: Option<U512> = get_marketplace_dictionary_item(
+            &builder,
+            marketplace_hash,
+            "last_sale_prices",
+            &listing_key(nft_hash, U256::from(2)),
+        );
+        assert_eq!(last_sale_price, Some(offer_price));
+
+        let volume: U512 = get_marketplace_dictionary_item(
+            &builder,
+            marketplace_hash,
+            "collection_volume",
+            &hex::encode(nft_hash.value()),
+        )
+        .unwrap_or_default();
+        assert_eq!(volume, offer_price + U512::from(100u64));
+
+        for (entry_point, args) in [
+            ("get_sale", runtime_args! { "sale_id" => 1u64 }),
+            ("get_sale_count", runtime_args! {}),
+            (
+                "get_last_sale_price",
+                runtime_args! {
+                    "contract_hash" => nft_hash.to_formatted_string(),
+                    "token_id" => U256::from(1),
+                },
+            ),
+            (
+                "get_collection_volume",
+                runtime_args! { "contract_hash" => nft_hash.to_formatted_string() },
+            ),
+        ] {
+            let req = build_query_request(account_two, marketplace_hash, entry_point, args);
+            builder.exec(req).expect_success().commit();
+        }
+    }
+
+    #[test]
+    fn collection_stats_track_floor_and_volume() {
+        const AUCTION_END_BLOCKTIME: u64 = 21 * 60 * 1000;
+
+        let (mut builder, marketplace_hash, nft_hash, package_hash) = setup();
+
+        let account_one: AccountHash = *DEFAULT_ACCOUNT_ADDR;
+        let account_two: AccountHash = DEFAULT_ACCOUNTS.get(1).unwrap().account_hash();
+        let collection_key = hex::encode(nft_hash.value());
+
+        mint_and_approve(
+            &mut builder,
+            nft_hash,
+            package_hash,
+            account_one,
+            vec![U256::from(1), U256::from(2), U256::from(3), U256::from(4)],
+        );
+
+        for (token_id, price) in [(1u64, 300u64), (2, 100), (3, 200)] {
+            let req = build_create_listing_request(
+                account_one,
+                marketplace_hash,
+                nft_hash,
+                U256::from(token_id),
+                U512::from(price),
+                0u64,
+            );
+            builder.exec(req).expect_success().commit();
+        }
+
+        // The floor listing is kept up to date by the listing changes themselves
+        let floor_price = |builder: &InMemoryWasmTestBuilder| -> Option<U512> {
+            get_floor_listing(builder, marketplace_hash, nft_hash).map(|(price, _token_id)| price)
+        };
+
+        assert_eq!(floor_price(&builder), Some(U512::from(100u64)));
+        assert_eq!(
+            get_collection_listing_count(&builder, marketplace_hash, nft_hash),
+            3
+        );
+
+        // Raising the floor listing moves the floor to the next cheapest one
+        let req = build_update_listing_request(
+            account_one,
+            marketplace_hash,
+            nft_hash,
+            U256::from(2),
+            U512::from(400u64),
+            0u64,
+        );
+        builder.exec(req).expect_success().commit();
+        assert_eq!(floor_price(&builder), Some(U512::from(200u64)));
+
+        let req =
+            build_cancel_listing_request(account_one, marketplace_hash, nft_hash, U256::from(3));
+        builder.exec(req).expect_success().commit();
+        assert_eq!(floor_price(&builder), Some(U512::from(300u64)));
+
+        let req = create_buy_nft_request(account_two, U256::from(1), marketplace_hash, nft_hash);
+        builder.exec(req).expect_success().commit();
+        assert_eq!(floor_price(&builder), Some(U512::from(400u64)));
+        assert_eq!(
+            get_collection_listing_count(&builder, marketplace_hash, nft_hash),
+            1
+        );
+
+        let total_sales: u64 = get_marketplace_dictionary_item(
+            &builder,
+            marketplace_hash,
+            "collection_sales",
+            &collection_key,
+        )
+        .unwrap_or_default();
+        assert_eq!(total_sales, 1);
+
+        let daily_volume: U512 = get_marketplace_dictionary_item(
+            &builder,
+            marketplace_hash,
+            "collection_daily_volume",
+            &collection_day_key(nft_hash, 0),
+        )
+        .unwrap_or_default();
+        assert_eq!(daily_volume, U512::from(300u64));
+
+        // Auctions are counted while they run
+        let req =
+            build_start_auction_request(account_one, marketplace_hash, nft_hash, U256::from(4));
+        builder.exec(req).expect_success().commit();
+
+        let active_auctions = |builder: &InMemoryWasmTestBuilder| -> u64 {
+            get_marketplace_dictionary_item(
+                builder,
+                marketplace_hash,
+                "collection_auctions",
+                &collection_key,
+            )
+            .unwrap_or_default()
+        };
+        assert_eq!(active_auctions(&builder), 1);
+
+        // Restarting the auction before it has bids does not count it twice
+        let req =
+            build_start_auction_request(account_one, marketplace_hash, nft_hash, U256::from(4));
+        builder.exec(req).expect_success().commit();
+        assert_eq!(active_auctions(&builder), 1);
+
+        let req = create_place_bid_request(
+            account_two,
+            U256::from(4),
+            U512::from(60u64),
+            marketplace_hash,
+            nft_hash,
+            5u64,
+        );
+        builder.exec(req).expect_success().commit();
+
+        let req = build_end_auction_request(
+            account_one,
+            marketplace_hash,
+            nft_hash,
+            U256::from(4),
+            AUCTION_END_BLOCKTIME,
+        );
+        builder.exec(req).expect_success().commit();
+        assert_eq!(active_auctions(&builder), 0);
+
+        for (entry_point, args) in [
+            (
+                "get_collection_stats",
+                runtime_args! { "contract_hash" => nft_hash.to_formatted_string() },
+            ),
+            (
+                "get_daily_volume",
+                runtime_args! {
+                    "contract_hash" => nft_hash.to_formatted_string(),
+                    "day" => 0u64,
+                },
+            ),
+        ] {
+            let req = build_query_request(account_two, marketplace_hash, entry_point, args);
+            builder.exec(req).expect_success().commit();
+        }
+    }
+
+    #[test]
+    fn typed_collection_and_account_args() {
+        use marketplace_actions::request_builder;
+        use marketplace_client::Marketplace;
+
+        const ERROR_INVALID_TOKEN_CONTRACT: u16 = 37;
+        const ERROR_INVALID_ACCOUNT: u16 = 38;
+        const ERROR_UNKNOWN_COLLECTION_PACKAGE: u16 = 42;
+
+        let (mut builder, marketplace_hash, nft_hash, package_hash) = setup();
+
+        let account_one: AccountHash = *DEFAULT_ACCOUNT_ADDR;
+        let account_two: AccountHash = DEFAULT_ACCOUNTS.get(1).unwrap().account_hash();
+
+        mint_and_approve(
+            &mut builder,
+            nft_hash,
+            package_hash,
+            account_one,
+            vec![U256::from(1), U256::from(2)],
+        );
+
+        // The collection as a `Key`
+        let req = build_query_request(
+            account_one,
+            marketplace_hash,
+            "create_listing",
+            runtime_args! {
+                "contract_hash" => Key::Hash(nft_hash.value()),
+                "token_id" => U256::from(1),
+                "price" => U512::from(100u64),
+                "duration_minutes" => 0u64,
+            },
+        );
+        builder.exec(req).expect_success().commit();
+
+        let listing: Option<ListingData> = get_marketplace_dictionary_item(
+            &builder,
+            marketplace_hash,
+            "listings",
+            &listing_key(nft_hash, U256::from(1)),
+        );
+        assert_eq!(
+            listing.map(|listing| listing.price),
+            Some(U512::from(100u64))
+        );
+
+        // In the formatted form older clients send
+        let req = build_query_request(
+            account_two,
+            marketplace_hash,
+            "get_listing",
+            runtime_args! {
+                "contract_hash" => nft_hash.to_formatted_string(),
+                "token_id" => U256::from(1),
+            },
+        );
+        builder.exec(req).expect_success().commit();
+
+        let req = create_make_offer_request(account_two, U256::from(2), marketplace_hash, nft_hash);
+        builder.exec(req).expect_success().commit();
+
+        let req = build_query_request(
+            account_one,
+            marketplace_hash,
+            "get_offer",
+            runtime_args! {
+                "contract_hash" => Key::Hash(nft_hash.value()),
+                "token_id" => U256::from(2),
+                "offerer" => Key::Account(account_two),
+            },
+        );
+        builder.exec(req).expect_success().commit();
+
+        // Malformed arguments revert with an explicit error instead of trapping
+        let req = build_query_request(
+            account_one,
+            marketplace_hash,
+            "create_listing",
+            runtime_args! {
+                "contract_hash" => "contract-not-a-hash",
+                "token_id" => U256::from(2),
+                "price" => U512::from(100u64),
+                "duration_minutes" => 0u64,
+            },
+        );
+        builder.exec(req).expect_failure();
+        assert_user_error(&builder, ERROR_INVALID_TOKEN_CONTRACT);
+
+        // Package hashes, as a `ContractPackageHash` or formatted, are resolved through the
+        // registry the curator keeps, and are rejected until the package is recorded there
+        let nft_package_hash = builder
+            .get_contract(nft_hash)
+            .expect("should have cep47 contract")
+            .contract_package_hash();
+
+        let package_args = [
+            CLValue::from_t(nft_package_hash.to_formatted_string()).unwrap(),
+            CLValue::from_t(nft_package_hash).unwrap(),
+        ];
+        let get_listing = |contract_hash: &CLValue| {
+            let mut args = RuntimeArgs::new();
+            args.insert_cl_value("contract_hash", contract_hash.clone());
+            args.insert("token_id", U256::from(1)).unwrap();
+
+            build_query_request(account_two, marketplace_hash, "get_listing", args)
+        };
+
+        for contract_hash in &package_args {
+            builder.exec(get_listing(contract_hash)).expect_failure();
+            assert_user_error(&builder, ERROR_UNKNOWN_COLLECTION_PACKAGE);
+        }
+
+        let item = Marketplace::at_contract(marketplace_hash)
+            .set_collection_package(nft_package_hash, nft_hash);
+        builder
+            .exec(request_builder(account_two, item.clone()).build())
+            .expect_failure();
+        builder
+            .exec(request_builder(account_one, item).build())
+            .expect_success()
+            .commit();
+
+        for contract_hash in &package_args {
+            builder
+                .exec(get_listing(contract_hash))
+                .expect_success()
+                .commit();
+        }
+
+        let req = build_query_request(
+            account_one,
+            marketplace_hash,
+            "create_listing",
+            runtime_args! {
+                "contract_hash" => nft_package_hash,
+                "token_id" => U256::from(2),
+                "price" => U512::from(200u64),
+                "duration_minutes" => 0u64,
+            },
+        );
+        builder.exec(req).expect_success().commit();
+
+        let listing: Option<ListingData> = get_marketplace_dictionary_item(
+            &builder,
+            marketplace_hash,
+            "listings",
+            &listing_key(nft_hash, U256::from(2)),
+        );
+        assert_eq!(
+            listing.map(|listing| listing.price),
+            Some(U512::from(200u64))
+        );
+
+        let req = build_query_request(
+            account_one,
+            marketplace_hash,
+            "get_offer",
+            runtime_args! {
+                "contract_hash" => nft_hash.to_formatted_string(),
+                "token_id" => U256::from(2),
+                "offerer" => "account-hash-not-a-hash",
+            },
+        );
+        builder.exec(req).expect_failure();
+        assert_user_error(&builder, ERROR_INVALID_ACCOUNT);
+    }
+
+    #[test]
+    fn entry_points_read_their_declared_params() {
+        let (mut builder, marketplace_hash, nft_hash, _package_hash) = setup();
+
+        let account_one: AccountHash = *DEFAULT_ACCOUNT_ADDR;
+        let main_purse = builder.get_expected_account(account_one).main_purse();
+
+        let entry_points = builder
+            .get_contract(marketplace_hash)
+            .expect("should have marketplace contract")
+            .entry_points()
+            .clone()
+            .take_entry_points();
+        assert!(!entry_points.is_empty());
+
+        // Declared with `Param::optional`: callers may leave these out, so only a wrongly typed
+        // value has to be rejected
+        const OPTIONAL_PARAMS: &[(&str, &str)] = &[
+            ("create_listing", "custodial"),
+            ("start_auction", "custodial"),
+            ("buy_listing", "referrer"),
+            ("buy_listings_batch", "referrer"),
+            ("accept_offer", "referrer"),
+            ("place_bid", "referrer"),
+            ("propose_swap", "buy_purse"),
+            ("withdraw", "target_purse"),
+        ];
+
+        let call = |builder: &mut InMemoryWasmTestBuilder,
+                    entry_point: &str,
+                    args: &[(String, CLValue)]|
+         -> Option<EngineError> {
+            let mut runtime_args = RuntimeArgs::new();
+            for (name, value) in args {
+                runtime_args.insert_cl_value(name.clone(), value.clone());
+            }
+
+            let req = build_query_request(account_one, marketplace_hash, entry_point, runtime_args);
+            builder.exec(req);
+            builder.get_error()
+        };
+
+        // Called with exactly the declared params, an entry point may still fail on business rules,
+        // but never because an argument it reads is missing or of another type, or is read as
+        // required when it is optional or the other way around. Leaving a required param out or
+        // passing a value of another type has to revert with an argument error, unless the entry
+        // point already gave up on the business rule before reading it. Leaving an optional param
+        // out never does.
+        for entry_point in entry_points {
+            let declared_args: Vec<(String, CLValue)> = entry_point
+                .args()
+                .iter()
+                .map(|param| {
+                    let value = sample_arg(
+                        param.name(),
+                        param.cl_type(),
+                        nft_hash,
+                        account_one,
+                        main_purse,
+                    );
+                    (param.name().to_string(), value)
+                })
+                .collect();
+
+            let declared_error = call(&mut builder, entry_point.name(), &declared_args);
+
+            if let Some(error) = &declared_error {
+                assert!(
+                    !is_argument_error(error),
+                    "{} does not match its declared params: {:?}",
+                    entry_point.name(),
+                    error
+                );
+            }
+
+            for (index, (name, _value)) in declared_args.iter().enumerate() {
+                // No declared param is a list of `u64`s, and its bytes do not parse as any of them
+                let mut wrongly_typed = declared_args.clone();
+                wrongly_typed[index].1 = CLValue::from_t(vec![u64::MAX; 5]).unwrap();
+
+                let mut missing = declared_args.clone();
+                missing.remove(index);
+
+                let mut variants = vec![("of another type", wrongly_typed)];
+                if OPTIONAL_PARAMS.contains(&(entry_point.name(), name.as_str())) {
+                    let error = call(&mut builder, entry_point.name(), &missing);
+                    assert!(
+                        !error.as_ref().is_some_and(is_argument_error),
+                        "{} requires its optional param {}: {:?}",
+                        entry_point.name(),
+                        name,
+                        error
+                    );
+                } else {
+                    variants.push(("missing", missing));
+                }
+
+                for (variant, args) in variants {
+                    let error = call(&mut builder, entry_point.name(), &args);
+                    let rejected = error.as_ref().is_some_and(|error| {
+                        is_argument_error(error)
+                            || Some(format!("{:?}", error))
+                                == declared_error.as_ref().map(|error| format!("{:?}", error))
+                    });
+
+                    assert!(
+                        rejected,
+                        "{} accepted {} {}: {:?}",
+                        entry_point.name(),
+                        name,
+                        variant,
+                        error
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn contract_caller_lists_and_withdraws() {
+        const ERROR_TARGET_PURSE_REQUIRED: u16 = 40;
+
+        let (mut builder, marketplace_hash, nft_hash, package_hash) = setup();
+
+        let account_one: AccountHash = *DEFAULT_ACCOUNT_ADDR;
+        let account_two: AccountHash = DEFAULT_ACCOUNTS.get(1).unwrap().account_hash();
+        let price = U512::from(1000u64);
+
+        let request = ExecuteRequestBuilder::standard(
+            account_one,
+            VAULT_WASM,
+            runtime_args! {
+                "marketplace_hash" => marketplace_hash.to_formatted_string(),
+                "marketplace_package_hash" => Key::Hash(package_hash.value()),
+            },
+        )
+        .build();
+        builder.exec(request).expect_success().commit();
+
+        let vault_hash = get_contract_hash_from_account(&mut builder, "vault_contract_hash");
+        let vault_key = *builder
+            .get_expected_account(account_one)
+            .named_keys()
+            .get("vault_package_hash")
+            .expect("should have vault package hash");
+
+        // The vault owns the token under its package hash and lists it itself
+        mint_cep_47(&mut builder, nft_hash, vault_key, vec![U256::from(1)]);
+
+        let request = ExecuteRequestBuilder::contract_call_by_hash(
+            account_one,
+            vault_hash,
+            "list_token",
+            runtime_args! {
+                "contract_hash" => nft_hash.to_formatted_string(),
+                "token_id" => U256::from(1),
+                "price" => price,
+            },
+        )
+        .build();
+        builder.exec(request).expect_success().commit();
+
+        let listing: ListingData = get_marketplace_dictionary_item(
+            &builder,
+            marketplace_hash,
+            "listings",
+            &listing_key(nft_hash, U256::from(1)),
+        )
+        .expect("should have listing");
+        assert_eq!(listing.seller, vault_key);
+
+        let req = create_buy_nft_request(account_two, U256::from(1), marketplace_hash, nft_hash);
+        builder.exec(req).expect_success().commit();
+
+        assert_eq!(
+            get_cep_47_owner(&builder, nft_hash, U256::from(1)),
+            Some(Key::Account(account_two))
+        );
+        assert_eq!(
+            get_pending_balance(&builder, marketplace_hash, vault_key),
+            price
+        );
+
+        // A contract has no main purse, so it has to name one
+        let withdraw = |to_purse: bool| {
+            ExecuteRequestBuilder::contract_call_by_hash(
+                account_one,
+                vault_hash,
+                "withdraw",
+                runtime_args! { "to_purse" => to_purse },
+            )
+            .build()
+        };
+
+        builder.exec(withdraw(false)).expect_failure();
+        assert_user_error(&builder, ERROR_TARGET_PURSE_REQUIRED);
+        builder.commit();
+
+        builder.exec(withdraw(true)).expect_success().commit();
+
+        let vault_purse = builder
+            .get_contract(vault_hash)
+            .expect("should have vault contract")
+            .named_keys()
+            .get("vault_purse")
+            .expect("should have vault purse")
+            .into_uref()
+            .expect("vault purse should be a uref");
+        assert_eq!(builder.get_purse_balance(vault_purse), price);
+        assert_eq!(
+            get_pending_balance(&builder, marketplace_hash, vault_key),
+            U512::zero()
+        );
+    }
+
+    #[test]
+    fn upgrade_migrates_legacy_auctions_and_offers() {
+        const AUCTION_END_BLOCKTIME: u64 = 21 * 60 * 1000;
+
+        let mut builder = InMemoryWasmTestBuilder::default();
+        builder
+            .run_genesis(&PRODUCTION_RUN_GENESIS_REQUEST)
+            .commit();
+
+        let account_one: AccountHash = *DEFAULT_ACCOUNT_ADDR;
+        let account_two: AccountHash = DEFAULT_ACCOUNTS.get(1).unwrap().account_hash();
+
+        // The first release stored auction parties and offerers as an `AccountHash`
+        let legacy_hash = deploy_marketplace(&mut builder, LEGACY_MARKETPLACE_WASM);
+        let nft_hash = deploy_cep_47(&mut builder);
+        let package_hash = builder
+            .get_expected_account(account_one)
+            .named_keys()
+            .get("mystra_marketplace_package_name777")
+            .and_then(|key| key.into_hash())
+            .map(ContractHash::new)
+            .expect("should have marketplace package");
+
+        mint_and_approve(
+            &mut builder,
+            nft_hash,
+            package_hash,
+            account_one,
+            vec![U256::from(1), U256::from(2), U256::from(3)],
+        );
+
+        for token_id in [U256::from(1), U256::from(3)] {
+            let req =
+                build_legacy_start_auction_request(account_one, legacy_hash, nft_hash, token_id);
+            builder.exec(req).expect_success().commit();
+        }
+
+        let req = create_make_offer_request(account_two, U256::from(2), legacy_hash, nft_hash);
+        builder.exec(req).expect_success().commit();
+
+        let legacy_auction =
+            |builder: &InMemoryWasmTestBuilder, hash: ContractHash, token_id: u64| {
+                get_marketplace_dictionary_item::<LegacyAuctionData>(
+                    builder,
+                    hash,
+                    "auctions",
+                    &listing_key(nft_hash, U256::from(token_id)),
+                )
+            };
+        let legacy_key = legacy_offer_key(nft_hash, U256::from(2), account_two);
+
+        assert_eq!(
+            legacy_auction(&builder, legacy_hash, 1).map(|auction| auction.seller),
+            Some(account_one)
+        );
+        assert!(get_marketplace_dictionary_item::<OfferData>(
+            &builder,
+            legacy_hash,
+            "offers",
+            &legacy_key
+        )
+        .is_some());
+
+        // Upgrading migrates the auctions it is given, the rest stays in the legacy layout
+        let request = ExecuteRequestBuilder::standard(
+            account_one,
+            MARKETPLACE_WASM,
+            runtime_args! {
+                "auctions" => vec![(Key::Hash(nft_hash.value()), U256::from(1))],
+            },
+        )
+        .build();
+        builder.exec(request).expect_success().commit();
+
+        let marketplace_hash = get_contract_hash_from_account(&mut builder, CONTRACT_KEY);
+        assert_ne!(marketplace_hash, legacy_hash);
+
+        // The first release is disabled, so it cannot write to the storage in the old layout
+        let req =
+            build_legacy_start_auction_request(account_one, legacy_hash, nft_hash, U256::from(2));
+        builder.exec(req).expect_failure();
+
+        let auction: AuctionData = get_marketplace_dictionary_item(
+            &builder,
+            marketplace_hash,
+            "auctions_v2",
+            &listing_key(nft_hash, U256::from(1)),
+        )
+        .expect("should have migrated the auction");
+        assert_eq!(auction.seller, Key::Account(account_one));
+        assert_eq!(auction.current_winner, Key::Account(account_one));
+        assert_eq!(auction.starting_price, U512::from(1));
+        assert_eq!(legacy_auction(&builder, marketplace_hash, 1), None);
+        assert!(legacy_auction(&builder, marketplace_hash, 3).is_some());
+
+        // Records left out are still read
+        let req = build_query_request(
+            account_one,
+            marketplace_hash,
+            "get_offer",
+            runtime_args! {
+                "contract_hash" => Key::Hash(nft_hash.value()),
+                "token_id" => U256::from(2),
+                "offerer" => Key::Account(account_two),
+            },
+        );
+        builder.exec(req).expect_success().commit();
+
+        let req =
+            build_accept_offer_request(account_one, marketplace_hash, nft_hash, U256::from(2));
+        builder.exec(req).expect_success().commit();
+
+        assert_eq!(
+            get_cep_47_owner(&builder, nft_hash, U256::from(2)),
+            Some(Key::Account(account_two))
+        );
+        for key in [
+            legacy_key,
+            offer_key(nft_hash, U256::from(2), &Key::Account(account_two)),
+        ] {
+            assert!(get_marketplace_dictionary_item::<OfferData>(
+                &builder,
+                marketplace_hash,
+                "offers",
+                &key
+            )
+            .is_none());
+        }
+
+        // A bid moves the auction left out into the current layout while it still runs
+        let req = create_place_bid_request(
+            account_two,
+            U256::from(3),
+            U512::from(60u64),
+            marketplace_hash,
+            nft_hash,
+            5u64,
+        );
+        builder.exec(req).expect_success().commit();
+        assert_eq!(legacy_auction(&builder, marketplace_hash, 3), None);
+
+        let req = build_end_auction_request(
+            account_one,
+            marketplace_hash,
+            nft_hash,
+            U256::from(3),
+            AUCTION_END_BLOCKTIME,
+        );
+        builder.exec(req).expect_success().commit();
+
+        assert_eq!(
+            get_cep_47_owner(&builder, nft_hash, U256::from(3)),
+            Some(Key::Account(account_two))
+        );
+        assert!(get_marketplace_dictionary_item::<AuctionData>(
+            &builder,
+            marketplace_hash,
+            "auctions_v2",
+            &listing_key(nft_hash, U256::from(3)),
+        )
+        .is_none());
+    }
+
+    #[test]
+    fn upgrade_drops_legacy_auctions_that_ended() {
+        use marketplace_actions::request_builder;
+        use marketplace_client::Marketplace;
+
+        const AUCTION_END_BLOCKTIME: u64 = 21 * 60 * 1000;
+        const ERROR_AUCTION_DOESNT_EXIST: u16 = 13;
+
+        let mut builder = InMemoryWasmTestBuilder::default();
+        builder
+            .run_genesis(&PRODUCTION_RUN_GENESIS_REQUEST)
+            .commit();
+
+        let account_one: AccountHash = *DEFAULT_ACCOUNT_ADDR;
+        let account_two: AccountHash = DEFAULT_ACCOUNTS.get(1).unwrap().account_hash();
+
+        let legacy_hash = deploy_marketplace(&mut builder, LEGACY_MARKETPLACE_WASM);
+        let nft_hash = deploy_cep_47(&mut builder);
+        let package_hash = builder
+            .get_expected_account(account_one)
+            .named_keys()
+            .get("mystra_marketplace_package_name777")
+            .and_then(|key| key.into_hash())
+            .map(ContractHash::new)
+            .expect("should have marketplace package");
+
+        let token_ids = vec![U256::from(1), U256::from(2)];
+        mint_and_approve(
+            &mut builder,
+            nft_hash,
+            package_hash,
+            account_one,
+            token_ids.clone(),
+        );
+
+        // The first release settles the auctions but leaves them in its `auctions` dictionary
+        for token_id in token_ids.iter().copied() {
+            let req =
+                build_legacy_start_auction_request(account_one, legacy_hash, nft_hash, token_id);
+            builder.exec(req).expect_success().commit();
+
+            let req = create_place_bid_request(
+                account_two,
+                token_id,
+                U512::from(60u64),
+                legacy_hash,
+                nft_hash,
+                5u64,
+            );
+            builder.exec(req).expect_success().commit();
+
+            let req = build_legacy_end_auction_request(
+                account_one,
+                legacy_hash,
+                nft_hash,
+                token_id,
+                AUCTION_END_BLOCKTIME,
+            );
+            builder.exec(req).expect_success().commit();
+
+            assert_eq!(
+                get_cep_47_owner(&builder, nft_hash, token_id),
+                Some(Key::Account(account_two))
+            );
+            assert!(get_marketplace_dictionary_item::<LegacyAuctionData>(
+                &builder,
+                legacy_hash,
+                "auctions",
+                &listing_key(nft_hash, token_id),
+            )
+            .is_some());
+        }
+
+        // Migrating an auction that has ended drops it
+        let request = ExecuteRequestBuilder::standard(
+            account_one,
+            MARKETPLACE_WASM,
+            runtime_args! {
+                "auctions" => vec![(Key::Hash(nft_hash.value()), U256::from(1))],
+            },
+        )
+        .with_block_time(AUCTION_END_BLOCKTIME)
+        .build();
+        builder.exec(request).expect_success().commit();
+
+        let marketplace_hash = get_contract_hash_from_account(&mut builder, CONTRACT_KEY);
+        let key = listing_key(nft_hash, U256::from(1));
+        assert!(get_marketplace_dictionary_item::<LegacyAuctionData>(
+            &builder,
+            marketplace_hash,
+            "auctions",
+            &key
+        )
+        .is_none());
+        assert!(get_marketplace_dictionary_item::<AuctionData>(
+            &builder,
+            marketplace_hash,
+            "auctions_v2",
+            &key
+        )
+        .is_none());
+
+        // Neither the migrated auction nor the one left out can be settled a second time
+        for token_id in token_ids.iter().copied() {
+            let req = build_end_auction_request(
+                account_one,
+                marketplace_hash,
+                nft_hash,
+                token_id,
+                AUCTION_END_BLOCKTIME,
+            );
+            builder.exec(req).expect_failure();
+            assert_user_error(&builder, ERROR_AUCTION_DOESNT_EXIST);
+            builder.commit();
+        }
+        assert_eq!(
+            get_pending_balance(&builder, marketplace_hash, Key::Account(account_one)),
+            U512::zero()
+        );
+
+        // The winner can list the tokens it won
+        approve_cep_47(
+            &mut builder,
+            account_two,
+            nft_hash,
+            package_hash.into(),
+            token_ids.clone(),
+        );
+        for token_id in token_ids {
+            let item = Marketplace::at_contract(marketplace_hash).list_for_rent(
+                nft_hash,
+                token_id,
+                U512::from(10u64),
+                7,
+            );
+            let req = request_builder(account_two, item)
+                .with_block_time(AUCTION_END_BLOCKTIME)
+                .build();
+            builder.exec(req).expect_success().commit();
+        }
+    }
+
+    #[test]
+    fn client_deploy_items_match_the_engine_encoding() {
+        use casper_types::bytesrepr::ToBytes;
+        use marketplace_actions::engine_deploy_item;
+        use marketplace_client::{sessions, types::decode_listing, Marketplace};
+
+        let caller = *DEFAULT_ACCOUNT_ADDR;
+        let marketplace_hash = ContractHash::new([1u8; 32]);
+        let nft_hash = ContractHash::new([2u8; 32]);
+        let by_contract = Marketplace::at_contract(marketplace_hash);
+        let by_package = Marketplace::at_package(ContractPackageHash::new([3u8; 32]));
+
+        let items = vec![
+            by_contract.create_listing(nft_hash, U256::one(), U512::from(10), 60, Some(true)),
+            by_contract.accept_offer(nft_hash, U256::one(), Key::Account(caller), None),
+            by_package.cancel_offer(nft_hash, U256::one()),
+            by_package.withdraw(None),
+            sessions::buy_listing(
+                vec![0, 97, 115, 109],
+                marketplace_hash,
+                nft_hash,
+                U256::one(),
+                U512::from(10),
+                None,
+            ),
+        ];
+
+        for item in items {
+            let engine_item = engine_deploy_item(caller, item.clone());
+            assert_eq!(
+                engine_item.session.to_bytes().unwrap(),
+                item.to_bytes().unwrap()
+            );
+        }
+
+        let cleared = CLValue::from_t(None::<ListingData>).unwrap();
+        assert_eq!(decode_listing(cleared).unwrap(), None);
+
+        let listing = ListingData {
+            seller: Key::Account(caller),
+            price: U512::from(10),
+            expiration_time: None,
+        };
+        let stored = CLValue::from_t(listing.clone()).unwrap();
+        assert_eq!(decode_listing(stored).unwrap(), Some(listing));
+    }
+
+    #[test]
+    fn offline_deploy_is_hashed_and_signed() {
+        use blake2::{
+            digest::{Update, VariableOutput},
+            VarBlake2b,
+        };
+        use casper_types::{bytesrepr::ToBytes, crypto, PublicKey, SecretKey, TimeDiff, Timestamp};
+        use marketplace_client::{Deploy, DeployItem, DeployParams, Marketplace};
+
+        fn blake2b(data: &[u8]) -> Vec<u8> {
+            let mut hasher = VarBlake2b::new(32).unwrap();
+            hasher.update(data);
+            hasher.finalize_boxed().to_vec()
+        }
+
+        let secret_key = SecretKey::ed25519_from_bytes([7u8; 32]).unwrap();
+        let public_key = PublicKey::from(&secret_key);
+        let payment = DeployItem::standard_payment(U512::from(5_000_000_000u64));
+        let session = Marketplace::at_contract(ContractHash::new([1u8; 32]))
+            .end_auction(ContractHash::new([2u8; 32]), U256::one());
+        let params = DeployParams {
+            chain_name: "casper-test".to_string(),
+            timestamp: Timestamp::from(1_700_000_000_000),
+            ttl: TimeDiff::from_millis(30 * 60 * 1000),
+            gas_price: 1,
+        };
+
+        let deploy = Deploy::new(params, payment.clone(), session.clone(), &secret_key).unwrap();
+
+        let mut body = payment.to_bytes().unwrap();
+        body.extend(session.to_bytes().unwrap());
+        assert_eq!(deploy.header.body_hash.to_vec(), blake2b(&body));
+        assert_eq!(
+            deploy.hash.to_vec(),
+            blake2b(&deploy.header.to_bytes().unwrap())
+        );
+        assert_eq!(deploy.approvals.len(), 1);
+        assert_eq!(deploy.approvals[0].signer, public_key);
+        assert!(crypto::verify(deploy.hash, &deploy.approvals[0].signature, &public_key).is_ok());
+
+        let deploy_json = deploy.to_json().unwrap();
+        assert_eq!(deploy_json["hash"], hex::encode(deploy.hash));
+        assert_eq!(deploy_json["header"]["chain_name"], "casper-test");
+        assert_eq!(
+            deploy_json["session"]["StoredContractByHash"]["entry_point"],
+            "end_auction"
+        );
+    }
+
+    #[test]
+    fn marketplace_state_is_dumped_and_decoded() {
+        use casper_types::{
+            bytesrepr::{Bytes, ToBytes},
+            StoredValue,
+        };
+        use marketplace_client::storage::{decode_stored_record, Record, RecordKind};
+
+        let (mut builder, marketplace_hash, nft_hash, package_hash) = setup();
+
+        let account_one: AccountHash = *DEFAULT_ACCOUNT_ADDR;
+        let account_two: AccountHash = DEFAULT_ACCOUNTS.get(1).unwrap().account_hash();
+
+        mint_and_approve(
+            &mut builder,
+            nft_hash,
+            package_hash,
+            account_one,
+            vec![U256::from(1), U256::from(2)],
+        );
+
+        let req = build_create_listing_request(
+            account_one,
+            marketplace_hash,
+            nft_hash,
+            U256::from(1),
+            U512::from(100u64),
+            0u64,
+        );
+        builder.exec(req).expect_success().commit();
+
+        let req = create_make_offer_request(account_two, U256::from(2), marketplace_hash, nft_hash);
+        builder.exec(req).expect_success().commit();
+
+        let state = dump_marketplace_state(
+            &builder,
+            marketplace_hash,
+            &[(nft_hash, U256::from(1)), (nft_hash, U256::from(2))],
+            &[Key::Account(account_one), Key::Account(account_two)],
+        );
+        println!("{}", state);
+
+        let listing = &state["listings"][format!("{}:1", nft_hash)];
+        assert_eq!(listing["price"], "100");
+        assert_eq!(
+            listing["seller"],
+            Key::Account(account_one).to_formatted_string()
+        );
+        assert!(state["listings"].get(format!("{}:2", nft_hash)).is_none());
+
+        let offer = format!(
+            "{}:2:{}",
+            nft_hash,
+            Key::Account(account_two).to_formatted_string()
+        );
+        assert_eq!(state["offers"][offer]["price"], "100000000");
+        assert_eq!(state["offers"].as_object().unwrap().len(), 1);
+        assert!(state["auctions"].as_object().unwrap().is_empty());
+
+        // Raw global state: the value itself, and a dictionary entry wrapped with its seed and
+        // item key
+        let listing = ListingData {
+            seller: Key::Account(account_one),
+            price: U512::from(100u64),
+            expiration_time: None,
+        };
+        let value = CLValue::from_t(listing.clone()).unwrap();
+        let mut wrapped = value.to_bytes().unwrap();
+        wrapped.extend(Bytes::from(vec![1u8; 32]).to_bytes().unwrap());
+        wrapped.extend(listing_key(nft_hash, U256::from(1)).to_bytes().unwrap());
+        let wrapped = CLValue::from_components(CLType::Any, wrapped);
+
+        for value in [value, wrapped] {
+            let bytes = StoredValue::CLValue(value).to_bytes().unwrap();
+            assert_eq!(
+                decode_stored_record(RecordKind::Listing, &bytes).unwrap(),
+                Some(Record::Listing(listing.clone()))
+            );
+        }
+
+        let cleared = StoredValue::CLValue(CLValue::from_t(None::<ListingData>).unwrap());
+        assert_eq!(
+            decode_stored_record(RecordKind::Listing, &cleared.to_bytes().unwrap()).unwrap(),
+            None
+        );
+    }
 }
 
 fn main() {
@@ -20,7 +20,7 @@ pub fn mint_cep_47(
         "mint",
         runtime_args! {
             "recipient" => recipient,
-            "token_metas" => vec![meta],
+            "token_metas" => vec![meta; ids.len()],
             "token_ids" => ids,
         },
     )
@@ -51,21 +51,64 @@ pub fn deploy_cep_47(builder: &mut InMemoryWasmTestBuilder) -> ContractHash {
 
 
 pub fn approve_cep_47(
-        builder: &mut InMemoryWasmTestBuilder,
-        caller: AccountHash,
-        cep47_hash: ContractHash,
-        recipient: Key,
-        ids: Vec<U256>,
-    ) {
-        let request = ExecuteRequestBuilder::contract_call_by_hash(
-            caller,
-            cep47_hash,
-            "approve",
-            runtime_args! {
-                "spender" => recipient,
-                "token_ids" => ids,
-            },
-        )
-        .build();
-        builder.exec(request).expect_success().commit();
-    }
\ No newline at end of file
+    builder: &mut InMemoryWasmTestBuilder,
+    caller: AccountHash,
+    cep47_hash: ContractHash,
+    recipient: Key,
+    ids: Vec<U256>,
+) {
+    let request = ExecuteRequestBuilder::contract_call_by_hash(
+        caller,
+        cep47_hash,
+        "approve",
+        runtime_args! {
+            "spender" => recipient,
+            "token_ids" => ids,
+        },
+    )
+    .build();
+    builder.exec(request).expect_success().commit();
+}
+
+pub fn transfer_cep_47(
+    builder: &mut InMemoryWasmTestBuilder,
+    caller: AccountHash,
+    cep47_hash: ContractHash,
+    recipient: Key,
+    ids: Vec<U256>,
+) {
+    let request = ExecuteRequestBuilder::contract_call_by_hash(
+        caller,
+        cep47_hash,
+        "transfer",
+        runtime_args! {
+            "recipient" => recipient,
+            "token_ids" => ids,
+        },
+    )
+    .build();
+    builder.exec(request).expect_success().commit();
+}
+
+/// Reads the current owner of `token_id` straight from the CEP-47 `owners` dictionary.
+pub fn get_cep_47_owner(
+    builder: &InMemoryWasmTestBuilder,
+    cep47_hash: ContractHash,
+    token_id: U256,
+) -> Option<Key> {
+    let owners_uref = builder
+        .get_contract(cep47_hash)
+        .expect("should have cep47 contract")
+        .named_keys()
+        .get("owners")
+        .expect("should have owners dictionary")
+        .into_uref()
+        .expect("owners should be a uref");
+
+    builder
+        .query_dictionary_item(None, owners_uref, &token_id.to_string())
+        .ok()
+        .and_then(|value| value.as_cl_value().cloned())
+        .and_then(|value| value.into_t::<Option<Key>>().ok())
+        .flatten()
+}
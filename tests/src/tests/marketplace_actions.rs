@@ -1,10 +1,71 @@
+use std::{
+    collections::BTreeMap,
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+use blake2::{
+    digest::{Update, VariableOutput},
+    VarBlake2b,
+};
 use casper_engine_test_support::{
-    ExecuteRequestBuilder, InMemoryWasmTestBuilder, DEFAULT_ACCOUNTS, DEFAULT_ACCOUNT_ADDR,
+    utils::read_wasm_file_bytes, DeployItemBuilder, ExecuteRequestBuilder, DEFAULT_ACCOUNTS,
+    DEFAULT_PAYMENT,
 };
-use casper_execution_engine::core::engine_state::ExecuteRequest;
+use casper_execution_engine::core::engine_state::{DeployItem as EngineDeployItem, ExecuteRequest};
 use casper_types::{account::AccountHash, runtime_args, ContractHash, Key, RuntimeArgs, U256, U512};
+use marketplace_client::{sessions, DeployItem, Marketplace};
+
+use super::{
+    BATCH_PAYMENT_WASM, BID_WASM, DROP_WASM, OFFER_WASM, PAYMENT_WASM, RENT_WASM, STATS_WASM,
+    SWAP_WASM,
+};
+
+static DEPLOY_COUNTER: AtomicU64 = AtomicU64::new(0);
 
-use super::{BID_WASM, ENTRY_POINT_ACCEPT_OFFER, ENTRY_POINT_CANCEL_OFFER, ENTRY_POINT_CREATE_LISTING, OFFER_WASM, PAYMENT_WASM};
+fn next_deploy_hash() -> [u8; 32] {
+    let counter = DEPLOY_COUNTER.fetch_add(1, Ordering::SeqCst);
+    let mut deploy_hash = [0u8; 32];
+    let mut hasher = VarBlake2b::new(32).unwrap();
+    hasher.update(counter.to_le_bytes());
+    hasher.finalize_variable(|hash| deploy_hash.copy_from_slice(hash));
+    deploy_hash
+}
+
+/// Turns a client deploy item into the engine's deploy, paid with the standard payment.
+pub fn engine_deploy_item(caller: AccountHash, item: DeployItem) -> EngineDeployItem {
+    let builder = DeployItemBuilder::new()
+        .with_address(caller)
+        .with_empty_payment_bytes(runtime_args! { "amount" => *DEFAULT_PAYMENT })
+        .with_authorization_keys(&[caller])
+        .with_deploy_hash(next_deploy_hash());
+
+    match item {
+        DeployItem::ModuleBytes { module_bytes, args } => {
+            builder.with_session_bytes(module_bytes.into(), args)
+        }
+        DeployItem::StoredContractByHash {
+            hash,
+            entry_point,
+            args,
+        } => builder.with_stored_session_hash(hash, &entry_point, args),
+        DeployItem::StoredVersionedContractByHash {
+            hash,
+            version,
+            entry_point,
+            args,
+        } => builder.with_stored_versioned_contract_by_hash(
+            hash.value(),
+            version,
+            &entry_point,
+            args,
+        ),
+    }
+    .build()
+}
+
+pub fn request_builder(caller: AccountHash, item: DeployItem) -> ExecuteRequestBuilder {
+    ExecuteRequestBuilder::from_deploy_item(engine_deploy_item(caller, item))
+}
 
 pub fn build_create_listing_request(
     caller: AccountHash,
@@ -14,20 +75,33 @@ pub fn build_create_listing_request(
     price: U512,
     expiration_time: u64
 ) -> ExecuteRequest {
+    let item = Marketplace::at_contract(marketplace_hash).create_listing(
+        cep47_hash,
+        token_id,
+        price,
+        expiration_time,
+        None,
+    );
 
-    ExecuteRequestBuilder::contract_call_by_hash(
-        caller,
-        marketplace_hash,
-        ENTRY_POINT_CREATE_LISTING,
-        runtime_args! {
-            "token_id" => token_id,
-            "contract_hash" => cep47_hash.to_formatted_string(),
-            "price" => price,
-            "duration_minutes" => expiration_time
+    request_builder(caller, item).build()
+}
 
-        },
-    )
-    .build()
+pub fn build_update_listing_request(
+    caller: AccountHash,
+    marketplace_hash: ContractHash,
+    cep47_hash: ContractHash,
+    token_id: U256,
+    price: U512,
+    duration_minutes: u64,
+) -> ExecuteRequest {
+    let item = Marketplace::at_contract(marketplace_hash).update_listing(
+        cep47_hash,
+        token_id,
+        price,
+        duration_minutes,
+    );
+
+    request_builder(caller, item).build()
 }
 
 pub fn create_buy_nft_request(
@@ -36,17 +110,71 @@ pub fn create_buy_nft_request(
     marketplace_hash: ContractHash,
     contract_hash: ContractHash,
 ) -> ExecuteRequest {
-    ExecuteRequestBuilder::standard(
+    create_referred_buy_nft_request(caller, token_id, marketplace_hash, contract_hash, None)
+}
+
+pub fn create_referred_buy_nft_request(
+    caller: AccountHash,
+    token_id: U256,
+    marketplace_hash: ContractHash,
+    contract_hash: ContractHash,
+    referrer: Option<Key>,
+) -> ExecuteRequest {
+    let item = sessions::buy_listing(
+        read_wasm_file_bytes(PAYMENT_WASM),
+        marketplace_hash,
+        contract_hash,
+        token_id,
+        U512::from(1000000000000u64),
+        referrer,
+    );
+
+    request_builder(caller, item).with_block_time(40).build()
+}
+
+pub fn create_batch_buy_request(
+    caller: AccountHash,
+    marketplace_hash: ContractHash,
+    contract_hash: ContractHash,
+    token_ids: Vec<U256>,
+    max_spend: U512,
+    skip_unavailable: bool,
+) -> ExecuteRequest {
+    create_referred_batch_buy_request(
         caller,
-        PAYMENT_WASM,
-        runtime_args! {
-            "token_id" => token_id,
-            "buy_contract_hash" => contract_hash.to_formatted_string(),
-            "marketplace_hash" => marketplace_hash.to_formatted_string(),
-            "amount" => U512::from(1000000000000u64)
-        },
+        marketplace_hash,
+        contract_hash,
+        token_ids,
+        max_spend,
+        skip_unavailable,
+        None,
     )
-    .with_block_time(40).build()
+}
+
+pub fn create_referred_batch_buy_request(
+    caller: AccountHash,
+    marketplace_hash: ContractHash,
+    contract_hash: ContractHash,
+    token_ids: Vec<U256>,
+    max_spend: U512,
+    skip_unavailable: bool,
+    referrer: Option<Key>,
+) -> ExecuteRequest {
+    let tokens: Vec<(ContractHash, U256)> = token_ids
+        .into_iter()
+        .map(|token_id| (contract_hash, token_id))
+        .collect();
+    let item = sessions::buy_listings_batch(
+        read_wasm_file_bytes(BATCH_PAYMENT_WASM),
+        marketplace_hash,
+        &tokens,
+        max_spend,
+        skip_unavailable,
+        max_spend,
+        referrer,
+    );
+
+    request_builder(caller, item).with_block_time(40).build()
 }
 
 pub fn create_make_offer_request(
@@ -55,17 +183,15 @@ pub fn create_make_offer_request(
     marketplace_hash: ContractHash,
     contract_hash: ContractHash,
 ) -> ExecuteRequest {
-    ExecuteRequestBuilder::standard(
-        caller,
-        OFFER_WASM,
-        runtime_args! {
-            "token_id" => token_id,
-            "offer_contract_hash" => contract_hash.to_formatted_string(),
-            "marketplace_hash" => marketplace_hash.to_formatted_string(),
-            "amount" => U512::from(100000000u64)
-        },
-    )
-    .build()
+    let item = sessions::make_offer(
+        read_wasm_file_bytes(OFFER_WASM),
+        marketplace_hash,
+        contract_hash,
+        token_id,
+        U512::from(100000000u64),
+    );
+
+    request_builder(caller, item).build()
 }
 
 pub fn build_accept_offer_request(
@@ -74,18 +200,11 @@ pub fn build_accept_offer_request(
     cep47_hash: ContractHash,
     token_id: U256,
 ) -> ExecuteRequest {
+    let offerer = Key::Account(DEFAULT_ACCOUNTS.get(1).unwrap().account_hash());
+    let marketplace = Marketplace::at_contract(marketplace_hash);
+    let item = marketplace.accept_offer(cep47_hash, token_id, offerer, None);
 
-    ExecuteRequestBuilder::contract_call_by_hash(
-        caller,
-        marketplace_hash,
-        ENTRY_POINT_ACCEPT_OFFER,
-        runtime_args! {
-            "token_id" => token_id,
-            "contract_hash" => cep47_hash.to_formatted_string(),
-            "offerer" => (DEFAULT_ACCOUNTS.get(1).unwrap().account_hash()).to_formatted_string()
-        },
-    )
-    .build()
+    request_builder(caller, item).build()
 }
 
 pub fn build_cancel_offer_request(
@@ -94,37 +213,20 @@ pub fn build_cancel_offer_request(
     cep47_hash: ContractHash,
     token_id: U256,
 ) -> ExecuteRequest {
+    let item = Marketplace::at_contract(marketplace_hash).cancel_offer(cep47_hash, token_id);
 
-    ExecuteRequestBuilder::contract_call_by_hash(
-        caller,
-        marketplace_hash,
-        ENTRY_POINT_CANCEL_OFFER,
-        runtime_args! {
-            "token_id" => token_id,
-            "contract_hash" => cep47_hash.to_formatted_string(),
-        },
-    )
-    .build()
+    request_builder(caller, item).build()
 }
 
 pub fn build_cancel_listing_request(
-    
     caller: AccountHash,
     marketplace_hash: ContractHash,
     cep47_hash: ContractHash,
     token_id: U256,
 ) -> ExecuteRequest {
+    let item = Marketplace::at_contract(marketplace_hash).cancel_listing(cep47_hash, token_id);
 
-    ExecuteRequestBuilder::contract_call_by_hash(
-        caller,
-        marketplace_hash,
-        "cancel_listing",
-        runtime_args! {
-            "token_id" => token_id,
-            "contract_hash" => cep47_hash.to_formatted_string(),
-        },
-    )
-    .build()
+    request_builder(caller, item).build()
 }
 
 pub fn build_start_auction_request(
@@ -132,25 +234,58 @@ pub fn build_start_auction_request(
     marketplace_hash: ContractHash,
     cep47_hash: ContractHash,
     token_id: U256,
-
 ) -> ExecuteRequest {
+    let item = Marketplace::at_contract(marketplace_hash).start_auction(
+        cep47_hash,
+        token_id,
+        U512::from(1),
+        20,
+        None,
+    );
 
-    ExecuteRequestBuilder::contract_call_by_hash(
-        caller,
-        marketplace_hash,
+    request_builder(caller, item).build()
+}
+
+/// Starts an auction on the first release, which only takes the collection as a formatted string.
+pub fn build_legacy_start_auction_request(
+    caller: AccountHash,
+    marketplace_hash: ContractHash,
+    cep47_hash: ContractHash,
+    token_id: U256,
+) -> ExecuteRequest {
+    let item = Marketplace::at_contract(marketplace_hash).call(
         "start_auction",
         runtime_args! {
-            "token_id" => token_id,
             "contract_hash" => cep47_hash.to_formatted_string(),
+            "token_id" => token_id,
+            "price" => U512::from(1),
             "duration_minutes" => 20u64,
-            "price" => U512::from(1)
         },
-    )
-    .build()
-}
+    );
 
+    request_builder(caller, item).build()
+}
 
+/// Ends an auction on the first release, which only takes the collection as a formatted string.
+pub fn build_legacy_end_auction_request(
+    caller: AccountHash,
+    marketplace_hash: ContractHash,
+    cep47_hash: ContractHash,
+    token_id: U256,
+    blocktime: u64,
+) -> ExecuteRequest {
+    let item = Marketplace::at_contract(marketplace_hash).call(
+        "end_auction",
+        runtime_args! {
+            "contract_hash" => cep47_hash.to_formatted_string(),
+            "token_id" => token_id,
+        },
+    );
 
+    request_builder(caller, item)
+        .with_block_time(blocktime)
+        .build()
+}
 
 pub fn create_place_bid_request(
     caller: AccountHash,
@@ -160,19 +295,39 @@ pub fn create_place_bid_request(
     contract_hash: ContractHash,
     blocktime: u64
 ) -> ExecuteRequest {
-    ExecuteRequestBuilder::standard(
+    create_referred_place_bid_request(
         caller,
-        BID_WASM,
-        runtime_args! {
-            "token_id" => token_id,
-            "bid_contract_hash" => contract_hash.to_formatted_string(),
-            "marketplace_hash" => marketplace_hash.to_formatted_string(),
-            "amount" => bid
-        },
+        token_id,
+        bid,
+        marketplace_hash,
+        contract_hash,
+        blocktime,
+        None,
     )
-    .with_block_time(blocktime).build()
 }
 
+pub fn create_referred_place_bid_request(
+    caller: AccountHash,
+    token_id: U256,
+    bid: U512,
+    marketplace_hash: ContractHash,
+    contract_hash: ContractHash,
+    blocktime: u64,
+    referrer: Option<Key>,
+) -> ExecuteRequest {
+    let item = sessions::place_bid(
+        read_wasm_file_bytes(BID_WASM),
+        marketplace_hash,
+        contract_hash,
+        token_id,
+        bid,
+        referrer,
+    );
+
+    request_builder(caller, item)
+        .with_block_time(blocktime)
+        .build()
+}
 
 pub fn build_end_auction_request(
     caller: AccountHash,
@@ -180,18 +335,456 @@ pub fn build_end_auction_request(
     cep47_hash: ContractHash,
     token_id: U256,
     blocktime: u64
+) -> ExecuteRequest {
+    let item = Marketplace::at_contract(marketplace_hash).end_auction(cep47_hash, token_id);
 
+    request_builder(caller, item)
+        .with_block_time(blocktime)
+        .build()
+}
+
+pub fn build_remove_expired_listing_request(
+    caller: AccountHash,
+    marketplace_hash: ContractHash,
+    cep47_hash: ContractHash,
+    token_id: U256,
+    blocktime: u64,
 ) -> ExecuteRequest {
+    let item =
+        Marketplace::at_contract(marketplace_hash).remove_expired_listing(cep47_hash, token_id);
 
-    ExecuteRequestBuilder::contract_call_by_hash(
+    request_builder(caller, item)
+        .with_block_time(blocktime)
+        .build()
+}
+
+pub fn build_get_collection_listings_request(
+    caller: AccountHash,
+    marketplace_hash: ContractHash,
+    cep47_hash: ContractHash,
+    offset: u64,
+    limit: u64,
+) -> ExecuteRequest {
+    let item = Marketplace::at_contract(marketplace_hash)
+        .get_collection_listings(cep47_hash, offset, limit);
+
+    request_builder(caller, item).build()
+}
+
+pub fn build_get_seller_listings_request(
+    caller: AccountHash,
+    marketplace_hash: ContractHash,
+    seller: Key,
+    offset: u64,
+    limit: u64,
+) -> ExecuteRequest {
+    let item =
+        Marketplace::at_contract(marketplace_hash).get_seller_listings(seller, offset, limit);
+
+    request_builder(caller, item).build()
+}
+
+/// Calls one of the read-only entry points; the returned value is only checked for success.
+pub fn build_query_request(
+    caller: AccountHash,
+    marketplace_hash: ContractHash,
+    entry_point: &str,
+    args: RuntimeArgs,
+) -> ExecuteRequest {
+    request_builder(
         caller,
-        marketplace_hash,
-        "end_auction",
+        Marketplace::at_contract(marketplace_hash).call(entry_point, args),
+    )
+    .build()
+}
+
+/// Runs `stats-call.wasm`, which stores the collection's stats under the caller's
+/// `collection_stats` named key.
+pub fn build_collection_stats_request(
+    caller: AccountHash,
+    marketplace_hash: ContractHash,
+    cep47_hash: ContractHash,
+    block_time: u64,
+) -> ExecuteRequest {
+    ExecuteRequestBuilder::standard(
+        caller,
+        STATS_WASM,
         runtime_args! {
-            "token_id" => token_id,
+            "marketplace_hash" => marketplace_hash.to_formatted_string(),
             "contract_hash" => cep47_hash.to_formatted_string(),
         },
     )
-    .with_block_time(blocktime).build()
+    .with_block_time(block_time)
+    .build()
+}
+
+pub fn build_withdraw_request(
+    caller: AccountHash,
+    marketplace_hash: ContractHash,
+) -> ExecuteRequest {
+    request_builder(
+        caller,
+        Marketplace::at_contract(marketplace_hash).withdraw(None),
+    )
+    .build()
+}
+
+pub fn build_set_curator_request(
+    caller: AccountHash,
+    marketplace_hash: ContractHash,
+    curator: Key,
+) -> ExecuteRequest {
+    request_builder(
+        caller,
+        Marketplace::at_contract(marketplace_hash).set_curator(curator),
+    )
+    .build()
+}
+
+pub fn build_set_allowlist_mode_request(
+    caller: AccountHash,
+    marketplace_hash: ContractHash,
+    enabled: bool,
+) -> ExecuteRequest {
+    let item = Marketplace::at_contract(marketplace_hash).set_allowlist_mode(enabled);
+
+    request_builder(caller, item).build()
+}
+
+pub fn build_register_collection_request(
+    caller: AccountHash,
+    marketplace_hash: ContractHash,
+    cep47_hash: ContractHash,
+    royalty_recipient: Option<Key>,
+    royalties_percentage: u8,
+) -> ExecuteRequest {
+    let item = Marketplace::at_contract(marketplace_hash).register_collection(
+        cep47_hash,
+        "my nft",
+        true,
+        royalty_recipient,
+        royalties_percentage,
+    );
+
+    request_builder(caller, item).build()
+}
+
+pub fn build_set_royalties_request(
+    caller: AccountHash,
+    marketplace_hash: ContractHash,
+    cep47_hash: ContractHash,
+    royalties_percentage: u8,
+) -> ExecuteRequest {
+    let item =
+        Marketplace::at_contract(marketplace_hash).set_royalties(cep47_hash, royalties_percentage);
+
+    request_builder(caller, item).build()
+}
+
+pub fn build_remove_collection_request(
+    caller: AccountHash,
+    marketplace_hash: ContractHash,
+    cep47_hash: ContractHash,
+) -> ExecuteRequest {
+    let item = Marketplace::at_contract(marketplace_hash).remove_collection(cep47_hash);
+
+    request_builder(caller, item).build()
+}
+
+pub fn build_set_blocked_request(
+    caller: AccountHash,
+    marketplace_hash: ContractHash,
+    account: Key,
+    blocked: bool,
+) -> ExecuteRequest {
+    let item = Marketplace::at_contract(marketplace_hash).set_blocked(account, blocked);
+
+    request_builder(caller, item).build()
+}
+
+/// Same as `build_create_listing_request` and `build_start_auction_request`, but the marketplace
+/// takes the token into custody for the duration of the listing or auction.
+pub fn build_custodial_listing_request(
+    caller: AccountHash,
+    marketplace_hash: ContractHash,
+    cep47_hash: ContractHash,
+    token_id: U256,
+    price: U512,
+) -> ExecuteRequest {
+    let item = Marketplace::at_contract(marketplace_hash).create_listing(
+        cep47_hash,
+        token_id,
+        price,
+        0,
+        Some(true),
+    );
+
+    request_builder(caller, item).build()
+}
+
+pub fn build_custodial_auction_request(
+    caller: AccountHash,
+    marketplace_hash: ContractHash,
+    cep47_hash: ContractHash,
+    token_id: U256,
+) -> ExecuteRequest {
+    let item = Marketplace::at_contract(marketplace_hash).start_auction(
+        cep47_hash,
+        token_id,
+        U512::from(1),
+        20,
+        Some(true),
+    );
+
+    request_builder(caller, item).build()
+}
+
+pub fn build_propose_swap_request(
+    caller: AccountHash,
+    marketplace_hash: ContractHash,
+    counterparty: Key,
+    offered_tokens: Vec<(ContractHash, U256)>,
+    requested_tokens: Vec<(ContractHash, U256)>,
+    amount: U512,
+) -> ExecuteRequest {
+    let item = sessions::propose_swap(
+        read_wasm_file_bytes(SWAP_WASM),
+        marketplace_hash,
+        counterparty,
+        &offered_tokens,
+        &requested_tokens,
+        0,
+        amount,
+    );
+
+    request_builder(caller, item).build()
+}
+
+pub fn build_accept_swap_request(
+    caller: AccountHash,
+    marketplace_hash: ContractHash,
+    swap_id: u64,
+) -> ExecuteRequest {
+    request_builder(
+        caller,
+        Marketplace::at_contract(marketplace_hash).accept_swap(swap_id),
+    )
+    .build()
+}
+
+pub fn build_cancel_swap_request(
+    caller: AccountHash,
+    marketplace_hash: ContractHash,
+    swap_id: u64,
+) -> ExecuteRequest {
+    request_builder(
+        caller,
+        Marketplace::at_contract(marketplace_hash).cancel_swap(swap_id),
+    )
+    .build()
+}
+
+pub fn build_list_for_rent_request(
+    caller: AccountHash,
+    marketplace_hash: ContractHash,
+    cep47_hash: ContractHash,
+    token_id: U256,
+    price_per_day: U512,
+    max_days: u64,
+) -> ExecuteRequest {
+    let item = Marketplace::at_contract(marketplace_hash).list_for_rent(
+        cep47_hash,
+        token_id,
+        price_per_day,
+        max_days,
+    );
+
+    request_builder(caller, item).build()
+}
+
+pub fn build_rent_request(
+    caller: AccountHash,
+    marketplace_hash: ContractHash,
+    cep47_hash: ContractHash,
+    token_id: U256,
+    days: u64,
+    blocktime: u64,
+) -> ExecuteRequest {
+    let item = sessions::rent(
+        read_wasm_file_bytes(RENT_WASM),
+        marketplace_hash,
+        cep47_hash,
+        token_id,
+        days,
+        U512::from(1000000000000u64),
+    );
+
+    request_builder(caller, item)
+        .with_block_time(blocktime)
+        .build()
+}
+
+pub fn build_end_rental_request(
+    caller: AccountHash,
+    marketplace_hash: ContractHash,
+    cep47_hash: ContractHash,
+    token_id: U256,
+    blocktime: u64,
+) -> ExecuteRequest {
+    let item = Marketplace::at_contract(marketplace_hash).end_rental(cep47_hash, token_id);
+
+    request_builder(caller, item)
+        .with_block_time(blocktime)
+        .build()
+}
+
+pub fn build_set_collection_creator_request(
+    caller: AccountHash,
+    marketplace_hash: ContractHash,
+    cep47_hash: ContractHash,
+    creator: Option<Key>,
+) -> ExecuteRequest {
+    let item =
+        Marketplace::at_contract(marketplace_hash).set_collection_creator(cep47_hash, creator);
+
+    request_builder(caller, item).build()
+}
+
+pub fn build_create_lazy_listing_request(
+    caller: AccountHash,
+    marketplace_hash: ContractHash,
+    cep47_hash: ContractHash,
+    token_id: U256,
+    price: U512,
+) -> ExecuteRequest {
+    let mut token_meta = BTreeMap::new();
+    token_meta.insert("rarity".to_string(), "Legendary".to_string());
+
+    let item = Marketplace::at_contract(marketplace_hash)
+        .create_lazy_listing(cep47_hash, token_id, price, 0, token_meta);
+
+    request_builder(caller, item).build()
+}
+
+pub fn build_set_marketplace_fee_request(
+    caller: AccountHash,
+    marketplace_hash: ContractHash,
+    fee_basis_points: u32,
+    fee_recipient: Key,
+) -> ExecuteRequest {
+    let item = Marketplace::at_contract(marketplace_hash)
+        .set_marketplace_fee(fee_basis_points, fee_recipient);
+
+    request_builder(caller, item).build()
+}
+
+pub fn build_create_drop_request(
+    caller: AccountHash,
+    marketplace_hash: ContractHash,
+    cep47_hash: ContractHash,
+    start_token_id: U256,
+    max_supply: u64,
+) -> ExecuteRequest {
+    let mut token_meta = BTreeMap::new();
+    token_meta.insert("rarity".to_string(), "Common".to_string());
+
+    let item = Marketplace::at_contract(marketplace_hash).create_drop(
+        cep47_hash,
+        token_meta,
+        start_token_id,
+        Vec::new(),
+        max_supply,
+    );
+
+    request_builder(caller, item).build()
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn build_add_drop_phase_request(
+    caller: AccountHash,
+    marketplace_hash: ContractHash,
+    cep47_hash: ContractHash,
+    allowlist_only: bool,
+    price: U512,
+    start_time: u64,
+    end_time: u64,
+    wallet_limit: u64,
+) -> ExecuteRequest {
+    let item = Marketplace::at_contract(marketplace_hash).add_drop_phase(
+        cep47_hash,
+        allowlist_only,
+        price,
+        start_time,
+        end_time,
+        wallet_limit,
+    );
+
+    request_builder(caller, item).build()
 }
 
+pub fn build_set_drop_allowlist_request(
+    caller: AccountHash,
+    marketplace_hash: ContractHash,
+    cep47_hash: ContractHash,
+    accounts: Vec<Key>,
+    allowed: bool,
+) -> ExecuteRequest {
+    let item = Marketplace::at_contract(marketplace_hash)
+        .set_drop_allowlist(cep47_hash, accounts, allowed);
+
+    request_builder(caller, item).build()
+}
+
+pub fn build_buy_drop_request(
+    caller: AccountHash,
+    marketplace_hash: ContractHash,
+    cep47_hash: ContractHash,
+    quantity: u64,
+    blocktime: u64,
+) -> ExecuteRequest {
+    let item = sessions::buy_drop(
+        read_wasm_file_bytes(DROP_WASM),
+        marketplace_hash,
+        cep47_hash,
+        quantity,
+        U512::from(1000000000000u64),
+    );
+
+    request_builder(caller, item)
+        .with_block_time(blocktime)
+        .build()
+}
+
+pub fn build_set_referral_share_request(
+    caller: AccountHash,
+    marketplace_hash: ContractHash,
+    referral_share_basis_points: u32,
+) -> ExecuteRequest {
+    let item =
+        Marketplace::at_contract(marketplace_hash).set_referral_share(referral_share_basis_points);
+
+    request_builder(caller, item).build()
+}
+
+pub fn build_set_collection_fee_request(
+    caller: AccountHash,
+    marketplace_hash: ContractHash,
+    cep47_hash: ContractHash,
+    fee_basis_points: Option<u32>,
+) -> ExecuteRequest {
+    let item =
+        Marketplace::at_contract(marketplace_hash).set_collection_fee(cep47_hash, fee_basis_points);
+
+    request_builder(caller, item).build()
+}
+
+pub fn build_set_fee_exempt_request(
+    caller: AccountHash,
+    marketplace_hash: ContractHash,
+    account: Key,
+    exempt: bool,
+) -> ExecuteRequest {
+    let item = Marketplace::at_contract(marketplace_hash).set_fee_exempt(account, exempt);
+
+    request_builder(caller, item).build()
+}
@@ -0,0 +1,272 @@
+use blake2::{
+    digest::{Update, VariableOutput},
+    VarBlake2b,
+};
+use casper_engine_test_support::InMemoryWasmTestBuilder;
+use casper_types::{
+    account::AccountHash,
+    bytesrepr::{FromBytes, ToBytes},
+    CLTyped, CLValue, ContractHash, Key, U256, U512,
+};
+use casper_types_derive::{CLTyped, FromBytes, ToBytes};
+use marketplace_client::storage::{decode_record, party_key, Record, RecordKind};
+use serde_json::{json, Map, Value};
+
+pub use marketplace_client::{
+    storage::{legacy_offer_key, listing_key, offer_key},
+    types::{AuctionData, LegacyAuctionData, ListingData, OfferData},
+};
+
+// Mirrors of the records the marketplace keeps in its dictionaries that the client does not decode.
+
+#[derive(CLTyped, ToBytes, FromBytes, Debug, PartialEq)]
+pub struct PriceChangeRecord {
+    pub token_contract: ContractHash,
+    pub token_id: U256,
+    pub seller: Key,
+    pub old_price: U512,
+    pub new_price: U512,
+    pub expiration_time: Option<u64>,
+    pub timestamp: u64,
+}
+
+#[derive(CLTyped, ToBytes, FromBytes, Debug, PartialEq)]
+pub struct RentalData {
+    pub owner: Key,
+    pub price_per_day: U512,
+    pub max_days: u64,
+    pub user: Option<Key>,
+    pub expiration_time: Option<u64>,
+}
+
+#[derive(CLTyped, ToBytes, FromBytes, Debug, PartialEq)]
+pub struct CollectionStats {
+    pub active_listings: u64,
+    pub floor_price: Option<U512>,
+    pub active_auctions: u64,
+    pub total_sales: u64,
+    pub total_volume: U512,
+    pub daily_volume: U512,
+}
+
+#[derive(CLTyped, ToBytes, FromBytes, Debug, PartialEq)]
+pub struct SaleRecord {
+    pub token_contract: ContractHash,
+    pub token_id: U256,
+    pub seller: Key,
+    pub buyer: Key,
+    pub price: U512,
+    pub payment_asset: Option<ContractHash>,
+    pub timestamp: u64,
+    pub sale_type: u8,
+}
+
+fn get_marketplace_dictionary_value(
+    builder: &InMemoryWasmTestBuilder,
+    marketplace_hash: ContractHash,
+    dictionary_name: &str,
+    item_key: &str,
+) -> Option<CLValue> {
+    let dictionary_uref = builder
+        .get_contract(marketplace_hash)
+        .expect("should have marketplace contract")
+        .named_keys()
+        .get(dictionary_name)?
+        .into_uref()?;
+
+    builder
+        .query_dictionary_item(None, dictionary_uref, item_key)
+        .ok()
+        .and_then(|value| value.as_cl_value().cloned())
+}
+
+/// Reads `item_key` from one of the marketplace dictionaries, returning `None` when the dictionary
+/// or the item does not exist or holds a cleared (`None`) entry.
+pub fn get_marketplace_dictionary_item<T: CLTyped + FromBytes>(
+    builder: &InMemoryWasmTestBuilder,
+    marketplace_hash: ContractHash,
+    dictionary_name: &str,
+    item_key: &str,
+) -> Option<T> {
+    get_marketplace_dictionary_value(builder, marketplace_hash, dictionary_name, item_key)
+        .and_then(|value| value.into_t::<T>().ok())
+}
+
+fn get_marketplace_record(
+    builder: &InMemoryWasmTestBuilder,
+    marketplace_hash: ContractHash,
+    kind: RecordKind,
+    item_key: &str,
+) -> Option<Record> {
+    let value =
+        get_marketplace_dictionary_value(builder, marketplace_hash, kind.dictionary(), item_key)?;
+    decode_record(kind, value).expect("should decode marketplace record")
+}
+
+fn record_json(record: Record) -> Value {
+    match record {
+        Record::Listing(listing) => json!(listing),
+        Record::Offer(offer) => json!(offer),
+        Record::Auction(auction) => json!(auction),
+    }
+}
+
+/// Dumps the listings, auctions and offers on `tokens` and the balances of `parties` to JSON.
+/// Dictionaries cannot be enumerated, so only the records reachable from these are included;
+/// offers are looked up for every party, under both the current and the legacy key.
+pub fn dump_marketplace_state(
+    builder: &InMemoryWasmTestBuilder,
+    marketplace_hash: ContractHash,
+    tokens: &[(ContractHash, U256)],
+    parties: &[Key],
+) -> Value {
+    let mut listings = Map::new();
+    let mut auctions = Map::new();
+    let mut offers = Map::new();
+    let mut balances = Map::new();
+
+    for (cep47_hash, token_id) in tokens {
+        let token = format!("{}:{}", cep47_hash, token_id);
+        let key = listing_key(*cep47_hash, *token_id);
+
+        if let Some(listing) =
+            get_marketplace_record(builder, marketplace_hash, RecordKind::Listing, &key)
+        {
+            listings.insert(token.clone(), record_json(listing));
+        }
+        let auction = [RecordKind::Auction, RecordKind::LegacyAuction]
+            .into_iter()
+            .find_map(|kind| get_marketplace_record(builder, marketplace_hash, kind, &key));
+        if let Some(auction) = auction {
+            auctions.insert(token.clone(), record_json(auction));
+        }
+
+        for party in parties {
+            let mut keys = vec![offer_key(*cep47_hash, *token_id, party)];
+            if let Some(account_hash) = party.into_account() {
+                keys.push(legacy_offer_key(*cep47_hash, *token_id, account_hash));
+            }
+
+            let offer = keys.iter().find_map(|key| {
+                get_marketplace_record(builder, marketplace_hash, RecordKind::Offer, key)
+            });
+            if let Some(offer) = offer {
+                offers.insert(
+                    format!("{}:{}", token, party.to_formatted_string()),
+                    record_json(offer),
+                );
+            }
+        }
+    }
+
+    for party in parties {
+        let balance_key = party_key(party);
+        let balance: Option<U512> =
+            get_marketplace_dictionary_item(builder, marketplace_hash, "balances", &balance_key);
+        if let Some(balance) = balance {
+            balances.insert(party.to_formatted_string(), json!(balance));
+        }
+    }
+
+    json!({
+        "marketplace": marketplace_hash.to_formatted_string(),
+        "listings": listings,
+        "auctions": auctions,
+        "offers": offers,
+        "balances": balances,
+    })
+}
+
+/// Number of active listings the marketplace has indexed for a collection.
+pub fn get_collection_listing_count(
+    builder: &InMemoryWasmTestBuilder,
+    marketplace_hash: ContractHash,
+    cep47_hash: ContractHash,
+) -> u64 {
+    get_marketplace_dictionary_item(
+        builder,
+        marketplace_hash,
+        "collection_listings_len",
+        &hex::encode(cep47_hash.value()),
+    )
+    .unwrap_or_default()
+}
+
+/// Price and token at the root of the collection's floor heap, the cheapest listing still
+/// indexed. Expired listings stay in the heap until they are removed.
+pub fn get_floor_listing(
+    builder: &InMemoryWasmTestBuilder,
+    marketplace_hash: ContractHash,
+    cep47_hash: ContractHash,
+) -> Option<(U512, U256)> {
+    let len: u64 = get_marketplace_dictionary_item(
+        builder,
+        marketplace_hash,
+        "collection_floor_len",
+        &hex::encode(cep47_hash.value()),
+    )
+    .unwrap_or_default();
+
+    if len == 0 {
+        return None;
+    }
+
+    // Same key derivation as `stats::get_floor_key` in the contract
+    let root_key = hex::encode(blake2b(format!("{}_0", hex::encode(cep47_hash.value()))));
+    get_marketplace_dictionary_item(
+        builder,
+        marketplace_hash,
+        "collection_floor_items",
+        &root_key,
+    )
+}
+
+/// Stats stored by the last `stats-call.wasm` that `account` ran.
+pub fn get_collection_stats(
+    builder: &InMemoryWasmTestBuilder,
+    account: AccountHash,
+) -> CollectionStats {
+    let stats_key = *builder
+        .get_expected_account(account)
+        .named_keys()
+        .get("collection_stats")
+        .expect("should have collection stats");
+
+    builder
+        .query(None, stats_key, &[])
+        .expect("should query collection stats")
+        .as_cl_value()
+        .cloned()
+        .expect("should be a CLValue")
+        .into_t()
+        .expect("should decode collection stats")
+}
+
+/// Same key derivation as `stats::get_day_key` in the contract.
+pub fn collection_day_key(cep47_hash: ContractHash, day: u64) -> String {
+    let key_string = format!("{}_{}", hex::encode(cep47_hash.value()), day);
+    hex::encode(blake2b(key_string))
+}
+
+/// Same key derivation as `utils::key_to_str` in the contract.
+pub fn key_to_str(key: &Key) -> String {
+    hex::encode(blake2b(key.to_bytes().expect("should serialize key")))
+}
+
+/// Funds credited to `owner` in the marketplace ledger and not yet withdrawn.
+pub fn get_pending_balance(
+    builder: &InMemoryWasmTestBuilder,
+    marketplace_hash: ContractHash,
+    owner: Key,
+) -> U512 {
+    get_marketplace_dictionary_item(builder, marketplace_hash, "balances", &key_to_str(&owner))
+        .unwrap_or_default()
+}
+
+fn blake2b<T: AsRef<[u8]>>(data: T) -> [u8; 32] {
+    let mut result = [0u8; 32];
+    let mut hasher = VarBlake2b::new(32).expect("should create hasher");
+    hasher.update(data);
+    hasher.finalize_variable(|hash| result.copy_from_slice(hash));
+    result
+}
@@ -0,0 +1,129 @@
+//! Test-only stored contract standing in for a DAO treasury or vault. It owns NFTs under its
+//! package hash, lists them on the marketplace and withdraws its proceeds into its own purse.
+
+#![no_std]
+#![no_main]
+
+#[cfg(not(target_arch = "wasm32"))]
+compile_error!("target arch should be wasm32: compile with '--target wasm32-unknown-unknown'");
+
+extern crate alloc;
+
+use alloc::{string::String, vec};
+
+use casper_contract::{
+    contract_api::{runtime, storage, system},
+    unwrap_or_revert::UnwrapOrRevert,
+};
+use casper_types::{
+    bytesrepr::FromBytes, contracts::NamedKeys, runtime_args, CLType, CLTyped, ContractHash,
+    EntryPoint, EntryPointAccess, EntryPointType, EntryPoints, Key, Parameter, RuntimeArgs, U256,
+    U512,
+};
+
+const MARKETPLACE_KEY: &str = "marketplace";
+const MARKETPLACE_PACKAGE_KEY: &str = "marketplace_package";
+const VAULT_PURSE_KEY: &str = "vault_purse";
+
+fn read_named<T: CLTyped + FromBytes>(name: &str) -> T {
+    let uref = runtime::get_key(name)
+        .unwrap_or_revert()
+        .into_uref()
+        .unwrap_or_revert();
+    storage::read(uref).unwrap_or_revert().unwrap_or_revert()
+}
+
+fn marketplace_hash() -> ContractHash {
+    let marketplace_hash: String = read_named(MARKETPLACE_KEY);
+    ContractHash::from_formatted_str(&marketplace_hash).unwrap()
+}
+
+#[no_mangle]
+pub extern "C" fn list_token() {
+    let contract_hash: String = runtime::get_named_arg("contract_hash");
+    let token_id: U256 = runtime::get_named_arg("token_id");
+    let price: U512 = runtime::get_named_arg("price");
+    let marketplace_package_hash: Key = read_named(MARKETPLACE_PACKAGE_KEY);
+
+    runtime::call_contract::<()>(
+        ContractHash::from_formatted_str(&contract_hash).unwrap(),
+        "approve",
+        runtime_args! {
+            "spender" => marketplace_package_hash,
+            "token_ids" => vec![token_id],
+        },
+    );
+
+    runtime::call_contract::<()>(
+        marketplace_hash(),
+        "create_listing",
+        runtime_args! {
+            "contract_hash" => contract_hash,
+            "token_id" => token_id,
+            "price" => price,
+            "duration_minutes" => 0u64,
+        },
+    );
+}
+
+#[no_mangle]
+pub extern "C" fn withdraw() {
+    let to_purse: bool = runtime::get_named_arg("to_purse");
+
+    let args = if to_purse {
+        let vault_purse = runtime::get_key(VAULT_PURSE_KEY)
+            .unwrap_or_revert()
+            .into_uref()
+            .unwrap_or_revert();
+        runtime_args! { "target_purse" => vault_purse.into_add() }
+    } else {
+        runtime_args! {}
+    };
+
+    runtime::call_contract::<()>(marketplace_hash(), "withdraw", args);
+}
+
+#[no_mangle]
+pub extern "C" fn call() {
+    let marketplace_hash: String = runtime::get_named_arg("marketplace_hash");
+    let marketplace_package_hash: Key = runtime::get_named_arg("marketplace_package_hash");
+
+    let mut named_keys = NamedKeys::new();
+    named_keys.insert(
+        MARKETPLACE_KEY.into(),
+        storage::new_uref(marketplace_hash).into(),
+    );
+    named_keys.insert(
+        MARKETPLACE_PACKAGE_KEY.into(),
+        storage::new_uref(marketplace_package_hash).into(),
+    );
+    named_keys.insert(VAULT_PURSE_KEY.into(), system::create_purse().into());
+
+    let mut entry_points = EntryPoints::new();
+    entry_points.add_entry_point(EntryPoint::new(
+        "list_token",
+        vec![
+            Parameter::new("contract_hash", String::cl_type()),
+            Parameter::new("token_id", U256::cl_type()),
+            Parameter::new("price", U512::cl_type()),
+        ],
+        CLType::Unit,
+        EntryPointAccess::Public,
+        EntryPointType::Contract,
+    ));
+    entry_points.add_entry_point(EntryPoint::new(
+        "withdraw",
+        vec![Parameter::new("to_purse", bool::cl_type())],
+        CLType::Unit,
+        EntryPointAccess::Public,
+        EntryPointType::Contract,
+    ));
+
+    let (contract_hash, _version) = storage::new_contract(
+        entry_points,
+        Some(named_keys),
+        Some("vault_package_hash".into()),
+        Some("vault_access_uref".into()),
+    );
+    runtime::put_key("vault_contract_hash", contract_hash.into());
+}